@@ -0,0 +1,148 @@
+use serde::Serialize;
+use statrs::distribution::{Binomial, ChiSquared, ContinuousCDF, DiscreteCDF};
+
+//Kupiec test size: the model is rejected when the LR p-value drops below this
+const KUPIEC_SIGNIFICANCE: f64 = 0.05;
+
+//Basel traffic-light cut-offs on the cumulative binomial probability of the failure count
+const YELLOW_ZONE_PROBABILITY: f64 = 0.95;
+const RED_ZONE_PROBABILITY: f64 = 0.9999;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TrafficLight {
+    Green,
+    Yellow,
+    Red,
+}
+
+impl TrafficLight {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrafficLight::Green => "Green",
+            TrafficLight::Yellow => "Yellow",
+            TrafficLight::Red => "Red",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestResult {
+    pub observations: usize,
+    pub failures: usize,
+    pub failure_rate: f64,
+    //1 - confidence, the breach rate a calibrated VaR should show
+    pub expected_rate: f64,
+    pub lr_statistic: f64,
+    pub p_value: f64,
+    pub passed: bool,
+    pub zone: TrafficLight,
+}
+
+//Kupiec proportion-of-failures test. predicted_var is a positive fractional loss (as in
+//SimStats::var95) and returns are simple returns over the same horizon; a breach is a return
+//below -predicted_var. LR = -2 ln[(1-p)^(T-x) p^x / (1-x/T)^(T-x) (x/T)^x] ~ chi2(1).
+//With no observations there is no evidence against the model: LR 0, p-value 1, green
+pub fn backtest_var(historical_returns: &[f64], predicted_var: f64, confidence: f64) -> BacktestResult {
+    let observations = historical_returns.len();
+    let failures = historical_returns.iter().filter(|&&r| r < -predicted_var).count();
+    let expected_rate = (1.0 - confidence).clamp(1e-12, 1.0 - 1e-12);
+    let failure_rate = if observations == 0 { 0.0 } else { failures as f64 / observations as f64 };
+
+    //0 * ln(0) terms are taken as 0, so no failures (or all failures) still give a finite LR
+    let log_likelihood = |p: f64| {
+        let hits = if failures == 0 { 0.0 } else { failures as f64 * p.ln() };
+        let misses = if failures == observations { 0.0 } else { (observations - failures) as f64 * (1.0 - p).ln() };
+        hits + misses
+    };
+    let lr_statistic = if observations == 0 {
+        0.0
+    } else {
+        (-2.0 * (log_likelihood(expected_rate) - log_likelihood(failure_rate))).max(0.0)
+    };
+    let p_value = ChiSquared::new(1.0).map_or(1.0, |chi2| 1.0 - chi2.cdf(lr_statistic));
+
+    let zone = match Binomial::new(expected_rate, observations as u64) {
+        Ok(binomial) if observations > 0 => {
+            let cumulative = binomial.cdf(failures as u64);
+            if cumulative < YELLOW_ZONE_PROBABILITY {
+                TrafficLight::Green
+            } else if cumulative < RED_ZONE_PROBABILITY {
+                TrafficLight::Yellow
+            } else {
+                TrafficLight::Red
+            }
+        }
+        _ => TrafficLight::Green,
+    };
+
+    BacktestResult { observations, failures, failure_rate, expected_rate, lr_statistic, p_value, passed: p_value >= KUPIEC_SIGNIFICANCE, zone }
+}
+
+//non-overlapping simple returns over horizon bars from log returns at the same bar frequency, oldest first.
+//Overlapping windows would make breaches dependent, which the Kupiec test assumes away
+pub fn horizon_returns(log_returns: &[f64], horizon: usize) -> Vec<f64> {
+    let horizon = horizon.max(1);
+    log_returns.chunks_exact(horizon)
+        .map(|window| window.iter().sum::<f64>().exp() - 1.0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //observations returns of -10%, failures of them below a 5% VaR
+    fn returns_with_failures(observations: usize, failures: usize) -> Vec<f64> {
+        (0..observations).map(|i| if i < failures { -0.1 } else { 0.01 }).collect()
+    }
+
+    #[test]
+    fn counts_returns_below_the_var_as_failures() {
+        let mut returns = returns_with_failures(100, 7);
+        //a loss exactly at the VaR is not a breach
+        returns[50] = -0.05;
+        let result = backtest_var(&returns, 0.05, 0.95);
+        assert_eq!((result.observations, result.failures), (100, 7));
+        assert!((result.failure_rate - 0.07).abs() < 1e-12);
+        assert!((result.expected_rate - 0.05).abs() < 1e-12);
+    }
+
+    #[test]
+    fn failure_rate_at_the_expected_rate_gives_zero_lr() {
+        let result = backtest_var(&returns_with_failures(200, 10), 0.05, 0.95);
+        assert!(result.lr_statistic.abs() < 1e-9, "LR {}", result.lr_statistic);
+        assert!((result.p_value - 1.0).abs() < 1e-9);
+        assert!(result.passed);
+        assert_eq!(result.zone, TrafficLight::Green);
+    }
+
+    #[test]
+    fn traffic_light_zones_follow_the_basel_cut_offs() {
+        //250 days at 99%: 0-4 failures green, 5-9 yellow, 10 or more red
+        let zone = |failures| backtest_var(&returns_with_failures(250, failures), 0.05, 0.99).zone;
+        assert_eq!((zone(0), zone(4)), (TrafficLight::Green, TrafficLight::Green));
+        assert_eq!((zone(5), zone(9)), (TrafficLight::Yellow, TrafficLight::Yellow));
+        assert_eq!((zone(10), zone(30)), (TrafficLight::Red, TrafficLight::Red));
+        assert!(!backtest_var(&returns_with_failures(250, 10), 0.05, 0.99).passed);
+    }
+
+    #[test]
+    fn no_observations_is_no_evidence_against_the_model() {
+        let result = backtest_var(&[], 0.05, 0.99);
+        assert_eq!((result.observations, result.failures), (0, 0));
+        assert_eq!((result.lr_statistic, result.p_value), (0.0, 1.0));
+        assert!(result.passed);
+        assert_eq!(result.zone, TrafficLight::Green);
+    }
+
+    #[test]
+    fn horizon_returns_compound_whole_windows_and_drop_the_rest() {
+        let log_returns = [0.01, 0.02, -0.03, 0.05, 0.05, 0.1, 0.2];
+        let returns = horizon_returns(&log_returns, 3);
+        assert_eq!(returns.len(), 2);
+        assert!(returns[0].abs() < 1e-12);
+        assert!((returns[1] - (0.2_f64.exp() - 1.0)).abs() < 1e-12);
+        assert_eq!(horizon_returns(&log_returns, 0).len(), log_returns.len());
+        assert!(horizon_returns(&log_returns, 8).is_empty());
+    }
+}
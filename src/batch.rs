@@ -0,0 +1,95 @@
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+
+use crate::core_sim::{CoreSimParams, RunControl, SimStats, estimate_paramaters, resolve_seed, simulate_stats};
+use crate::data_io::{Frequency, StockRecord, get_ticker_info};
+
+//fewest log returns a ticker needs to be screened, shorter histories give meaningless estimates
+pub const MIN_TICKER_RETURNS: usize = 30;
+
+//what a screening run needs from one ticker: its last close as the initial price and its returns
+#[derive(Debug, Clone)]
+pub struct TickerHistory {
+    pub last_price: f64,
+    pub log_returns: Vec<f64>,
+}
+
+//every ticker's history at the given frequency, keyed (and so screened) in ticker order
+pub fn ticker_histories(all_data: &[StockRecord], tickers: &[String], frequency: Frequency) -> BTreeMap<String, TickerHistory> {
+    tickers
+        .iter()
+        .map(|ticker| {
+            let (_, log_returns) = get_ticker_info(all_data, ticker, frequency);
+            let last_price = all_data.iter().rfind(|r| &r.ticker == ticker).map_or(0.0, |r| r.close);
+            (ticker.clone(), TickerHistory { last_price, log_returns })
+        })
+        .collect()
+}
+
+//state of a screening run. Tickers already in results or skipped are not run again, so after a
+//cancel the same BatchProgress passed back in resumes with the next ticker
+#[derive(Debug, Clone, Default)]
+pub struct BatchProgress {
+    pub results: Vec<(String, SimStats)>,
+    //tickers left out, with the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+impl BatchProgress {
+    fn is_done(&self, ticker: &str) -> bool {
+        self.results.iter().any(|(t, _)| t == ticker) || self.skipped.iter().any(|(t, _)| t == ticker)
+    }
+}
+
+//screens every ticker under base_params: mu and sigma are re-estimated from each ticker's returns
+//and its last close is the initial price, every other model parameter comes from base_params.
+//All tickers share one resolved seed. control.progress gets the share of tickers finished; a
+//ticker whose run fails is skipped rather than stopping the batch
+pub fn run_all_tickers(data_map: &BTreeMap<String, TickerHistory>, base_params: &CoreSimParams, progress: &mut BatchProgress, control: &RunControl) -> Result<()> {
+    let base = resolve_seed(base_params.clone());
+    let silent = RunControl { progress: None, cancel: control.cancel.clone() };
+    let cancelled = |progress: &BatchProgress| {
+        anyhow!("Batch cancelled after {} of {} tickers", progress.results.len() + progress.skipped.len(), data_map.len())
+    };
+
+    for (ticker, history) in data_map {
+        if progress.is_done(ticker) {
+            continue;
+        }
+        if control.cancel.load(Ordering::Relaxed) {
+            return Err(cancelled(progress));
+        }
+
+        if history.log_returns.len() < MIN_TICKER_RETURNS {
+            let reason = format!("{} returns, need at least {}", history.log_returns.len(), MIN_TICKER_RETURNS);
+            progress.skipped.push((ticker.clone(), reason));
+        } else if history.last_price <= 0.0 {
+            progress.skipped.push((ticker.clone(), format!("last close {} is not positive", history.last_price)));
+        } else {
+            let run = estimate_paramaters(&history.log_returns).and_then(|(mu, sigma)| {
+                //a target price or diagnosed path belongs to one ticker's setup, not to a screen
+                let params = CoreSimParams {
+                    initial_price: history.last_price as f32,
+                    mu: mu as f32,
+                    sigma: sigma as f32,
+                    target_price: 0.0,
+                    target_mean_price: 0.0,
+                    record_diagnostics: false,
+                    ..base.clone()
+                };
+                simulate_stats(params, &history.log_returns, &silent)
+            });
+            match run {
+                Ok(stats) => progress.results.push((ticker.clone(), stats)),
+                //a cancel mid-run is not a failed ticker, it is picked up again on resume
+                Err(_) if control.cancel.load(Ordering::Relaxed) => return Err(cancelled(progress)),
+                Err(e) => progress.skipped.push((ticker.clone(), e.to_string())),
+            }
+        }
+
+        let done = progress.results.len() + progress.skipped.len();
+        control.report(done - 1, done, data_map.len());
+    }
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use anyhow::{Result, anyhow};
+use std::{fs, path::{Path, PathBuf}, sync::Arc, time::Instant};
+
+use monte_carlo::batch::{BatchProgress, run_all_tickers, ticker_histories};
+use monte_carlo::config::{load_config, validate_config};
+use monte_carlo::core_sim::{CoreSimParams, RunControl, run_simulation};
+use monte_carlo::data_io::{Frequency, get_ticker_info, load_records_streaming, write_ticker_ranking_csv};
+
+const USAGE: &str = "Usage: monte_carlo --config <setup.json|yaml> [--out <prefix>] [--data <prices.csv> [--ticker <name> | --all-tickers]]
+
+Runs the saved setup without opening the window and writes
+<prefix>_summary.csv, <prefix>_paths.png and <prefix>_hist.png (default prefix: simulation).
+The paths chart is skipped when the setup has \"store_paths\": false, and a setup with
+\"diagnostic_path\" also writes that path's steps to <prefix>_diagnostics.csv.
+--data supplies historical returns, needed for the Bootstrap model.
+--all-tickers screens every ticker in --data instead, with mu, sigma and the initial price taken
+from each ticker's history, and writes <prefix>_tickers.csv ranked by VaR.";
+
+struct CliArgs {
+    config: PathBuf,
+    out: String,
+    data: Option<PathBuf>,
+    ticker: Option<String>,
+    all_tickers: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<CliArgs> {
+    let mut config = None;
+    let mut out = "simulation".to_string();
+    let mut data = None;
+    let mut ticker = None;
+    let mut all_tickers = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().cloned().ok_or_else(|| anyhow!("{} needs a value\n\n{}", arg, USAGE));
+        match arg.as_str() {
+            "--config" => config = Some(PathBuf::from(value()?)),
+            "--out" => out = value()?,
+            "--data" => data = Some(PathBuf::from(value()?)),
+            "--ticker" => ticker = Some(value()?),
+            "--all-tickers" => all_tickers = true,
+            "-h" | "--help" => return Err(anyhow!("{}", USAGE)),
+            other => return Err(anyhow!("Unknown argument '{}'\n\n{}", other, USAGE)),
+        }
+    }
+
+    if all_tickers && (data.is_none() || ticker.is_some()) {
+        return Err(anyhow!("--all-tickers needs --data and no --ticker\n\n{}", USAGE));
+    }
+
+    Ok(CliArgs {
+        config: config.ok_or_else(|| anyhow!("--config is required\n\n{}", USAGE))?,
+        out,
+        data,
+        ticker,
+        all_tickers,
+    })
+}
+
+//headless run: load setup, simulate, write summary csv and both charts next to the out prefix
+pub fn run(args: &[String]) -> Result<()> {
+    let args = parse_args(args)?;
+
+    let config = load_config(&args.config)?;
+    validate_config(&config)?;
+    let params = config.to_sim_params();
+
+    if args.all_tickers && let Some(path) = &args.data {
+        return screen_tickers(path, &params, &args.out);
+    }
+
+    let hist_log_returns = match &args.data {
+        Some(path) => {
+            //with a ticker given, every other symbol is dropped while reading
+            let filter = args.ticker.clone().map(|t| vec![t]);
+            let (records, tickers) = load_records_streaming(path.clone(), filter.as_deref(), |rows| {
+                eprintln!("Read {} rows of {:?}", rows, path);
+            })?;
+            let ticker = match args.ticker {
+                Some(ticker) => ticker,
+                None => tickers.first().cloned().ok_or_else(|| anyhow!("No tickers in {:?}", path))?,
+            };
+            let (info, log_returns) = get_ticker_info(&records, &ticker, Frequency::Daily);
+            println!("{}", info);
+            log_returns
+        }
+        None => Vec::new(),
+    };
+    if hist_log_returns.is_empty() && params.model_type == "Bootstrap" {
+        return Err(anyhow!("Bootstrap model needs historical data, pass --data <prices.csv>"));
+    }
+
+    let start_time = Instant::now();
+    let output = run_simulation(params, hist_log_returns)?;
+    let (stats, paths_chart, (hist_buf, hist_w, hist_h)) = (output.stats, output.paths_chart, output.hist_chart);
+    let diagnostics = output.diagnostics;
+    let exec_time = format!("{} ms", start_time.elapsed().as_millis());
+
+    let summary_path = PathBuf::from(format!("{}_summary.csv", args.out));
+    let paths_path = PathBuf::from(format!("{}_paths.png", args.out));
+    let hist_path = PathBuf::from(format!("{}_hist.png", args.out));
+
+    fs::write(&summary_path, crate::summary_csv(&stats, &exec_time))?;
+    crate::encode_and_save_png(&hist_path, &hist_buf, hist_w, hist_h)?;
+    if !diagnostics.is_empty() {
+        let diagnostics_path = PathBuf::from(format!("{}_diagnostics.csv", args.out));
+        monte_carlo::data_io::write_diagnostics_csv(&diagnostics_path, &diagnostics)?;
+        println!("Wrote {:?}", diagnostics_path);
+    }
+    //store_paths: false leaves nothing to draw, so no paths chart is written
+    if let Some((paths_buf, paths_w, paths_h)) = paths_chart {
+        crate::encode_and_save_png(&paths_path, &paths_buf, paths_w, paths_h)?;
+        println!("Simulation finished in {}, wrote {:?}, {:?} and {:?}", exec_time, summary_path, paths_path, hist_path);
+    } else {
+        println!("Simulation finished in {}, wrote {:?} and {:?}", exec_time, summary_path, hist_path);
+    }
+    Ok(())
+}
+
+//--all-tickers: every ticker in the data file under the setup, one ranked CSV
+fn screen_tickers(path: &Path, params: &CoreSimParams, out: &str) -> Result<()> {
+    let (records, tickers) = load_records_streaming(path.to_path_buf(), None, |rows| {
+        eprintln!("Read {} rows of {:?}", rows, path);
+    })?;
+    let histories = ticker_histories(&records, &tickers, Frequency::Daily);
+
+    let start_time = Instant::now();
+    let control = RunControl {
+        progress: Some(Arc::new(|fraction| eprintln!("Screened {:.0}% of tickers", fraction * 100.0))),
+        ..RunControl::default()
+    };
+    let mut progress = BatchProgress::default();
+    run_all_tickers(&histories, params, &mut progress, &control)?;
+    for (ticker, reason) in &progress.skipped {
+        eprintln!("Skipped {}: {}", ticker, reason);
+    }
+
+    let ranking_path = PathBuf::from(format!("{}_tickers.csv", out));
+    write_ticker_ranking_csv(&ranking_path, &progress.results)?;
+    println!("Screened {} tickers in {} ms ({} skipped), wrote {:?}", progress.results.len(), start_time.elapsed().as_millis(), progress.skipped.len(), ranking_path);
+    Ok(())
+}
@@ -1,20 +1,85 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::core_sim::ModelParams;
+use crate::core_sim::{CoreSimParams, ModelParams, OutputMode, ShockDistribution, StepFrequency, VarianceReduction};
+
+/// Schema version written by this build, bump it and extend `migrate_config` when
+/// `SimConfig` changes shape
+pub const CONFIG_VERSION: u32 = 2;
 
 /// Configuration for a single simulation
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct SimConfig {
+    /// Schema version, configs saved before versioning load as 1
+    #[serde(default = "default_version")]
+    pub version: u32,
+
     // Simulation parameters
     pub initial_price: f64,
     pub horizon: usize,
     pub num_paths: usize,
     pub seed: u64,
+    /// Draw a fresh seed from system entropy for every run, the one used is reported in the stats
+    #[serde(default)]
+    pub auto_seed: bool,
     pub use_antithetic: bool,
+    #[serde(default)]
+    pub use_quasi_random: bool,
+    #[serde(default = "default_variance_reduction")]
+    pub variance_reduction: String,  // "None", "ControlVariate", "Stratified", "ImportanceSampling" (plain GBM only)
+    #[serde(default = "default_shock_distribution")]
+    pub shock_distribution: String,  // "Normal", "StudentT" (GBM and the jump models only)
+    #[serde(default = "default_student_t_df")]
+    pub student_t_df: f64,
     pub dt: f64,
+    /// "PerStep" steps by dt with per-step rates. "Daily", "Weekly" or "Monthly" step 1/252, 1/52
+    /// or 1/12 of a year instead, dt is then ignored and mu, sigma and every other rate are annualized
+    #[serde(default = "default_step_frequency")]
+    pub step_frequency: String,
+    #[serde(default = "default_confidence_level")]
+    pub confidence_level: f64,
+    /// Per-step risk-free rate for the Sharpe and Sortino ratios
+    #[serde(default)]
+    pub risk_free_rate: f64,
+    /// Price whose exceedance probability is reported, omitted when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_price: Option<f64>,
+    /// Expected terminal price to calibrate mu to, overriding the model's own mu. Only for
+    /// models whose mean is S0·e^{μT}: GBM (without a mu schedule), Heston, RegimeSwitching, LocalVol
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_mean_price: Option<f64>,
+    /// Stress scenario: every volatility parameter is multiplied by this factor before the paths
+    /// are generated (variances by its square), omitted for no stress
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stress_factor_sigma: Option<f64>,
+    /// Stress scenario: added to mu before the paths are generated, omitted for no shift
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stress_shift_mu: Option<f64>,
+    /// Keep every full path for the path chart, false only keeps terminal prices and drawdowns
+    #[serde(default = "default_store_paths")]
+    pub store_paths: bool,
+    /// Streaming P-square quantiles instead of sorting, only used from 10k paths up
+    #[serde(default)]
+    pub approximate_quantiles: bool,
+    /// "Price" or "Return": whether mean, spread and percentiles are reported in price units or
+    /// as returns on the initial price (VaR and CVaR are returns either way)
+    #[serde(default = "default_output_mode")]
+    pub output_mode: String,
+    /// One RNG keystream per chunk of paths instead of per path: faster, still seeded, but
+    /// results differ from the default mode and single paths can't be regenerated alone
+    #[serde(default)]
+    pub fast_mode: bool,
+    /// Zero every random shock so each path is the pure drift trajectory, a preview for
+    /// teaching and debugging. Antithetic, quasi-random and stratified draws are bypassed
+    #[serde(default)]
+    pub deterministic: bool,
+    /// Index of a path to replay with its per-step variance, drift and shock recorded,
+    /// omitted for no diagnostics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnostic_path: Option<usize>,
     
     // Model configuration
     pub model_type: String,  // "GBM", "Bootstrap", "MeanReversion", etc.
@@ -29,24 +94,94 @@ pub struct SimConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub jump_diffusion_params: Option<JumpDiffusionParams>,
     
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hawkes_jump_params: Option<HawkesJumpParams>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub garch_params: Option<GARCHParams>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heston_params: Option<HestonParams>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cir_params: Option<CIRParams>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vasicek_params: Option<VasicekParams>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sabr_params: Option<SABRParams>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_vol_params: Option<LocalVolParams>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regime_switching_params: Option<RegimeSwitchingParams>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bootstrap_params: Option<BootstrapParams>,
+    
+    // Optional European option priced on the terminal prices
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub option_params: Option<EuropeanOptionParams>,
+}
+
+fn default_version() -> u32 {
+    1
+}
+
+fn default_confidence_level() -> f64 {
+    0.95
+}
+
+fn default_store_paths() -> bool {
+    true
+}
+
+fn default_greek_bump() -> f64 {
+    0.01
+}
+
+fn default_variance_reduction() -> String {
+    "None".to_string()
+}
+
+fn default_shock_distribution() -> String {
+    "Normal".to_string()
+}
+
+fn default_output_mode() -> String {
+    "Price".to_string()
+}
+
+fn default_step_frequency() -> String {
+    "PerStep".to_string()
+}
+
+fn default_student_t_df() -> f64 {
+    5.0
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct GBMParams {
     pub mu: f64,
     pub sigma: f64,
+    /// Per-step drift, GBM only. Steps past its end fall back to `mu`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mu_schedule: Option<Vec<f64>>,
+    /// Per-step volatility, GBM only. Steps past its end fall back to `sigma`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sigma_schedule: Option<Vec<f64>>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct MeanReversionParams {
     pub theta: f64,
     pub mu_long_term: f64,
     pub sigma: f64,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct JumpDiffusionParams {
     pub mu: f64,
     pub sigma: f64,
@@ -55,14 +190,247 @@ pub struct JumpDiffusionParams {
     pub sigma_j: f64,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// Jump diffusion whose jump intensity jumps by `excitation` after every jump and decays back
+/// to the baseline `lambda` at rate `decay`. Needs excitation < decay to stay stationary
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HawkesJumpParams {
+    pub mu: f64,
+    pub sigma: f64,
+    pub lambda: f64,
+    pub excitation: f64,
+    pub decay: f64,
+    pub mu_j: f64,
+    pub sigma_j: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct GARCHParams {
     pub omega: f64,
     pub alpha: f64,
     pub beta: f64,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HestonParams {
+    pub mu: f64,
+    pub v0: f64,
+    pub kappa: f64,
+    pub theta: f64,
+    pub xi: f64,
+    pub rho: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CIRParams {
+    pub kappa: f64,
+    pub theta: f64,
+    pub sigma: f64,
+}
+
+/// Short-rate model, initial_price is read as the initial rate
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct VasicekParams {
+    pub kappa: f64,
+    pub theta: f64,
+    pub sigma: f64,
+}
+
+/// Stochastic-alpha-beta-rho forward model, initial_price is read as the forward f0
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SABRParams {
+    pub alpha: f64,
+    pub beta: f64,
+    pub rho: f64,
+    pub nu: f64,
+}
+
+/// GBM drift with volatility read off a σ(S, t) grid. The surface file is a CSV whose header
+/// is a label cell then the times (in dt units), followed by one row per spot level:
+/// `spot,0.0,0.5` / `80,0.32,0.30` / `100,0.25,0.24`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LocalVolParams {
+    pub mu: f64,
+    pub surface_file: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RegimeSwitchingParams {
+    pub mu: f64,
+    pub sigma_low: f64,
+    pub sigma_high: f64,
+    pub p_low_to_high: f64,
+    pub p_high_to_low: f64,
+}
+
+/// Moving-block bootstrap settings, `block_size` 1 resamples single days
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BootstrapParams {
+    pub block_size: usize,
+    /// Subtract the sample mean so only the volatility is resampled
+    #[serde(default)]
+    pub demean: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EuropeanOptionParams {
+    pub strike: f64,
+    pub is_call: bool,
+    pub discount_rate: f64,
+    #[serde(default)]
+    pub compute_greeks: bool,
+    #[serde(default = "default_greek_bump")]
+    pub greek_bump: f64,
+    // Knock-out barrier on the same strike/type/rate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub barrier_params: Option<BarrierParams>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BarrierParams {
+    pub barrier: f64,
+    pub is_up_and_out: bool,
+}
+
 impl SimConfig {
+    /// Build the simulation input from this config, as the GUI would after loading it.
+    /// Chart options take the GUI defaults since they aren't part of the saved setup
+    pub fn to_sim_params(&self) -> CoreSimParams {
+        let mut params = CoreSimParams {
+            initial_price: self.initial_price as f32,
+            horizon: self.horizon as i32,
+            num_paths: self.num_paths as i32,
+            seed: self.seed as i32,
+            auto_seed: self.auto_seed,
+            use_antithetic: self.use_antithetic,
+            use_quasi_random: self.use_quasi_random,
+            variance_reduction: match self.variance_reduction.as_str() {
+                "ControlVariate" => VarianceReduction::ControlVariate,
+                "Stratified" => VarianceReduction::Stratified,
+                "ImportanceSampling" => VarianceReduction::ImportanceSampling,
+                _ => VarianceReduction::None,
+            },
+            shock_distribution: match self.shock_distribution.as_str() {
+                "StudentT" => ShockDistribution::StudentT,
+                _ => ShockDistribution::Normal,
+            },
+            student_t_df: self.student_t_df as f32,
+            dt: (self.dt.round() as i32).max(1),
+            step_frequency: match self.step_frequency.as_str() {
+                "Daily" => StepFrequency::Daily,
+                "Weekly" => StepFrequency::Weekly,
+                "Monthly" => StepFrequency::Monthly,
+                _ => StepFrequency::PerStep,
+            },
+            model_type: self.model_type.clone(),
+            confidence_level: self.confidence_level as f32,
+            risk_free_rate: self.risk_free_rate as f32,
+            target_price: self.target_price.unwrap_or(0.0) as f32,
+            target_mean_price: self.target_mean_price.unwrap_or(0.0) as f32,
+            stress_factor_sigma: self.stress_factor_sigma.unwrap_or(1.0) as f32,
+            stress_shift_mu: self.stress_shift_mu.unwrap_or(0.0) as f32,
+            greek_bump: default_greek_bump() as f32,
+            show_bands: true,
+            overlay_density: true,
+            max_displayed_paths: 50,
+            store_paths: self.store_paths,
+            approximate_quantiles: self.approximate_quantiles,
+            output_mode: match self.output_mode.as_str() {
+                "Return" => OutputMode::Return,
+                _ => OutputMode::Price,
+            },
+            fast_mode: self.fast_mode,
+            deterministic: self.deterministic,
+            record_diagnostics: self.diagnostic_path.is_some(),
+            diagnostic_path: self.diagnostic_path.unwrap_or(0) as i32,
+            block_size: self.bootstrap_params.as_ref().map_or(1, |b| b.block_size) as i32,
+            bootstrap_demean: self.bootstrap_params.as_ref().is_some_and(|b| b.demean),
+            ..Default::default()
+        };
+
+        if let Some(gbm) = &self.gbm_params {
+            params.mu = gbm.mu as f32;
+            params.sigma = gbm.sigma as f32;
+            params.mu_schedule = gbm.mu_schedule.as_deref().map(format_schedule).unwrap_or_default();
+            params.sigma_schedule = gbm.sigma_schedule.as_deref().map(format_schedule).unwrap_or_default();
+        }
+        if let Some(mr) = &self.mean_reversion_params {
+            params.theta = mr.theta as f32;
+            params.mu_long_term = mr.mu_long_term as f32;
+            params.sigma = mr.sigma as f32;
+        }
+        if let Some(jd) = &self.jump_diffusion_params {
+            params.mu = jd.mu as f32;
+            params.sigma = jd.sigma as f32;
+            params.lambda = jd.lambda as f32;
+            params.mu_j = jd.mu_j as f32;
+            params.sigma_j = jd.sigma_j as f32;
+        }
+        if let Some(hawkes) = &self.hawkes_jump_params {
+            params.mu = hawkes.mu as f32;
+            params.sigma = hawkes.sigma as f32;
+            params.lambda = hawkes.lambda as f32;
+            params.hawkes_excitation = hawkes.excitation as f32;
+            params.hawkes_decay = hawkes.decay as f32;
+            params.mu_j = hawkes.mu_j as f32;
+            params.sigma_j = hawkes.sigma_j as f32;
+        }
+        if let Some(garch) = &self.garch_params {
+            params.omega = garch.omega as f32;
+            params.alpha = garch.alpha as f32;
+            params.beta = garch.beta as f32;
+        }
+        if let Some(heston) = &self.heston_params {
+            params.mu = heston.mu as f32;
+            params.v0 = heston.v0 as f32;
+            params.kappa = heston.kappa as f32;
+            params.theta_v = heston.theta as f32;
+            params.xi = heston.xi as f32;
+            params.rho = heston.rho as f32;
+        }
+        if let Some(cir) = &self.cir_params {
+            params.cir_kappa = cir.kappa as f32;
+            params.cir_theta = cir.theta as f32;
+            params.sigma = cir.sigma as f32;
+        }
+        if let Some(vasicek) = &self.vasicek_params {
+            params.vasicek_kappa = vasicek.kappa as f32;
+            params.vasicek_theta = vasicek.theta as f32;
+            params.sigma = vasicek.sigma as f32;
+        }
+        if let Some(sabr) = &self.sabr_params {
+            params.sabr_alpha = sabr.alpha as f32;
+            params.sabr_beta = sabr.beta as f32;
+            params.sabr_rho = sabr.rho as f32;
+            params.sabr_nu = sabr.nu as f32;
+        }
+        if let Some(local_vol) = &self.local_vol_params {
+            params.mu = local_vol.mu as f32;
+            params.local_vol_file = local_vol.surface_file.clone();
+        }
+        if let Some(rs) = &self.regime_switching_params {
+            params.mu = rs.mu as f32;
+            params.sigma_low = rs.sigma_low as f32;
+            params.sigma_high = rs.sigma_high as f32;
+            params.p_low_to_high = rs.p_low_to_high as f32;
+            params.p_high_to_low = rs.p_high_to_low as f32;
+        }
+        if let Some(option) = &self.option_params {
+            params.price_option = true;
+            params.strike = option.strike as f32;
+            params.is_call = option.is_call;
+            params.discount_rate = option.discount_rate as f32;
+            params.compute_greeks = option.compute_greeks;
+            params.greek_bump = option.greek_bump as f32;
+            if let Some(barrier) = &option.barrier_params {
+                params.price_barrier = true;
+                params.barrier = barrier.barrier as f32;
+                params.is_up_and_out = barrier.is_up_and_out;
+            }
+        }
+
+        params
+    }
+
     /// Convert to ModelParams enum
     pub fn to_model_params(&self) -> Result<ModelParams> {
         match self.model_type.as_str() {
@@ -71,12 +439,17 @@ impl SimConfig {
                     Ok(ModelParams::GBM {
                         mu: params.mu,
                         sigma: params.sigma,
+                        mu_schedule: params.mu_schedule.clone(),
+                        sigma_schedule: params.sigma_schedule.clone(),
                     })
                 } else {
                     Err(anyhow::anyhow!("GBM parameters not found"))
                 }
             }
-            "Bootstrap" => Ok(ModelParams::Bootstrap {}),
+            "Bootstrap" => Ok(ModelParams::Bootstrap {
+                block_size: self.bootstrap_params.as_ref().map_or(1, |b| b.block_size),
+                demean: self.bootstrap_params.as_ref().is_some_and(|b| b.demean),
+            }),
             "MeanReversion" => {
                 if let Some(ref params) = self.mean_reversion_params {
                     Ok(ModelParams::MeanReversion {
@@ -101,6 +474,21 @@ impl SimConfig {
                     Err(anyhow::anyhow!("Jump Diffusion parameters not found"))
                 }
             }
+            "HawkesJump" => {
+                if let Some(ref params) = self.hawkes_jump_params {
+                    Ok(ModelParams::HawkesJump {
+                        mu: params.mu,
+                        sigma: params.sigma,
+                        lambda: params.lambda,
+                        excitation: params.excitation,
+                        decay: params.decay,
+                        mu_j: params.mu_j,
+                        sigma_j: params.sigma_j,
+                    })
+                } else {
+                    Err(anyhow::anyhow!("Hawkes Jump parameters not found"))
+                }
+            }
             "GARCH" => {
                 if let Some(ref params) = self.garch_params {
                     Ok(ModelParams::GARCH {
@@ -112,29 +500,154 @@ impl SimConfig {
                     Err(anyhow::anyhow!("GARCH parameters not found"))
                 }
             }
+            "Heston" => {
+                if let Some(ref params) = self.heston_params {
+                    Ok(ModelParams::Heston {
+                        mu: params.mu,
+                        v0: params.v0,
+                        kappa: params.kappa,
+                        theta: params.theta,
+                        xi: params.xi,
+                        rho: params.rho,
+                    })
+                } else {
+                    Err(anyhow::anyhow!("Heston parameters not found"))
+                }
+            }
+            "CIR" => {
+                if let Some(ref params) = self.cir_params {
+                    Ok(ModelParams::CIR {
+                        kappa: params.kappa,
+                        theta: params.theta,
+                        sigma: params.sigma,
+                    })
+                } else {
+                    Err(anyhow::anyhow!("CIR parameters not found"))
+                }
+            }
+            "Vasicek" => {
+                if let Some(ref params) = self.vasicek_params {
+                    Ok(ModelParams::Vasicek {
+                        kappa: params.kappa,
+                        theta: params.theta,
+                        sigma: params.sigma,
+                    })
+                } else {
+                    Err(anyhow::anyhow!("Vasicek parameters not found"))
+                }
+            }
+            "SABR" => {
+                if let Some(ref params) = self.sabr_params {
+                    Ok(ModelParams::SABR {
+                        alpha: params.alpha,
+                        beta: params.beta,
+                        rho: params.rho,
+                        nu: params.nu,
+                    })
+                } else {
+                    Err(anyhow::anyhow!("SABR parameters not found"))
+                }
+            }
+            "LocalVol" => {
+                if let Some(ref params) = self.local_vol_params {
+                    Ok(ModelParams::LocalVol {
+                        mu: params.mu,
+                        surface: crate::data_io::load_vol_surface(Path::new(&params.surface_file))?,
+                    })
+                } else {
+                    Err(anyhow::anyhow!("Local vol parameters not found"))
+                }
+            }
+            "RegimeSwitching" => {
+                if let Some(ref params) = self.regime_switching_params {
+                    Ok(ModelParams::RegimeSwitching {
+                        mu: params.mu,
+                        sigma_low: params.sigma_low,
+                        sigma_high: params.sigma_high,
+                        p_low_to_high: params.p_low_to_high,
+                        p_high_to_low: params.p_high_to_low,
+                    })
+                } else {
+                    Err(anyhow::anyhow!("Regime Switching parameters not found"))
+                }
+            }
             _ => Err(anyhow::anyhow!("Unknown model type: {}", self.model_type)),
         }
     }
 }
 
-/// Save configuration to JSON file
+/// Schedule values as the comma separated text `CoreSimParams` carries
+pub fn format_schedule(values: &[f64]) -> String {
+    values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// True for `.yaml`/`.yml` paths, everything else is treated as JSON
+fn is_yaml(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"))
+}
+
+/// Save configuration to a JSON or YAML file, picked by extension
 pub fn save_config(config: &SimConfig, path: &Path) -> Result<()> {
-    let json = serde_json::to_string_pretty(config)?;
-    fs::write(path, json)?;
+    let text = if is_yaml(path) {
+        serde_yaml::to_string(config)?
+    } else {
+        serde_json::to_string_pretty(config)?
+    };
+    fs::write(path, text)?;
     Ok(())
 }
 
-/// Load configuration from JSON file
+/// Load configuration from a JSON or YAML file, picked by extension
 pub fn load_config(path: &Path) -> Result<SimConfig> {
-    let json = fs::read_to_string(path)?;
-    let config: SimConfig = serde_json::from_str(&json)?;
+    let text = fs::read_to_string(path)?;
+    let doc: Value = if is_yaml(path) {
+        serde_yaml::from_str(&text)?
+    } else {
+        serde_json::from_str(&text)?
+    };
+    let config: SimConfig = serde_json::from_value(migrate_config(doc)?)?;
     Ok(config)
 }
 
+/// Upgrade a raw config document to `CONFIG_VERSION` one step at a time, filling
+/// fields that older versions didn't have with their defaults
+fn migrate_config(mut doc: Value) -> Result<Value> {
+    let obj = doc
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Config root must be an object"))?;
+    let mut version = obj.get("version").and_then(Value::as_u64).unwrap_or(1) as u32;
+
+    if version > CONFIG_VERSION {
+        return Err(anyhow!(
+            "Config version {} is newer than supported version {}",
+            version,
+            CONFIG_VERSION
+        ));
+    }
+
+    while version < CONFIG_VERSION {
+        match version {
+            // v1 predates quasi-random sampling, variance reduction and the VaR confidence level
+            1 => {
+                obj.entry("use_quasi_random").or_insert(json!(false));
+                obj.entry("variance_reduction").or_insert(json!(default_variance_reduction()));
+                obj.entry("confidence_level").or_insert(json!(default_confidence_level()));
+            }
+            _ => return Err(anyhow!("No migration from config version {}", version)),
+        }
+        version += 1;
+    }
+
+    obj.insert("version".to_string(), json!(CONFIG_VERSION));
+    Ok(doc)
+}
+
 /// Validate configuration
 pub fn validate_config(config: &SimConfig) -> Result<()> {
-    // Basic validations
-    if config.initial_price <= 0.0 {
+    // Basic validations, the rate models check their initial value themselves
+    if !matches!(config.model_type.as_str(), "CIR" | "Vasicek") && config.initial_price <= 0.0 {
         return Err(anyhow::anyhow!("Initial price must be positive"));
     }
     
@@ -146,10 +659,104 @@ pub fn validate_config(config: &SimConfig) -> Result<()> {
         return Err(anyhow::anyhow!("Number of paths must be greater than 0"));
     }
     
-    if config.dt <= 0.0 {
+    if let Some(path) = config.diagnostic_path && path >= config.num_paths {
+        return Err(anyhow::anyhow!("Diagnostic path must be below the number of paths"));
+    }
+    
+    if !["Price", "Return"].contains(&config.output_mode.as_str()) {
+        return Err(anyhow::anyhow!("Unknown output mode: {}", config.output_mode));
+    }
+
+    if !["PerStep", "Daily", "Weekly", "Monthly"].contains(&config.step_frequency.as_str()) {
+        return Err(anyhow::anyhow!("Unknown step frequency: {}", config.step_frequency));
+    }
+
+    // calendar frequencies fix dt themselves
+    if config.step_frequency == "PerStep" && config.dt <= 0.0 {
         return Err(anyhow::anyhow!("dt must be positive"));
     }
     
+    if config.use_antithetic && config.use_quasi_random {
+        return Err(anyhow::anyhow!("Antithetic and quasi-random sampling cannot be used together"));
+    }
+    
+    if !["None", "ControlVariate", "Stratified", "ImportanceSampling"].contains(&config.variance_reduction.as_str()) {
+        return Err(anyhow::anyhow!("Unknown variance reduction: {}", config.variance_reduction));
+    }
+    
+    if config.use_quasi_random && config.variance_reduction == "Stratified" {
+        return Err(anyhow::anyhow!("Quasi-random and stratified sampling cannot be used together"));
+    }
+
+    // the likelihood ratio only depends on the terminal price for plain GBM with normal shocks
+    if config.variance_reduction == "ImportanceSampling" {
+        let has_schedule = config.gbm_params.as_ref().is_some_and(|gbm| gbm.mu_schedule.is_some() || gbm.sigma_schedule.is_some());
+        if config.model_type != "GBM" || has_schedule || config.shock_distribution != "Normal" {
+            return Err(anyhow::anyhow!("Importance sampling needs plain GBM with normal shocks and no schedules"));
+        }
+        if config.use_antithetic {
+            return Err(anyhow::anyhow!("Antithetic sampling and importance sampling cannot be used together"));
+        }
+        if config.option_params.is_some() {
+            return Err(anyhow::anyhow!("Option pricing is not available with importance sampling"));
+        }
+    }
+    
+    // resampling and regime draws have no shock to zero, and there is no variance to reduce
+    if config.deterministic {
+        if ["Bootstrap", "RegimeSwitching"].contains(&config.model_type.as_str()) {
+            return Err(anyhow::anyhow!("Drift-only preview is not available for {}", config.model_type));
+        }
+        if ["ControlVariate", "ImportanceSampling"].contains(&config.variance_reduction.as_str()) {
+            return Err(anyhow::anyhow!("Drift-only preview cannot be combined with {}", config.variance_reduction));
+        }
+    }
+
+    if !["Normal", "StudentT"].contains(&config.shock_distribution.as_str()) {
+        return Err(anyhow::anyhow!("Unknown shock distribution: {}", config.shock_distribution));
+    }
+
+    // t variance is df/(df-2), so it only exists (and can be rescaled to 1) for df > 2
+    if config.shock_distribution == "StudentT" && config.student_t_df <= 2.0 {
+        return Err(anyhow::anyhow!("Student-t degrees of freedom must be greater than 2"));
+    }
+
+    if config.confidence_level <= 0.0 || config.confidence_level >= 1.0 {
+        return Err(anyhow::anyhow!("Confidence level must be between 0 and 1 (exclusive)"));
+    }
+
+    if let Some(target) = config.target_price && target <= 0.0 {
+        return Err(anyhow::anyhow!("Target price must be positive"));
+    }
+
+    if let Some(factor) = config.stress_factor_sigma && !(factor.is_finite() && factor > 0.0) {
+        return Err(anyhow::anyhow!("Stress volatility factor must be positive"));
+    }
+
+    if let Some(target) = config.target_mean_price {
+        if target <= 0.0 {
+            return Err(anyhow::anyhow!("Target mean price must be positive"));
+        }
+        if !crate::core_sim::DRIFT_TARGET_MODELS.contains(&config.model_type.as_str()) {
+            return Err(anyhow::anyhow!("Target mean price only applies to {}", crate::core_sim::DRIFT_TARGET_MODELS.join(", ")));
+        }
+        if config.gbm_params.as_ref().is_some_and(|gbm| gbm.mu_schedule.is_some()) {
+            return Err(anyhow::anyhow!("Target mean price cannot be combined with a mu schedule"));
+        }
+    }
+    
+    if let Some(ref option) = config.option_params {
+        if option.strike <= 0.0 {
+            return Err(anyhow::anyhow!("Option strike must be positive"));
+        }
+        if option.compute_greeks && (option.greek_bump <= 0.0 || option.greek_bump >= 1.0) {
+            return Err(anyhow::anyhow!("Greek bump must be between 0 and 1 (relative)"));
+        }
+        if let Some(ref barrier) = option.barrier_params && barrier.barrier <= 0.0 {
+            return Err(anyhow::anyhow!("Option barrier must be positive"));
+        }
+    }
+    
     // Model-specific validations
     match config.model_type.as_str() {
         "GBM" => {
@@ -157,6 +764,23 @@ pub fn validate_config(config: &SimConfig) -> Result<()> {
                 if params.sigma < 0.0 {
                     return Err(anyhow::anyhow!("GBM sigma must be non-negative"));
                 }
+                // A schedule may be shorter than the horizon (the scalar covers the rest) but not longer
+                for (name, schedule) in [("mu", &params.mu_schedule), ("sigma", &params.sigma_schedule)] {
+                    if let Some(schedule) = schedule {
+                        if schedule.is_empty() {
+                            return Err(anyhow::anyhow!("GBM {} schedule must not be empty", name));
+                        }
+                        if schedule.len() > config.horizon {
+                            return Err(anyhow::anyhow!("GBM {} schedule has {} entries but the horizon is {} steps", name, schedule.len(), config.horizon));
+                        }
+                        if schedule.iter().any(|v| !v.is_finite()) {
+                            return Err(anyhow::anyhow!("GBM {} schedule values must be finite", name));
+                        }
+                    }
+                }
+                if params.sigma_schedule.as_ref().is_some_and(|s| s.iter().any(|&v| v < 0.0)) {
+                    return Err(anyhow::anyhow!("GBM sigma schedule values must be non-negative"));
+                }
             } else {
                 return Err(anyhow::anyhow!("GBM parameters missing"));
             }
@@ -188,6 +812,24 @@ pub fn validate_config(config: &SimConfig) -> Result<()> {
                 return Err(anyhow::anyhow!("Jump Diffusion parameters missing"));
             }
         }
+        "HawkesJump" => {
+            if let Some(ref params) = config.hawkes_jump_params {
+                if params.lambda < 0.0 {
+                    return Err(anyhow::anyhow!("Hawkes baseline intensity must be non-negative"));
+                }
+                if params.sigma < 0.0 || params.sigma_j < 0.0 {
+                    return Err(anyhow::anyhow!("Hawkes sigma and sigma_j must be non-negative"));
+                }
+                if params.excitation < 0.0 || params.decay <= 0.0 {
+                    return Err(anyhow::anyhow!("Hawkes excitation must be non-negative and decay positive"));
+                }
+                if params.excitation >= params.decay {
+                    return Err(anyhow::anyhow!("Hawkes excitation must be below the decay rate"));
+                }
+            } else {
+                return Err(anyhow::anyhow!("Hawkes Jump parameters missing"));
+            }
+        }
         "GARCH" => {
             if let Some(ref params) = config.garch_params {
                 if params.omega <= 0.0 {
@@ -206,8 +848,111 @@ pub fn validate_config(config: &SimConfig) -> Result<()> {
                 return Err(anyhow::anyhow!("GARCH parameters missing"));
             }
         }
+        "Heston" => {
+            if let Some(ref params) = config.heston_params {
+                if params.v0 < 0.0 {
+                    return Err(anyhow::anyhow!("Heston v0 must be non-negative"));
+                }
+                if params.kappa <= 0.0 {
+                    return Err(anyhow::anyhow!("Heston kappa must be positive"));
+                }
+                if params.theta <= 0.0 {
+                    return Err(anyhow::anyhow!("Heston theta must be positive"));
+                }
+                if params.xi < 0.0 {
+                    return Err(anyhow::anyhow!("Heston xi must be non-negative"));
+                }
+                if params.rho.abs() > 1.0 {
+                    return Err(anyhow::anyhow!("Heston rho must be in [-1, 1]"));
+                }
+                // Feller condition only warns: full truncation keeps variance usable anyway
+                if 2.0 * params.kappa * params.theta <= params.xi.powi(2) {
+                    eprintln!("⚠️ Heston Feller condition failed: 2*kappa*theta <= xi^2, variance can reach zero - config.rs:256");
+                }
+            } else {
+                return Err(anyhow::anyhow!("Heston parameters missing"));
+            }
+        }
+        "CIR" => {
+            if let Some(ref params) = config.cir_params {
+                if params.kappa <= 0.0 {
+                    return Err(anyhow::anyhow!("CIR kappa must be positive"));
+                }
+                if params.theta <= 0.0 {
+                    return Err(anyhow::anyhow!("CIR theta must be positive"));
+                }
+                if params.sigma < 0.0 {
+                    return Err(anyhow::anyhow!("CIR sigma must be non-negative"));
+                }
+                if config.initial_price < 0.0 {
+                    return Err(anyhow::anyhow!("CIR initial value must be non-negative"));
+                }
+                // Feller condition only warns: full truncation keeps the process at or above zero
+                if 2.0 * params.kappa * params.theta <= params.sigma.powi(2) {
+                    eprintln!("⚠️ CIR Feller condition failed: 2*kappa*theta <= sigma^2, the process can reach zero - config.rs:508");
+                }
+            } else {
+                return Err(anyhow::anyhow!("CIR parameters missing"));
+            }
+        }
+        "Vasicek" => {
+            if let Some(ref params) = config.vasicek_params {
+                if params.kappa <= 0.0 {
+                    return Err(anyhow::anyhow!("Vasicek kappa must be positive"));
+                }
+                if params.sigma <= 0.0 {
+                    return Err(anyhow::anyhow!("Vasicek sigma must be positive"));
+                }
+                if !config.initial_price.is_finite() {
+                    return Err(anyhow::anyhow!("Vasicek initial rate must be finite"));
+                }
+            } else {
+                return Err(anyhow::anyhow!("Vasicek parameters missing"));
+            }
+        }
+        "SABR" => {
+            if let Some(ref params) = config.sabr_params {
+                if params.alpha <= 0.0 {
+                    return Err(anyhow::anyhow!("SABR alpha must be positive"));
+                }
+                if !(0.0..=1.0).contains(&params.beta) {
+                    return Err(anyhow::anyhow!("SABR beta must be in [0, 1]"));
+                }
+                if !(-1.0..=1.0).contains(&params.rho) {
+                    return Err(anyhow::anyhow!("SABR rho must be in [-1, 1]"));
+                }
+                if params.nu < 0.0 {
+                    return Err(anyhow::anyhow!("SABR nu must be non-negative"));
+                }
+            } else {
+                return Err(anyhow::anyhow!("SABR parameters missing"));
+            }
+        }
+        "LocalVol" => {
+            if let Some(ref params) = config.local_vol_params {
+                // parsing the file checks the grid shape and values
+                crate::data_io::load_vol_surface(Path::new(&params.surface_file))?;
+            } else {
+                return Err(anyhow::anyhow!("Local vol parameters missing"));
+            }
+        }
+        "RegimeSwitching" => {
+            if let Some(ref params) = config.regime_switching_params {
+                if params.sigma_low < 0.0 || params.sigma_high < 0.0 {
+                    return Err(anyhow::anyhow!("Regime Switching sigmas must be non-negative"));
+                }
+                if !(0.0..=1.0).contains(&params.p_low_to_high) || !(0.0..=1.0).contains(&params.p_high_to_low) {
+                    return Err(anyhow::anyhow!("Regime Switching transition probabilities must be in [0, 1]"));
+                }
+            } else {
+                return Err(anyhow::anyhow!("Regime Switching parameters missing"));
+            }
+        }
         "Bootstrap" => {
-            // No additional validation needed
+            if let Some(ref params) = config.bootstrap_params
+                && params.block_size < 1 {
+                return Err(anyhow::anyhow!("Bootstrap block size must be at least 1"));
+            }
         }
         _ => {
             return Err(anyhow::anyhow!("Unknown model type: {}", config.model_type));
@@ -215,4 +960,112 @@ pub fn validate_config(config: &SimConfig) -> Result<()> {
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Window and file-dialog state remembered between GUI sessions, kept apart from any setup
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppSettings {
+    /// Directory the last price CSV was opened from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_dir: Option<PathBuf>,
+    /// Setup file last saved or loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_path: Option<PathBuf>,
+    /// Logical window size (width, height) when the app was closed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_size: Option<(f32, f32)>,
+}
+
+/// `settings.json` under the platform config directory: `%APPDATA%` on Windows,
+/// `~/Library/Application Support` on macOS, `$XDG_CONFIG_HOME` or `~/.config` elsewhere
+pub fn settings_path() -> Option<PathBuf> {
+    let env_dir = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let base = if cfg!(target_os = "windows") {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env_dir("XDG_CONFIG_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".config")))
+    };
+    base.map(|dir| dir.join("monte_carlo").join("settings.json"))
+}
+
+/// Remembered settings, defaults when there is no settings file yet or it can't be read
+pub fn load_settings() -> AppSettings {
+    settings_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Write the settings file, creating its directory on first use
+pub fn save_settings(settings: &AppSettings) -> Result<()> {
+    let path = settings_path().ok_or_else(|| anyhow!("No config directory for the settings file"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(settings)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //a saved setup with every model's parameters filled in, as the GUI's save writes them
+    fn full_config() -> SimConfig {
+        serde_json::from_value(json!({
+            "version": CONFIG_VERSION,
+            "initial_price": 100.0, "horizon": 30, "num_paths": 1000, "seed": 7,
+            "use_antithetic": true, "dt": 1.0, "target_price": 110.0, "stress_factor_sigma": 1.5,
+            "diagnostic_path": 3, "model_type": "GBM",
+            "gbm_params": { "mu": 0.0005, "sigma": 0.02, "mu_schedule": [0.001, 0.0005], "sigma_schedule": [0.03] },
+            "mean_reversion_params": { "theta": 0.1, "mu_long_term": 105.0, "sigma": 0.02 },
+            "jump_diffusion_params": { "mu": 0.0005, "sigma": 0.02, "lambda": 0.1, "mu_j": -0.02, "sigma_j": 0.05 },
+            "hawkes_jump_params": { "mu": 0.0005, "sigma": 0.02, "lambda": 0.1, "excitation": 0.5, "decay": 1.0, "mu_j": -0.02, "sigma_j": 0.05 },
+            "garch_params": { "omega": 0.00001, "alpha": 0.1, "beta": 0.85 },
+            "heston_params": { "mu": 0.0005, "v0": 0.0004, "kappa": 2.0, "theta": 0.0004, "xi": 0.3, "rho": -0.7 },
+            "cir_params": { "kappa": 0.5, "theta": 0.04, "sigma": 0.1 },
+            "vasicek_params": { "kappa": 0.5, "theta": 0.03, "sigma": 0.01 },
+            "sabr_params": { "alpha": 0.3, "beta": 0.5, "rho": -0.3, "nu": 0.4 },
+            "local_vol_params": { "mu": 0.0005, "surface_file": "surface.csv" },
+            "regime_switching_params": { "mu": 0.0005, "sigma_low": 0.01, "sigma_high": 0.04, "p_low_to_high": 0.05, "p_high_to_low": 0.2 },
+            "bootstrap_params": { "block_size": 5, "demean": true },
+            "option_params": { "strike": 100.0, "is_call": true, "discount_rate": 0.0001, "compute_greeks": true, "greek_bump": 0.02,
+                "barrier_params": { "barrier": 130.0, "is_up_and_out": true } }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn yaml_save_and_load_round_trips() {
+        let config = full_config();
+        let path = std::env::temp_dir().join(format!("monte_carlo_round_trip_{}.yaml", std::process::id()));
+        save_config(&config, &path).unwrap();
+        let loaded = load_config(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(loaded.unwrap(), config);
+    }
+
+    #[test]
+    fn v1_config_migrates_with_defaults() {
+        //saved before versioning, quasi-random sampling, variance reduction and the VaR level
+        let v1 = r#"{
+            "initial_price": 100.0, "horizon": 30, "num_paths": 1000, "seed": 7,
+            "use_antithetic": false, "dt": 1.0, "model_type": "GBM",
+            "gbm_params": { "mu": 0.0005, "sigma": 0.02 }
+        }"#;
+        let doc = migrate_config(serde_json::from_str(v1).unwrap()).unwrap();
+        let config: SimConfig = serde_json::from_value(doc).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert!(!config.use_quasi_random);
+        assert_eq!(config.variance_reduction, "None");
+        assert_eq!(config.confidence_level, 0.95);
+        assert_eq!(config.gbm_params.unwrap().sigma, 0.02);
+    }
+
+    #[test]
+    fn newer_config_version_is_rejected() {
+        assert!(migrate_config(json!({ "version": CONFIG_VERSION + 1 })).is_err());
+    }
+}
@@ -1,21 +1,172 @@
 use anyhow::{Ok, Result, anyhow};
-use rand::{rngs::StdRng, Rng, SeedableRng};
-use rand_distr::{Distribution, Normal};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use rand_distr::{Distribution, Normal, StudentT};
 use rayon::prelude::*;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use sobol::{Sobol, params::JoeKuoD6};
+use statrs::distribution::{ContinuousCDF, Normal as StatNormal, StudentsT as StatStudentsT};
 use statrs::statistics::{Data, Distribution as StatDist, Median, OrderStatistics};
 
-use crate::SimParams;
+use crate::data_io::Frequency;
+use crate::plotting::{ChartBuffer, ChartConfig, ENSEMBLE_COLORS, HistogramBins};
 
 
+//the engine's own copies of the gui.slint enums, so core_sim builds without slint
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VarianceReduction {
+    #[default]
+    None,
+    ControlVariate,
+    Stratified,
+    ImportanceSampling,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ShockDistribution {
+    #[default]
+    Normal,
+    StudentT,
+}
+
+//space of the reported mean, spread and percentiles: price units or returns on the initial price
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    #[default]
+    Price,
+    Return,
+}
+
+//PerStep steps by dt with per-step rates, the calendar frequencies read every rate as annualized
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StepFrequency {
+    #[default]
+    PerStep,
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+//simulation input in plain Rust types, field for field the SimParams struct in gui.slint. The
+//engine only takes this, so the CLI and batch runs use it as a library; the GUI converts its
+//SimParams at the callback (From impl in main.rs)
+#[derive(Debug, Clone, Default)]
+pub struct CoreSimParams {
+    pub initial_price: f32,
+    pub horizon: i32,
+    pub num_paths: i32,
+    pub mu: f32,
+    pub sigma: f32,
+    pub seed: i32,
+    pub auto_seed: bool,
+    pub use_antithetic: bool,
+    pub use_quasi_random: bool,
+    pub variance_reduction: VarianceReduction,
+    pub shock_distribution: ShockDistribution,
+    pub student_t_df: f32,
+    // European option pricing
+    pub price_option: bool,
+    pub strike: f32,
+    pub is_call: bool,
+    pub discount_rate: f32,
+    pub price_barrier: bool,
+    pub barrier: f32,
+    pub is_up_and_out: bool,
+    pub compute_greeks: bool,
+    pub greek_bump: f32,
+    pub show_bands: bool,
+    pub log_scale: bool,
+    pub max_displayed_paths: i32,
+    pub overlay_density: bool,
+    pub show_kde: bool,
+    pub histogram_bins: i32,
+    pub histogram_fixed_range: bool,
+    pub histogram_min: f32,
+    pub histogram_max: f32,
+    pub histogram_drop_outside: bool,
+    pub store_paths: bool,
+    pub show_convergence: bool,
+    pub approximate_quantiles: bool,
+    pub output_mode: OutputMode,
+    pub fast_mode: bool,
+    pub deterministic: bool,
+    pub record_diagnostics: bool,
+    pub diagnostic_path: i32,
+    pub chart_width: i32,
+    pub chart_height: i32,
+    pub dt: i32,
+    pub step_frequency: StepFrequency,
+    //bars of the historical returns passed alongside, set by whoever built them
+    pub history_frequency: Frequency,
+    pub model_type: String,
+    pub confidence_level: f32,
+    pub risk_free_rate: f32,
+    pub target_price: f32,
+    pub target_mean_price: f32,
+    pub stress_factor_sigma: f32,
+    pub stress_shift_mu: f32,
+    // Mean Reversion
+    pub theta: f32,
+    pub mu_long_term: f32,
+    // Jump Diffusion
+    pub lambda: f32,
+    pub mu_j: f32,
+    pub sigma_j: f32,
+    // Hawkes Jump (jump fields above shared, lambda is the baseline)
+    pub hawkes_excitation: f32,
+    pub hawkes_decay: f32,
+    // GARCH
+    pub omega: f32,
+    pub alpha: f32,
+    pub beta: f32,
+    // CIR (sigma shared)
+    pub cir_kappa: f32,
+    pub cir_theta: f32,
+    // Vasicek (sigma shared)
+    pub vasicek_kappa: f32,
+    pub vasicek_theta: f32,
+    // SABR (initial price is the forward)
+    pub sabr_alpha: f32,
+    pub sabr_beta: f32,
+    pub sabr_rho: f32,
+    pub sabr_nu: f32,
+    // Local Vol (mu shared), path of the sigma(S, t) grid CSV
+    pub local_vol_file: String,
+    // Regime Switching (mu shared)
+    pub sigma_low: f32,
+    pub sigma_high: f32,
+    pub p_low_to_high: f32,
+    pub p_high_to_low: f32,
+    // GBM term structure, comma separated per-step values, empty = scalar
+    pub mu_schedule: String,
+    pub sigma_schedule: String,
+    // Bootstrap
+    pub block_size: i32,
+    pub bootstrap_demean: bool,
+    // Heston
+    pub v0: f32,
+    pub kappa: f32,
+    pub theta_v: f32,
+    pub xi: f32,
+    pub rho: f32,
+}
+
 // Model-specific parameters enum
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone)]
 pub enum ModelParams {
     GBM {
         mu: f64,
         sigma: f64,
+        mu_schedule: Option<Vec<f64>>,
+        sigma_schedule: Option<Vec<f64>>,
     },
     Bootstrap {
-        
+        block_size: usize,
+        demean: bool,
     },
     MeanReversion {
         theta: f64,       
@@ -29,177 +180,2328 @@ pub enum ModelParams {
         mu_j: f64,        
         sigma_j: f64,     
     },
+    HawkesJump {
+        mu: f64,
+        sigma: f64,
+        lambda: f64,
+        excitation: f64,
+        decay: f64,
+        mu_j: f64,
+        sigma_j: f64,
+    },
     GARCH {
         omega: f64,      
         alpha: f64,       
         beta: f64,       
     },
+    Heston {
+        mu: f64,
+        v0: f64,
+        kappa: f64,
+        theta: f64,
+        xi: f64,
+        rho: f64,
+    },
+    CIR {
+        kappa: f64,
+        theta: f64,
+        sigma: f64,
+    },
+    Vasicek {
+        kappa: f64,
+        theta: f64,
+        sigma: f64,
+    },
+    SABR {
+        alpha: f64,
+        beta: f64,
+        rho: f64,
+        nu: f64,
+    },
+    LocalVol {
+        mu: f64,
+        surface: VolSurface,
+    },
+    RegimeSwitching {
+        mu: f64,
+        sigma_low: f64,
+        sigma_high: f64,
+        p_low_to_high: f64,
+        p_high_to_low: f64,
+    },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct SimStats {
     pub model: String,
+    //"Price" or "Return": the space mean, std_dev, median and percentiles are reported in.
+    //Returns are fractions of the initial price, like var95 and cvar95 always are
+    pub output_mode: String,
     pub paths: usize,
+    //independent draws behind the estimates: the kept paths, or their antithetic pairs
+    pub effective_samples: usize,
     pub horizon: usize,
     pub mean: f64,
+    //Monte Carlo standard error of the mean
+    pub mean_std_error: f64,
     pub std_dev: f64,
     pub median: f64,
     pub p5: f64,
     pub p25: f64,
     pub p75: f64,
     pub p95: f64,
+    pub confidence_level: f64,
+    //VaR and CVaR at confidence_level (named after the default 95%)
     pub var95: f64,
+    pub cvar95: f64,
+    //paths behind the CVaR average: the tail count, or (Σw)²/Σw² of the tail likelihood
+    //ratios under importance sampling
+    pub tail_effective_samples: f64,
+    pub mean_max_drawdown: f64,
+    pub p95_max_drawdown: f64,
+    pub skewness: f64,
+    pub kurtosis: f64,
+    //annualized, see risk_adjusted_ratios
+    pub sharpe_ratio: f64,
+    pub sortino_ratio: f64,
+    //share of paths ending above target_price (when one is set) and below the initial price
+    pub prob_above_target: Option<f64>,
+    pub loss_probability: f64,
+    //raw variance / adjusted variance of terminal prices, 1.0 when no reduction applied
+    pub variance_reduction_ratio: f64,
+    //control-variate estimate of the mean and its standard error, None unless that reduction ran.
+    //Only the mean is adjusted, every other statistic reads the raw terminal prices
+    pub cv_mean: Option<f64>,
+    pub cv_std_error: Option<f64>,
+    //paths with inf/NaN prices left out of every statistic (their antithetic partners too)
+    pub diverged_paths: usize,
+    //master seed the paths were drawn from, the drawn one when auto_seed was on
+    pub seed: u64,
+    pub option_price: Option<f64>,
+    pub option_std_error: Option<f64>,
+    pub barrier_option_price: Option<f64>,
+    pub delta: Option<f64>,
+    pub vega: Option<f64>,
+    //closed-form lognormal terminal moments for plain GBM (normal shocks, no schedules), in the
+    //same output space as mean/median/std_dev
+    pub analytic_mean: Option<f64>,
+    pub analytic_median: Option<f64>,
+    pub analytic_std_dev: Option<f64>,
+    //hindcasts only: share of simulated terminal prices below the realized one
+    pub realized_percentile: Option<f64>,
+    //mu solved from target_mean_price, None when the drift was not calibrated
+    pub implied_mu: Option<f64>,
+    //model-free VaR from resampled history over the same horizon, None without history
+    pub historical_var: Option<f64>,
+    //95% percentile bootstrap interval of var95, only set for run_simulation and run_hindcast outputs
+    //without importance sampling or approximate quantiles
+    pub var95_ci: Option<(f64, f64)>,
+    //stress scenario the paths ran under, 1.0 and 0.0 when unstressed
+    pub stress_factor_sigma: f64,
+    pub stress_shift_mu: f64,
 }
 
-pub fn run_simulation (params: SimParams, hist_log_returns: Vec<f64>,) -> Result<(SimStats, (Vec<u8>, u32, u32), (Vec<u8>, u32, u32))> {
-    let init_price = params.initial_price as f64;
-    let mu = params.mu as f64;
-    let sigma = params.sigma as f64;
-    let horizon = params.horizon as usize;
-    let num_paths = params.num_paths as usize;
-    let dt = params.dt as f64;
-    let model_name = match params.model_type.as_str() {
-        "GBM" => "GBM",
-        "Bootstrap" => "Bootstrap",
-        _ => "",
+impl SimStats {
+    //the run-level fields every statistics pass starts from: no variance reduction and nothing
+    //optional computed yet. The passes fill in their own estimates, simulate the rest
+    fn base(model: &str, output_mode: OutputMode, paths: usize, samples: usize, horizon: usize, confidence_level: f64) -> SimStats {
+        SimStats {
+            model: model.to_string(),
+            output_mode: format!("{:?}", output_mode),
+            paths,
+            effective_samples: samples,
+            horizon,
+            confidence_level,
+            variance_reduction_ratio: 1.0,
+            stress_factor_sigma: 1.0,
+            ..Default::default()
+        }
+    }
+
+    //field names as in the struct, non-finite ratios serialize as null
+    pub fn to_json(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+}
+
+//everything a run produces: stats, rendered charts and the raw results behind them
+pub struct SimOutput {
+    pub stats: SimStats,
+    //None when store_paths is off, there are no paths to draw
+    pub paths_chart: Option<ChartBuffer>,
+    pub hist_chart: ChartBuffer,
+    pub convergence_chart: Option<ChartBuffer>,
+    //per path, as simulated
+    pub terminal_prices: Vec<f64>,
+    //empty when store_paths is off
+    pub paths: Vec<Vec<f64>>,
+    //per-step internals of params.diagnostic_path, empty unless record_diagnostics is on
+    pub diagnostics: Vec<StepDiagnostics>,
+}
+
+//every model_type the path generator dispatches on
+pub const MODEL_TYPES: [&str; 12] = ["GBM", "Bootstrap", "MeanReversion", "JumpDiffusion", "HawkesJump", "GARCH", "Heston", "CIR", "Vasicek", "SABR", "LocalVol", "RegimeSwitching"];
+
+//share of paths allowed to end non-finite before the run is rejected as numerically unstable
+const MAX_DIVERGED_FRACTION: f64 = 0.01;
+
+//paths per progress/cancel check, keeps the shared atomics out of the per-path hot loop
+const PROGRESS_CHUNK: usize = 64;
+
+//every stream shares the key derived from the user seed and gets its own ChaCha stream id,
+//so paths draw from non-overlapping keystreams instead of reseeding with seed + i
+type PathRng = ChaCha12Rng;
+
+//progress callback and cancel flag for a run. progress gets the completed fraction in [0, 1],
+//reported at most once per whole percent
+#[derive(Clone, Default)]
+pub struct RunControl {
+    pub progress: Option<Arc<dyn Fn(f32) + Send + Sync>>,
+    pub cancel: Arc<AtomicBool>,
+}
+
+impl RunControl {
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    //same cancel flag, no progress, for the extra runs behind greeks
+    fn silent(&self) -> Self {
+        RunControl { progress: None, cancel: self.cancel.clone() }
+    }
+
+    pub fn report(&self, before: usize, after: usize, total: usize) {
+        if let Some(progress) = &self.progress
+            && before * 100 / total != after * 100 / total
+        {
+            progress(after as f32 / total as f32);
+        }
+    }
+}
+
+pub fn run_simulation (params: CoreSimParams, hist_log_returns: Vec<f64>,) -> Result<SimOutput> {
+    run_simulation_with_control(params, hist_log_returns, &RunControl::default())
+}
+
+//run_simulation with progress reporting, returns an error if cancelled before the paths finish
+pub fn run_simulation_with_control(params: CoreSimParams, hist_log_returns: Vec<f64>, control: &RunControl) -> Result<SimOutput> {
+    run_against_realized(params, hist_log_returns, None, control)
+}
+
+//simulates forward from a past date and scores the fan against what happened. realized[0] is the
+//last close before the start date and the run steps once per later bar (PerStep, dt 1), for at
+//most params.horizon bars. mu and sigma are re-estimated from hist_log_returns, the bars before
+//the start, so nothing after it leaks in; other model parameters are used as entered
+pub fn run_hindcast(params: CoreSimParams, hist_log_returns: Vec<f64>, realized: &[f64], control: &RunControl) -> Result<SimOutput> {
+    if realized.len() < 2 {
+        return Err(anyhow!("Hindcast needs at least one realized bar after the start date"));
+    }
+    let horizon = (realized.len() - 1).min(params.horizon.max(1) as usize);
+    let realized = &realized[..=horizon];
+    let (mu, sigma) = estimate_paramaters(&hist_log_returns)?;
+    let params = CoreSimParams {
+        initial_price: realized[0] as f32,
+        horizon: horizon as i32,
+        mu: mu as f32,
+        sigma: sigma as f32,
+        step_frequency: StepFrequency::PerStep,
+        dt: 1,
+        store_paths: true,
+        target_mean_price: 0.0,
+        ..params
     };
+    run_against_realized(params, hist_log_returns, Some(realized), control)
+}
 
-    let paths: Vec<Vec<f64>> = (0..num_paths).into_par_iter().map(|i| {
-        let seed = (params.seed as u64).wrapping_add(i as u64);
-        let mut rng = StdRng::seed_from_u64(seed);
+fn run_against_realized(params: CoreSimParams, hist_log_returns: Vec<f64>, realized: Option<&[f64]>, control: &RunControl) -> Result<SimOutput> {
+    validate_params(&params)?;
+    let params = resolve_drift_target(resolve_seed(params));
+    let (mut stats, paths, terminal_prices, diagnostics) = simulate(&params, &hist_log_returns, control)?;
+    stats.implied_mu = (params.target_mean_price > 0.0).then_some(params.mu as f64);
+    stats.realized_percentile = realized
+        .and_then(|r| r.last())
+        .map(|&terminal| probability_below(&terminal_prices, terminal));
+    //only the run behind the summary pays for the interval, the reruns of greeks, sweeps,
+    //ensembles and screens would throw it away. Plain resampling would drop the importance
+    //weights, and approximate mode exists to avoid repeated passes over many paths
+    let approximate = params.approximate_quantiles && terminal_prices.len() >= APPROX_QUANTILE_MIN_PATHS;
+    if params.variance_reduction != VarianceReduction::ImportanceSampling && !approximate {
+        stats.var95_ci = Some(var_ci(&terminal_prices, params.initial_price as f64, params.confidence_level as f64));
+    }
 
-        match params.model_type.as_str() {
-            "GBM" => generate_gbm_path(init_price, mu, sigma, horizon, dt, params.use_antithetic && (i%2==1), &mut rng),
-            "Bootstrap" => generate_bootstrap_path(init_price, horizon, &hist_log_returns, &mut rng),
-            "MeanReversion" => {
-                let theta = params.theta as f64;
-                let mu_long_term = params.mu_long_term as f64;
-                let sigma = params.sigma as f64;
-                generate_mean_reversion_path(init_price, theta, mu_long_term, sigma, horizon, dt, params.use_antithetic && (i%2==1), &mut rng)
+    if params.compute_greeks {
+        let (delta, vega) = compute_greeks(&params, &hist_log_returns, &control.silent())?;
+        stats.delta = delta;
+        stats.vega = vega;
+    }
+
+    let mu_long_term_value = if params.model_type == "MeanReversion" {
+        Some(params.mu_long_term as f64)
+    } else {
+        None
+    };
+
+    //var95 is a loss fraction of the initial price, the histogram marks the price it corresponds to
+    let var_threshold = Some(params.initial_price as f64 * (1.0 - stats.var95));
+
+    //non-positive sizes from the UI fall back to the default 800x600
+    let default_chart = ChartConfig::default();
+    let chart = ChartConfig {
+        width: if params.chart_width > 0 { params.chart_width as u32 } else { default_chart.width },
+        height: if params.chart_height > 0 { params.chart_height as u32 } else { default_chart.height },
+    };
+
+    //the charts only read paths and terminal prices, so they render concurrently. Wall time is
+    //then the slowest chart, usually the paths chart when percentile bands are on
+    let (paths_png, (hist_png, convergence_png)) = rayon::join(
+        //stats-only runs have no paths to draw, so no chart is rendered
+        || params.store_paths.then(|| crate::plotting::plot_price_paths(
+            &paths,
+            &params.model_type,
+            mu_long_term_value,
+            params.show_bands,
+            params.log_scale,
+            params.max_displayed_paths.max(0) as usize,
+            params.deterministic,
+            realized,
+            chart,
+        )),
+        || rayon::join(
+            || if params.show_kde {
+                crate::plotting::plot_kde(&terminal_prices, None, chart)
+            } else {
+                crate::plotting::plot_histogram(&terminal_prices, histogram_bins(&params), params.overlay_density, var_threshold, chart)
+            },
+            || params.show_convergence.then(|| crate::plotting::plot_convergence(&convergence_trace(&terminal_prices), chart)),
+        ),
+    );
+    let paths_png = paths_png.transpose()?;
+    let hist_png = hist_png?;
+    let convergence_png = convergence_png.transpose()?;
+
+    Ok(SimOutput {
+        stats,
+        paths_chart: paths_png,
+        hist_chart: hist_png,
+        convergence_chart: convergence_png,
+        terminal_prices,
+        paths,
+        diagnostics,
+    })
+}
+
+//vol multiplier of the stress scenario, non-positive values from the UI mean no stress
+pub fn stress_factor(params: &CoreSimParams) -> f64 {
+    if params.stress_factor_sigma > 0.0 { params.stress_factor_sigma as f64 } else { 1.0 }
+}
+
+pub fn is_stressed(params: &CoreSimParams) -> bool {
+    stress_factor(params) != 1.0 || params.stress_shift_mu != 0.0
+}
+
+//stress scenario: every volatility parameter times the factor (variances times its square, so
+//GARCH and Heston returns scale by the factor too) and stress_shift_mu added to mu. Models
+//without a drift rate (MeanReversion, CIR, Vasicek, GARCH, SABR) only take the vol factor, and
+//for SABR with beta < 1 scaling alpha is only approximately a vol multiplier. Bootstrap returns,
+//GBM schedules and vol surfaces are stressed where simulate reads them
+fn apply_stress(params: &CoreSimParams) -> CoreSimParams {
+    let factor = stress_factor(params) as f32;
+    CoreSimParams {
+        mu: params.mu + params.stress_shift_mu,
+        sigma: params.sigma * factor,
+        sigma_low: params.sigma_low * factor,
+        sigma_high: params.sigma_high * factor,
+        omega: params.omega * factor * factor,
+        v0: params.v0 * factor * factor,
+        theta_v: params.theta_v * factor * factor,
+        xi: params.xi * factor,
+        sabr_alpha: params.sabr_alpha * factor,
+        ..params.clone()
+    }
+}
+
+//non-positive bin counts from the UI fall back to the default 100
+fn histogram_bins(params: &CoreSimParams) -> HistogramBins {
+    let default_bins = HistogramBins::default();
+    HistogramBins {
+        count: if params.histogram_bins > 0 { params.histogram_bins as usize } else { default_bins.count },
+        range: params.histogram_fixed_range.then_some((params.histogram_min as f64, params.histogram_max as f64)),
+        clamp: !params.histogram_drop_outside,
+    }
+}
+
+//CoreSimParams counterpart of config::validate_config, run before any path is generated so bad
+//input from the UI gives a message instead of a panic or meaningless numbers
+pub fn validate_params(params: &CoreSimParams) -> Result<()> {
+    let model = params.model_type.as_str();
+    let non_negative = |value: f32, name: &str| {
+        if value.is_finite() && value >= 0.0 { Ok(()) } else { Err(anyhow!("{} must be non-negative", name)) }
+    };
+    let positive = |value: f32, name: &str| {
+        if value.is_finite() && value > 0.0 { Ok(()) } else { Err(anyhow!("{} must be positive", name)) }
+    };
+
+    //CIR models a rate or variance, which may start at zero. Vasicek rates may be negative
+    if model == "CIR" {
+        non_negative(params.initial_price, "CIR initial value")?;
+    } else if model == "Vasicek" {
+        if !params.initial_price.is_finite() {
+            return Err(anyhow!("Vasicek initial rate must be finite"));
+        }
+    } else {
+        positive(params.initial_price, "Initial price")?;
+    }
+    if params.horizon <= 0 {
+        return Err(anyhow!("Horizon must be greater than 0"));
+    }
+    if params.num_paths <= 0 {
+        return Err(anyhow!("Number of paths must be greater than 0"));
+    }
+    if params.histogram_fixed_range && !(params.histogram_min.is_finite() && params.histogram_max.is_finite() && params.histogram_min < params.histogram_max) {
+        return Err(anyhow!("Histogram range minimum must be below its maximum"));
+    }
+    if params.record_diagnostics && !(0..params.num_paths).contains(&params.diagnostic_path) {
+        return Err(anyhow!("Diagnostic path must be between 0 and {}", params.num_paths - 1));
+    }
+    if params.step_frequency == StepFrequency::PerStep && params.dt <= 0 {
+        return Err(anyhow!("dt must be positive"));
+    }
+    if params.use_antithetic && params.use_quasi_random {
+        return Err(anyhow!("Antithetic and quasi-random sampling cannot be used together"));
+    }
+    if params.use_quasi_random && params.variance_reduction == VarianceReduction::Stratified {
+        return Err(anyhow!("Quasi-random and stratified sampling cannot be used together"));
+    }
+    if params.variance_reduction == VarianceReduction::ImportanceSampling {
+        validate_importance_sampling(params)?;
+    }
+    if params.shock_distribution == ShockDistribution::StudentT && params.student_t_df <= 2.0 {
+        return Err(anyhow!("Student-t degrees of freedom must be greater than 2"));
+    }
+    if params.deterministic {
+        if params.model_type == "Bootstrap" || params.model_type == "RegimeSwitching" {
+            return Err(anyhow!("Drift-only preview is not available for {}", params.model_type));
+        }
+        if matches!(params.variance_reduction, VarianceReduction::ControlVariate | VarianceReduction::ImportanceSampling) {
+            return Err(anyhow!("Drift-only preview cannot be combined with {:?}", params.variance_reduction));
+        }
+    }
+    if !(params.confidence_level > 0.0 && params.confidence_level < 1.0) {
+        return Err(anyhow!("Confidence level must be between 0 and 1 (exclusive)"));
+    }
+    //0 means no target
+    non_negative(params.target_price, "Target price")?;
+    non_negative(params.target_mean_price, "Target mean price")?;
+    if params.target_mean_price > 0.0 {
+        if !DRIFT_TARGET_MODELS.contains(&model) {
+            return Err(anyhow!("Target mean price only applies to {}", DRIFT_TARGET_MODELS.join(", ")));
+        }
+        if !params.mu_schedule.trim().is_empty() {
+            return Err(anyhow!("Target mean price cannot be combined with a mu schedule"));
+        }
+    }
+    if params.price_option {
+        positive(params.strike, "Option strike")?;
+        if params.compute_greeks && !(params.greek_bump > 0.0 && params.greek_bump < 1.0) {
+            return Err(anyhow!("Greek bump must be between 0 and 1 (relative)"));
+        }
+        if params.price_barrier {
+            positive(params.barrier, "Option barrier")?;
+        }
+    }
+
+    match model {
+        "GBM" => non_negative(params.sigma, "GBM sigma")?,
+        "Bootstrap" => {
+            if params.block_size < 1 {
+                return Err(anyhow!("Bootstrap block size must be at least 1"));
             }
-            "JumpDiffusion" => {
-                let mu = params.mu as f64;
-                let sigma = params.sigma as f64;
-                let lambda = params.lambda as f64;
-                let mu_j = params.mu_j as f64;
-                let sigma_j = params.sigma_j as f64;
-                generate_jump_diffusion_path(init_price, mu, sigma, lambda, mu_j, sigma_j, horizon, dt, params.use_antithetic && (i%2==1), &mut rng)
+        }
+        "MeanReversion" => {
+            positive(params.theta, "Mean Reversion theta")?;
+            non_negative(params.sigma, "Mean Reversion sigma")?;
+        }
+        "JumpDiffusion" => {
+            non_negative(params.lambda, "Jump Diffusion lambda")?;
+            non_negative(params.sigma, "Jump Diffusion sigma")?;
+            non_negative(params.sigma_j, "Jump Diffusion sigma_j")?;
+        }
+        "HawkesJump" => {
+            non_negative(params.lambda, "Hawkes baseline intensity")?;
+            non_negative(params.sigma, "Hawkes sigma")?;
+            non_negative(params.sigma_j, "Hawkes sigma_j")?;
+            non_negative(params.hawkes_excitation, "Hawkes excitation")?;
+            positive(params.hawkes_decay, "Hawkes decay")?;
+            //each jump spawns excitation / decay further jumps on average, at 1 or more the
+            //cascade never dies out and the intensity explodes
+            if params.hawkes_excitation >= params.hawkes_decay {
+                return Err(anyhow!("Hawkes excitation must be below the decay rate"));
             }
-            "GARCH" => {
-                let omega = params.omega as f64;
-                let alpha = params.alpha as f64;
-                let beta = params.beta as f64;
-                generate_garch_path(init_price, omega, alpha, beta, horizon, dt, params.use_antithetic && (i%2==1), &mut rng)
+        }
+        "GARCH" => {
+            positive(params.omega, "GARCH omega")?;
+            non_negative(params.alpha, "GARCH alpha")?;
+            non_negative(params.beta, "GARCH beta")?;
+            if params.alpha + params.beta >= 1.0 {
+                return Err(anyhow!("GARCH stationarity condition failed: alpha + beta must be < 1"));
             }
-    _ => Vec::new()
+        }
+        "Heston" => {
+            non_negative(params.v0, "Heston v0")?;
+            positive(params.kappa, "Heston kappa")?;
+            positive(params.theta_v, "Heston theta")?;
+            non_negative(params.xi, "Heston xi")?;
+            if !(-1.0..=1.0).contains(&params.rho) {
+                return Err(anyhow!("Heston rho must be in [-1, 1]"));
+            }
+        }
+        "CIR" => {
+            positive(params.cir_kappa, "CIR kappa")?;
+            positive(params.cir_theta, "CIR theta")?;
+            non_negative(params.sigma, "CIR sigma")?;
+        }
+        "Vasicek" => {
+            positive(params.vasicek_kappa, "Vasicek kappa")?;
+            positive(params.sigma, "Vasicek sigma")?;
+            if !params.vasicek_theta.is_finite() {
+                return Err(anyhow!("Vasicek theta must be finite"));
+            }
+        }
+        "SABR" => {
+            positive(params.sabr_alpha, "SABR alpha")?;
+            if !(0.0..=1.0).contains(&params.sabr_beta) {
+                return Err(anyhow!("SABR beta must be in [0, 1]"));
+            }
+            if !(-1.0..=1.0).contains(&params.sabr_rho) {
+                return Err(anyhow!("SABR rho must be in [-1, 1]"));
+            }
+            non_negative(params.sabr_nu, "SABR nu")?;
+        }
+        "LocalVol" => {
+            if params.local_vol_file.trim().is_empty() {
+                return Err(anyhow!("Local vol surface file must be set"));
+            }
+        }
+        "RegimeSwitching" => {
+            non_negative(params.sigma_low, "Regime Switching sigma_low")?;
+            non_negative(params.sigma_high, "Regime Switching sigma_high")?;
+            if !(0.0..=1.0).contains(&params.p_low_to_high) || !(0.0..=1.0).contains(&params.p_high_to_low) {
+                return Err(anyhow!("Regime Switching transition probabilities must be in [0, 1]"));
+            }
+        }
+        _ => return Err(anyhow!("Unknown model type: {}", params.model_type)),
+    }
+    Ok(())
 }
-    }).collect();
 
-    let mut terminal_prices: Vec<f64> = paths.iter().map(|path| *path.last().unwrap()).collect();
-    let stats = calculate_statistics(&mut terminal_prices, model_name,num_paths, horizon, init_price)?;
+//models whose expected terminal price is S0·e^{μT}, so solve_drift_for_target pins their mean
+pub const DRIFT_TARGET_MODELS: [&str; 4] = ["GBM", "Heston", "RegimeSwitching", "LocalVol"];
 
-    let mu_long_term_value = if params.model_type == "MeanReversion" {
-        Some(params.mu_long_term as f64)
+//a set target_mean_price replaces mu with the drift that makes the expected terminal price hit it
+fn resolve_drift_target(mut params: CoreSimParams) -> CoreSimParams {
+    if params.target_mean_price > 0.0 {
+        let horizon = params.horizon.max(1) as usize;
+        let mu = solve_drift_for_target(params.initial_price as f64, params.sigma as f64, horizon, step_dt(&params), params.target_mean_price as f64);
+        params.mu = mu as f32;
+    }
+    params
+}
+
+//one rerun of a shared setup, validated and with its drift target solved for its own inputs.
+//Models outside DRIFT_TARGET_MODELS drop the target and run the setup's mu
+fn prepare_rerun(params: CoreSimParams) -> Result<CoreSimParams> {
+    let params = if DRIFT_TARGET_MODELS.contains(&params.model_type.as_str()) {
+        params
+    } else {
+        CoreSimParams { target_mean_price: 0.0, ..params }
+    };
+    validate_params(&params)?;
+    Ok(resolve_drift_target(params))
+}
+
+//auto_seed swaps the seed for a fresh one from system entropy, drawn once per run so greek
+//reruns and sweep points still share it. Kept to a non-negative i32 so the GUI can pin it
+pub fn resolve_seed(mut params: CoreSimParams) -> CoreSimParams {
+    if params.auto_seed {
+        params.seed = rand::random_range(0..=i32::MAX);
+        params.auto_seed = false;
+    }
+    params
+}
+
+//the likelihood ratio is only a function of the terminal price for constant-parameter GBM with
+//normal shocks, and reweighted prices would bias the option payoffs and antithetic pairing
+fn validate_importance_sampling(params: &CoreSimParams) -> Result<()> {
+    if params.model_type != "GBM" {
+        return Err(anyhow!("Importance sampling is only available for GBM"));
+    }
+    if !params.mu_schedule.trim().is_empty() || !params.sigma_schedule.trim().is_empty() {
+        return Err(anyhow!("Importance sampling does not support drift or volatility schedules"));
+    }
+    if params.shock_distribution != ShockDistribution::Normal {
+        return Err(anyhow!("Importance sampling only supports normal shocks"));
+    }
+    if params.use_antithetic {
+        return Err(anyhow!("Antithetic sampling and importance sampling cannot be used together"));
+    }
+    if params.price_option {
+        return Err(anyhow!("Option pricing is not available with importance sampling"));
+    }
+    if params.sigma <= 0.0 {
+        return Err(anyhow!("Importance sampling needs a positive sigma"));
+    }
+    Ok(())
+}
+
+//checkpoints per tenfold increase in path count for convergence_trace
+const CONVERGENCE_POINTS_PER_DECADE: f64 = 10.0;
+
+//running mean of the terminal prices and its standard error at log-spaced path counts,
+//always ending at the full count. One Welford pass in path order
+pub fn convergence_trace(terminal_prices: &[f64]) -> Vec<(usize, f64, f64)> {
+    let growth = 10f64.powf(1.0 / CONVERGENCE_POINTS_PER_DECADE);
+    let mut trace = Vec::new();
+    let (mut mean, mut m2) = (0.0, 0.0);
+    let mut next_checkpoint = 1.0;
+
+    for (i, &price) in terminal_prices.iter().enumerate() {
+        let count = i + 1;
+        let delta = price - mean;
+        mean += delta / count as f64;
+        m2 += delta * (price - mean);
+
+        if count as f64 >= next_checkpoint || count == terminal_prices.len() {
+            let std_error = if count > 1 { (m2 / (count - 1) as f64 / count as f64).sqrt() } else { 0.0 };
+            trace.push((count, mean, std_error));
+            while next_checkpoint <= count as f64 {
+                next_checkpoint *= growth;
+            }
+        }
+    }
+    trace
+}
+
+//option delta and vega by central differences, bumping initial_price and sigma by
+//greek_bump (relative, default 1%). Every rerun keeps the seed so common random numbers
+//cancel most of the noise. Vega is only non-zero for models driven by sigma.
+pub fn compute_greeks(params: &CoreSimParams, hist_log_returns: &[f64], control: &RunControl) -> Result<(Option<f64>, Option<f64>)> {
+    if !params.price_option {
+        return Ok((None, None));
+    }
+    let bump = params.greek_bump;
+    let option_price = |p: &CoreSimParams| -> Result<f64> {
+        let p = CoreSimParams { store_paths: false, ..p.clone() };
+        let (stats, _, _, _) = simulate(&p, hist_log_returns, control)?;
+        Ok(stats.option_price.unwrap_or(0.0))
+    };
+
+    let mut up = params.clone();
+    let mut down = params.clone();
+    up.initial_price = params.initial_price * (1.0 + bump);
+    down.initial_price = params.initial_price * (1.0 - bump);
+    let price_step = (up.initial_price - down.initial_price) as f64;
+    let delta = if price_step > 0.0 {
+        Some((option_price(&up)? - option_price(&down)?) / price_step)
+    } else {
+        None
+    };
+
+    let mut up = params.clone();
+    let mut down = params.clone();
+    up.sigma = params.sigma * (1.0 + bump);
+    down.sigma = params.sigma * (1.0 - bump);
+    let sigma_step = (up.sigma - down.sigma) as f64;
+    let vega = if sigma_step > 0.0 {
+        Some((option_price(&up)? - option_price(&down)?) / sigma_step)
     } else {
         None
     };
 
-    let paths_png = crate::plotting::plot_price_paths(
-        &paths,
-        &params.model_type,
-        mu_long_term_value,
-    )?;
-    let hist_png = crate::plotting::plot_histogram(&terminal_prices, 100)?;
+    Ok((delta, vega))
+}
 
-    Ok((stats, paths_png, hist_png))
+//CoreSimParams field a sweep varies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SweepField {
+    InitialPrice,
+    Mu,
+    Sigma,
+    Theta,
+    MuLongTerm,
+    Lambda,
+    Strike,
 }
 
-fn generate_gbm_path(init_price: f64, mu: f64, sigma: f64, steps: usize, dt: f64, is_antithetic: bool, rng: &mut StdRng,) -> Vec<f64> {
-    //plus 1 for init_price
-    let mut path = Vec::with_capacity(steps+1);
-    path.push(init_price);
-    let mut current_price = init_price;
+//grid start, start + step, ... up to and including end
+#[derive(Debug, Clone, Copy)]
+pub struct ParamSweep {
+    pub field: SweepField,
+    pub start: f64,
+    pub end: f64,
+    pub step: f64,
+}
 
-    let drift = (mu - 0.5 * sigma.powi(2)) * dt;
-    let diffusion = sigma * dt.sqrt();
-    let normal = Normal::new(0.0, 1.0).unwrap();
+//reruns the base setup once per grid value, stats only (no plotting, no greeks), and
+//pairs each value with its stats. Runs go one after another, each already parallel over paths
+pub fn run_sweep(base: CoreSimParams, sweep: ParamSweep, hist_log_returns: &[f64]) -> Result<Vec<(f64, SimStats)>> {
+    if sweep.step <= 0.0 || sweep.end < sweep.start {
+        return Err(anyhow!("Sweep needs step > 0 and end >= start"));
+    }
+    //small tolerance so an end value hit exactly by the grid is not lost to rounding
+    let steps = ((sweep.end - sweep.start) / sweep.step + 1e-9).floor() as usize;
+    //the target would replace every grid value with the same solved drift
+    if sweep.field == SweepField::Mu && base.target_mean_price > 0.0 {
+        return Err(anyhow!("A mu sweep cannot be combined with a target mean price"));
+    }
+    let base = resolve_seed(base);
+
+    (0..=steps)
+        .map(|i| {
+            let value = sweep.start + i as f64 * sweep.step;
+            let mut params = CoreSimParams { store_paths: false, ..base.clone() };
+            let field = match sweep.field {
+                SweepField::InitialPrice => &mut params.initial_price,
+                SweepField::Mu => &mut params.mu,
+                SweepField::Sigma => &mut params.sigma,
+                SweepField::Theta => &mut params.theta,
+                SweepField::MuLongTerm => &mut params.mu_long_term,
+                SweepField::Lambda => &mut params.lambda,
+                SweepField::Strike => &mut params.strike,
+            };
+            *field = value as f32;
+            let params = prepare_rerun(params)?;
+            let (mut stats, _, _, _) = simulate(&params, hist_log_returns, &RunControl::default())?;
+            stats.implied_mu = (params.target_mean_price > 0.0).then_some(params.mu as f64);
+            Ok((value, stats))
+        })
+        .collect()
+}
+
+//the same scenario under each model, stats only (no plotting, no greeks). Models run as parallel
+//rayon tasks while each run stays parallel over its paths, work stealing shares the one pool
+//between both levels. Every model draws from the same resolved seed, and base supplies each
+//model's own parameter fields. Any failing model fails the whole ensemble, named in the error
+pub fn run_model_ensemble(base: CoreSimParams, models: &[&str], hist_log_returns: &[f64]) -> Result<Vec<(String, SimStats)>> {
+    let runs = ensemble_runs(CoreSimParams { store_paths: false, ..base }, models, hist_log_returns)?;
+    Ok(runs.into_iter().map(|(model, stats, _)| (model, stats)).collect())
+}
+
+//run_model_ensemble plus every model's paths on shared axes. The chart only has lines when
+//base.store_paths is on
+pub fn run_model_ensemble_overlay(base: CoreSimParams, models: &[&str], hist_log_returns: &[f64], max_displayed_paths: usize, chart: ChartConfig) -> Result<(Vec<(String, SimStats)>, ChartBuffer)> {
+    let runs = ensemble_runs(base, models, hist_log_returns)?;
+    let sets: Vec<_> = runs.iter().zip(ENSEMBLE_COLORS.iter().cycle())
+        .map(|((model, _, paths), &color)| (model.as_str(), paths.as_slice(), color))
+        .collect();
+    let overlay = crate::plotting::plot_price_paths_overlay(&sets, max_displayed_paths, chart)?;
+    Ok((runs.into_iter().map(|(model, stats, _)| (model, stats)).collect(), overlay))
+}
+
+//model, its stats and its paths (empty when store_paths is off)
+type EnsembleRun = (String, SimStats, Vec<Vec<f64>>);
+
+fn ensemble_runs(base: CoreSimParams, models: &[&str], hist_log_returns: &[f64]) -> Result<Vec<EnsembleRun>> {
+    if models.is_empty() {
+        return Err(anyhow!("Ensemble needs at least one model"));
+    }
+    let base = resolve_seed(base);
+
+    models
+        .par_iter()
+        .map(|&model| {
+            prepare_rerun(CoreSimParams { model_type: model.into(), ..base.clone() })
+                .and_then(|params| {
+                    let (mut stats, paths, _, _) = simulate(&params, hist_log_returns, &RunControl::default())?;
+                    stats.implied_mu = (params.target_mean_price > 0.0).then_some(params.mu as f64);
+                    Ok((model.to_string(), stats, paths))
+                })
+                .map_err(|e| anyhow!("{}: {}", model, e))
+        })
+        .collect()
+}
+
+//a single stats-only run (no plotting, no greeks, no stored paths), for screening many inputs
+pub fn simulate_stats(params: CoreSimParams, hist_log_returns: &[f64], control: &RunControl) -> Result<SimStats> {
+    validate_params(&params)?;
+    let params = resolve_drift_target(resolve_seed(CoreSimParams { store_paths: false, ..params }));
+    let (mut stats, _, _, _) = simulate(&params, hist_log_returns, control)?;
+    stats.implied_mu = (params.target_mean_price > 0.0).then_some(params.mu as f64);
+    Ok(stats)
+}
+
+//stats, stored paths, terminal prices and the diagnosed path's steps
+type SimRun = (SimStats, Vec<Vec<f64>>, Vec<f64>, Vec<StepDiagnostics>);
+
+//runs the paths and computes stats without plotting, also returns the paths and terminal prices
+fn simulate(params: &CoreSimParams, hist_log_returns: &[f64], control: &RunControl) -> Result<SimRun> {
+    //model_type is free-form text from the UI and the generator has no paths for an unknown one,
+    //so reject it before the parallel loop. compute_greeks gets here without validate_params
+    if !MODEL_TYPES.contains(&params.model_type.as_str()) {
+        return Err(anyhow!("Unknown model type: {}", params.model_type));
+    }
+    //resampling has nothing to draw from without history
+    if params.model_type == "Bootstrap" && hist_log_returns.is_empty() {
+        return Err(anyhow!("Bootstrap needs historical returns, select a ticker first"));
+    }
+    //the stress applies to a copy, so callers keep their estimates as entered
+    let stressed;
+    let params = if is_stressed(params) {
+        stressed = apply_stress(params);
+        &stressed
+    } else {
+        params
+    };
+    let init_price = params.initial_price as f64;
+    let mu = params.mu as f64;
+    let sigma = params.sigma as f64;
+    let horizon = params.horizon as usize;
+    //antithetic runs are whole pairs, an odd request gets one more mirror for its last base draw
+    let num_paths = if params.use_antithetic { (params.num_paths as usize).next_multiple_of(2) } else { params.num_paths as usize };
+    let dt = step_dt(params);
+    let confidence_level = params.confidence_level as f64;
+    let model_name = params.model_type.as_str();
+
+    //Sobol points are generated up front (sequence is sequential), one point per path.
+    //Antithetic negation would break the low-discrepancy structure, so the two are exclusive.
+    //Bootstrap ignores quasi-random draws and JumpDiffusion only uses them for the diffusion part.
+    let quasi_normals = if params.use_quasi_random {
+        let normals_per_step = if params.model_type == "Heston" || params.model_type == "SABR" { 2 } else { 1 };
+        Some(sobol_normals(num_paths, horizon * normals_per_step)?)
+    } else {
+        None
+    };
+
+    let std_normal = StatNormal::new(0.0, 1.0)?;
+
+    //fat-tailed shocks for GBM and the jump models, rescaled by sqrt((df-2)/df) to unit variance.
+    //t is symmetric, so antithetic negation still produces a valid mirrored draw
+    let student_t = if params.shock_distribution == ShockDistribution::StudentT
+        && matches!(params.model_type.as_str(), "GBM" | "JumpDiffusion" | "HawkesJump")
+    {
+        let df = params.student_t_df as f64;
+        if params.use_quasi_random {
+            return Err(anyhow!("Quasi-random sampling only supports normal shocks"));
+        }
+        let t = StudentT::new(df).map_err(|e| anyhow!("Invalid Student-t distribution: {}", e))?;
+        let std_t = StatStudentsT::new(0.0, 1.0, df)?;
+        Some((t, std_t, ((df - 2.0) / df).sqrt()))
+    } else {
+        None
+    };
+
+    //model-free reference from the raw history, before the Bootstrap model demeans it. The
+    //horizon is counted in bars of the history's own frequency
+    let historical_var = (!hist_log_returns.is_empty())
+        .then(|| historical_var(hist_log_returns, horizon_history_bars(params), confidence_level));
+
+    //demeaned bootstrap drops the historical drift and resamples pure volatility
+    let demeaned;
+    let hist_log_returns = if params.model_type == "Bootstrap" && params.bootstrap_demean && !hist_log_returns.is_empty() {
+        let mean = hist_log_returns.iter().sum::<f64>() / hist_log_returns.len() as f64;
+        demeaned = hist_log_returns.iter().map(|r| r - mean).collect::<Vec<f64>>();
+        demeaned.as_slice()
+    } else {
+        hist_log_returns
+    };
+    //a stressed Bootstrap resamples mean + f·(r - mean) + shift: wider moves around a shifted drift
+    let stressed_returns;
+    let hist_log_returns = if params.model_type == "Bootstrap" && is_stressed(params) && !hist_log_returns.is_empty() {
+        let mean = hist_log_returns.iter().sum::<f64>() / hist_log_returns.len() as f64;
+        let factor = stress_factor(params);
+        let shift = params.stress_shift_mu as f64;
+        stressed_returns = hist_log_returns.iter().map(|r| mean + factor * (r - mean) + shift).collect::<Vec<f64>>();
+        stressed_returns.as_slice()
+    } else {
+        hist_log_returns
+    };
+    let hist_mean = if hist_log_returns.is_empty() { 0.0 } else { hist_log_returns.iter().sum::<f64>() / hist_log_returns.len() as f64 };
+
+    //antithetic pairs: num_paths / 2 base draws, path 2k+1 replays the exact stream of path 2k
+    //(same stream id, same draws) and its generator negates every normal, so the two mirror each
+    //other and only half as many streams are drawn. Bootstrap partners replay the same index
+    //sequence and mirror each return around the historical mean
+    let paths_per_stream = if params.use_antithetic { 2 } else { 1 };
+    let num_streams = num_paths.div_ceil(paths_per_stream);
+
+    //per-step GBM drift/vol term structure, steps past the end of a schedule use the scalar
+    let (mu_schedule, sigma_schedule) = if params.model_type == "GBM" {
+        let shift = params.stress_shift_mu as f64;
+        let factor = stress_factor(params);
+        (
+            parse_schedule(&params.mu_schedule)?.into_iter().map(|mu| mu + shift).collect(),
+            parse_schedule(&params.sigma_schedule)?.into_iter().map(|sigma| sigma * factor).collect(),
+        )
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    if sigma_schedule.iter().any(|&s| s < 0.0) {
+        return Err(anyhow!("Sigma schedule values must be non-negative"));
+    }
+
+    //importance sampling draws every step's normal from N(θ, 1), the same as raising the drift by
+    //σθ/√dt. θ = z_{1-c}/√n centres the sampled terminal log return on the VaR quantile, so about
+    //half the paths land in the tail instead of 1 - c of them
+    let importance_shift = (params.variance_reduction == VarianceReduction::ImportanceSampling).then(|| {
+        validate_importance_sampling(params)?;
+        Ok(std_normal.inverse_cdf(1.0 - confidence_level) / (horizon as f64).sqrt())
+    }).transpose()?;
+    let shifted_params;
+    let sampling_params = match importance_shift {
+        Some(theta) => {
+            shifted_params = CoreSimParams { mu: (mu + sigma * theta / dt.sqrt()) as f32, ..params.clone() };
+            &shifted_params
+        }
+        None => params,
+    };
+
+    //the surface is read once per run, every path interpolates the same grid
+    let local_vol = if params.model_type == "LocalVol" {
+        Some(crate::data_io::load_vol_surface(Path::new(params.local_vol_file.trim()))?.scaled(stress_factor(params)))
+    } else {
+        None
+    };
+
+    let generator = PathGenerator {
+        params: sampling_params,
+        hist_log_returns,
+        hist_mean,
+        mu_schedule: &mu_schedule,
+        sigma_schedule: &sigma_schedule,
+        quasi_normals,
+        std_normal,
+        student_t,
+        local_vol,
+        paths_per_stream,
+        num_streams,
+    };
+
+    //without stored paths each task keeps a 32 byte PathSummary instead of a (horizon+1)*8 byte
+    //Vec: a 1M path, 252 step GBM run peaks at ~126 MB resident instead of ~2.1 GB
+    let (mut paths, mut summaries): (Vec<Vec<f64>>, Vec<PathSummary>) = if params.store_paths {
+        let paths: Vec<Vec<f64>> = generator.run(num_paths, control)?;
+        let summaries = paths.iter().map(|path| PathSummary::of(path)).collect();
+        (paths, summaries)
+    } else {
+        (Vec::new(), generator.run(num_paths, control)?)
+    };
+    //one extra path, replayed rather than recorded inside the parallel run
+    let diagnostics = if params.record_diagnostics {
+        generator.diagnose((params.diagnostic_path.max(0) as usize).min(num_paths - 1))
+    } else {
+        Vec::new()
+    };
+
+    //exp overflow or a variance blowup leaves inf/NaN prices that would poison every statistic.
+    //A few stray paths are dropped, whole antithetic pairs at a time so pairs stay aligned
+    let diverged_paths = summaries.iter().filter(|s| s.is_diverged()).count();
+    if diverged_paths as f64 > MAX_DIVERGED_FRACTION * num_paths as f64 {
+        return Err(anyhow!(
+            "{} of {} paths diverged to non-finite prices (limit {}%), reduce volatility, horizon or dt",
+            diverged_paths, num_paths, MAX_DIVERGED_FRACTION * 100.0
+        ));
+    }
+    if diverged_paths > 0 {
+        let keep: Vec<bool> = summaries.chunks(paths_per_stream)
+            .flat_map(|group| std::iter::repeat_n(!group.iter().any(|s| s.is_diverged()), group.len()))
+            .collect();
+        let mut flags = keep.iter();
+        summaries.retain(|_| *flags.next().unwrap_or(&true));
+        if !paths.is_empty() {
+            let mut flags = keep.iter();
+            paths.retain(|_| *flags.next().unwrap_or(&true));
+        }
+    }
+
+    let (mut terminal_prices, max_drawdowns): (Vec<f64>, Vec<f64>) = summaries.iter()
+        .map(|summary| (summary.terminal, summary.max_drawdown))
+        .unzip();
+
+    let barrier_option_price = if params.price_option && params.price_barrier {
+        Some(price_barrier_option(&summaries, params.strike as f64, params.barrier as f64, params.is_up_and_out, params.is_call, params.discount_rate as f64, horizon as f64 * dt))
+    } else {
+        None
+    };
+
+    //option payoffs use the raw terminal prices like every statistic but the control-variate mean
+    let option = if params.price_option {
+        let strike = params.strike as f64;
+        let rate = params.discount_rate as f64;
+        let t = horizon as f64 * dt;
+        let price = price_european_option(&terminal_prices, strike, params.is_call, rate, t);
+        let payoffs = european_payoffs(&terminal_prices, strike, params.is_call, (-rate * t).exp());
+        let std_error = estimate_paramaters(&payoffs).map(|(_, sd)| sd / (payoffs.len() as f64).sqrt()).unwrap_or(0.0);
+        Some((price, std_error))
+    } else {
+        None
+    };
+
+    //likelihood ratios that turn shifted-sampling averages back into plain expectations
+    let weights: Option<Vec<f64>> = importance_shift.map(|theta| {
+        terminal_prices.iter().map(|&price| importance_weight(price, init_price, mu, sigma, horizon, dt, theta)).collect()
+    });
+
+    //probabilities count actual outcomes, so like the option they use the unadjusted prices
+    let (prob_above_target, loss_probability) = match &weights {
+        Some(weights) => (
+            (params.target_price > 0.0).then(|| weighted_share(&terminal_prices, weights, |price| price > params.target_price as f64)),
+            weighted_share(&terminal_prices, weights, |price| price < init_price),
+        ),
+        None => (
+            (params.target_price > 0.0).then(|| probability_above(&terminal_prices, params.target_price as f64)),
+            probability_below(&terminal_prices, init_price),
+        ),
+    };
+
+    //the adjustment shifts each price by its control, which is only valid for the mean, so the
+    //prices behind quantiles, tails and the histogram stay raw
+    let control_variate = (params.model_type == "GBM" && params.variance_reduction == VarianceReduction::ControlVariate).then(|| {
+        let log_drift = (0..horizon)
+            .map(|step| {
+                let mu = mu_schedule.get(step).copied().unwrap_or(mu);
+                let sigma = sigma_schedule.get(step).copied().unwrap_or(sigma);
+                (mu - 0.5 * sigma.powi(2)) * dt
+            })
+            .sum();
+        control_variate_mean(&terminal_prices, init_price, log_drift, paths_per_stream == 2)
+    });
+
+    let mut stats = if let Some(weights) = &weights {
+        weighted_statistics(&terminal_prices, weights, &max_drawdowns, model_name, num_paths, horizon, init_price, confidence_level, params.output_mode)?
+    } else if params.approximate_quantiles && terminal_prices.len() >= APPROX_QUANTILE_MIN_PATHS {
+        approximate_statistics(&terminal_prices, &max_drawdowns, model_name, num_paths, horizon, init_price, confidence_level, params.output_mode)?
+    } else {
+        calculate_statistics(&mut terminal_prices, &max_drawdowns, model_name,num_paths, horizon, init_price, confidence_level, params.output_mode)?
+    };
+    if let Some((mean, std_error, ratio)) = control_variate {
+        let (shift, scale) = if params.output_mode == OutputMode::Return { (init_price, init_price) } else { (0.0, 1.0) };
+        stats.cv_mean = Some((mean - shift) / scale);
+        stats.cv_std_error = Some(std_error / scale);
+        stats.variance_reduction_ratio = ratio;
+    }
+    stats.diverged_paths = diverged_paths;
+    stats.seed = params.seed as u64;
+    let (sharpe_ratio, sortino_ratio) = risk_adjusted_ratios(&terminal_prices, weights.as_deref(), init_price, params.risk_free_rate as f64, horizon as f64 * dt, units_per_year(params));
+    stats.sharpe_ratio = sharpe_ratio;
+    stats.sortino_ratio = sortino_ratio;
+    stats.prob_above_target = prob_above_target;
+    stats.loss_probability = loss_probability;
+    //antithetic partners are negatively correlated, so std_dev / sqrt(paths) would overstate
+    //the error; each pair mean is one independent draw
+    if paths_per_stream == 2 {
+        let scale = if params.output_mode == OutputMode::Return { init_price } else { 1.0 };
+        stats.mean_std_error = paired_std_error(&terminal_prices) / scale;
+        stats.effective_samples = terminal_prices.len() / 2;
+    }
+    stats.option_price = option.map(|(price, _)| price);
+    stats.option_std_error = option.map(|(_, se)| se);
+    stats.barrier_option_price = barrier_option_price;
+
+    //the benchmark only holds for constant-parameter GBM with normal shocks
+    if params.model_type == "GBM" && params.shock_distribution == ShockDistribution::Normal && mu_schedule.is_empty() && sigma_schedule.is_empty() {
+        let (mean, median, variance) = gbm_analytic_stats(init_price, mu, sigma, horizon, dt);
+        let (shift, scale) = if params.output_mode == OutputMode::Return { (init_price, init_price) } else { (0.0, 1.0) };
+        stats.analytic_mean = Some((mean - shift) / scale);
+        stats.analytic_median = Some((median - shift) / scale);
+        stats.analytic_std_dev = Some(variance.sqrt() / scale);
+    }
+    stats.historical_var = historical_var;
+    stats.stress_factor_sigma = stress_factor(params);
+    stats.stress_shift_mu = params.stress_shift_mu as f64;
+
+    Ok((stats, paths, terminal_prices, diagnostics))
+}
+
+//σ(S, t) on a spot x time grid, sigmas[i][j] is the vol at spots[i] and times[j]. Bilinear
+//between nodes and flat beyond the edges. Times count the same units as dt: years with a
+//calendar step frequency, trading days with PerStep
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolSurface {
+    spots: Vec<f64>,
+    times: Vec<f64>,
+    sigmas: Vec<Vec<f64>>,
+}
+
+impl VolSurface {
+    pub fn new(spots: Vec<f64>, times: Vec<f64>, sigmas: Vec<Vec<f64>>) -> Result<Self> {
+        if spots.is_empty() || times.is_empty() {
+            return Err(anyhow!("Vol surface needs at least one spot and one time"));
+        }
+        for (name, axis) in [("spot", &spots), ("time", &times)] {
+            if axis.iter().any(|v| !v.is_finite()) || axis.windows(2).any(|w| w[0] >= w[1]) {
+                return Err(anyhow!("Vol surface {} levels must be finite and strictly increasing", name));
+            }
+        }
+        if sigmas.len() != spots.len() || sigmas.iter().any(|row| row.len() != times.len()) {
+            return Err(anyhow!("Vol surface must have one row per spot level and one column per time"));
+        }
+        if sigmas.iter().flatten().any(|&v| !v.is_finite() || v < 0.0) {
+            return Err(anyhow!("Vol surface values must be finite and non-negative"));
+        }
+        Ok(VolSurface { spots, times, sigmas })
+    }
+
+    //every node times factor, for stress scenarios
+    pub fn scaled(self, factor: f64) -> Self {
+        let sigmas = self.sigmas.into_iter().map(|row| row.into_iter().map(|v| v * factor).collect()).collect();
+        VolSurface { sigmas, ..self }
+    }
+
+    //single node, the constant-vol surface
+    pub fn flat(sigma: f64) -> Self {
+        VolSurface { spots: vec![0.0], times: vec![0.0], sigmas: vec![vec![sigma]] }
+    }
+
+    pub fn sigma(&self, spot: f64, t: f64) -> f64 {
+        let (i, ws) = grid_position(&self.spots, spot);
+        let (j, wt) = grid_position(&self.times, t);
+        let i1 = (i + 1).min(self.spots.len() - 1);
+        let j1 = (j + 1).min(self.times.len() - 1);
+        let near_t = self.sigmas[i][j] * (1.0 - ws) + self.sigmas[i1][j] * ws;
+        let far_t = self.sigmas[i][j1] * (1.0 - ws) + self.sigmas[i1][j1] * ws;
+        near_t * (1.0 - wt) + far_t * wt
+    }
+}
+
+//node at or below x and the weight of the node after it, pinned to the first or last node
+//outside the grid
+fn grid_position(axis: &[f64], x: f64) -> (usize, f64) {
+    let last = axis.len() - 1;
+    if x.is_nan() || x <= axis[0] {
+        return (0, 0.0);
+    }
+    if x >= axis[last] {
+        return (last, 0.0);
+    }
+    let i = axis.partition_point(|&node| node <= x) - 1;
+    (i, (x - axis[i]) / (axis[i + 1] - axis[i]))
+}
+
+//per-simulation state shared by every path, each path only depends on it and its index
+struct PathGenerator<'a> {
+    params: &'a CoreSimParams,
+    hist_log_returns: &'a [f64],
+    hist_mean: f64,
+    mu_schedule: &'a [f64],
+    sigma_schedule: &'a [f64],
+    quasi_normals: Option<Vec<Vec<f64>>>,
+    std_normal: StatNormal,
+    student_t: Option<(StudentT<f64>, StatStudentsT, f64)>,
+    local_vol: Option<VolSurface>,
+    paths_per_stream: usize,
+    num_streams: usize,
+}
+
+impl PathGenerator<'_> {
+    //exact mode: every stream (path or antithetic pair) gets its own keystream, so any path
+    //can be regenerated on its own
+    fn path_rng(&self, i: usize) -> PathRng {
+        let mut rng = PathRng::seed_from_u64(self.params.seed as u64);
+        rng.set_stream((i / self.paths_per_stream) as u64);
+        rng
+    }
+
+    fn generate<P: PathSink>(&self, i: usize, rng: &mut PathRng) -> P {
+        let params = self.params;
+        let init_price = params.initial_price as f64;
+        let mu = params.mu as f64;
+        let sigma = params.sigma as f64;
+        let horizon = params.horizon as usize;
+        let dt = step_dt(params);
+        let stream = i / self.paths_per_stream;
+
+        //stratified: each stream draws its first shock from its own one of num_streams
+        //equiprobable strata, a mirrored partner then lands in the mirrored stratum
+        let first_shock = if params.variance_reduction == VarianceReduction::Stratified {
+            let u = ((stream as f64 + rng.random::<f64>()) / self.num_streams as f64).clamp(1e-12, 1.0 - 1e-12);
+            match &self.student_t {
+                Some((_, std_t, scale)) => Some(std_t.inverse_cdf(u) * scale),
+                None => Some(self.std_normal.inverse_cdf(u)),
+            }
+        } else {
+            None
+        };
+        let shocks = ShockSource {
+            quasi_normals: self.quasi_normals.as_ref().map(|q| q[i].as_slice()),
+            first_shock,
+            student_t: self.student_t.as_ref().map(|(t, _, scale)| (*t, *scale)),
+            deterministic: params.deterministic,
+        };
+
+        match params.model_type.as_str() {
+            "GBM" => generate_gbm_path(init_price, mu, sigma, self.mu_schedule, self.sigma_schedule, horizon, dt, params.use_antithetic && (i%2==1), shocks, rng),
+            "Bootstrap" => generate_bootstrap_path(init_price, horizon, self.hist_log_returns, self.hist_mean, params.block_size.max(1) as usize, params.use_antithetic && (i%2==1), rng),
+            "MeanReversion" => {
+                let theta = params.theta as f64;
+                let mu_long_term = params.mu_long_term as f64;
+                let sigma = params.sigma as f64;
+                generate_mean_reversion_path(init_price, theta, mu_long_term, sigma, horizon, dt, params.use_antithetic && (i%2==1), shocks, rng)
+            }
+            "JumpDiffusion" => {
+                let mu = params.mu as f64;
+                let sigma = params.sigma as f64;
+                let lambda = params.lambda as f64;
+                let mu_j = params.mu_j as f64;
+                let sigma_j = params.sigma_j as f64;
+                generate_jump_diffusion_path(init_price, mu, sigma, lambda, 0.0, 0.0, mu_j, sigma_j, horizon, dt, params.use_antithetic && (i%2==1), shocks, rng)
+            }
+            "HawkesJump" => {
+                let lambda = params.lambda as f64;
+                let excitation = params.hawkes_excitation as f64;
+                let decay = params.hawkes_decay as f64;
+                let mu_j = params.mu_j as f64;
+                let sigma_j = params.sigma_j as f64;
+                generate_jump_diffusion_path(init_price, mu, sigma, lambda, excitation, decay, mu_j, sigma_j, horizon, dt, params.use_antithetic && (i%2==1), shocks, rng)
+            }
+            "GARCH" => {
+                let omega = params.omega as f64;
+                let alpha = params.alpha as f64;
+                let beta = params.beta as f64;
+                generate_garch_path(init_price, omega, alpha, beta, horizon, dt, params.use_antithetic && (i%2==1), shocks, rng)
+            }
+            "Heston" => {
+                let v0 = params.v0 as f64;
+                let kappa = params.kappa as f64;
+                let theta_v = params.theta_v as f64;
+                let xi = params.xi as f64;
+                let rho = params.rho as f64;
+                generate_heston_path(init_price, mu, v0, kappa, theta_v, xi, rho, horizon, dt, params.use_antithetic && (i%2==1), shocks, rng)
+            }
+            "CIR" => {
+                let kappa = params.cir_kappa as f64;
+                let theta = params.cir_theta as f64;
+                generate_cir_path(init_price, kappa, theta, sigma, horizon, dt, params.use_antithetic && (i%2==1), shocks, rng)
+            }
+            "Vasicek" => {
+                let kappa = params.vasicek_kappa as f64;
+                let theta = params.vasicek_theta as f64;
+                generate_vasicek_path(init_price, kappa, theta, sigma, horizon, dt, params.use_antithetic && (i%2==1), shocks, rng)
+            }
+            "SABR" => {
+                let alpha = params.sabr_alpha as f64;
+                let beta = params.sabr_beta as f64;
+                let rho = params.sabr_rho as f64;
+                let nu = params.sabr_nu as f64;
+                generate_sabr_path(init_price, alpha, beta, rho, nu, horizon, dt, params.use_antithetic && (i%2==1), shocks, rng)
+            }
+            "LocalVol" => {
+                let Some(surface) = &self.local_vol else {
+                    return P::with_capacity(0);
+                };
+                generate_local_vol_path(init_price, mu, |spot, t| surface.sigma(spot, t), horizon, dt, params.use_antithetic && (i%2==1), shocks, rng)
+            }
+            "RegimeSwitching" => {
+                let sigma_low = params.sigma_low as f64;
+                let sigma_high = params.sigma_high as f64;
+                let p_low_to_high = params.p_low_to_high as f64;
+                let p_high_to_low = params.p_high_to_low as f64;
+                generate_regime_switching_path(init_price, mu, sigma_low, sigma_high, p_low_to_high, p_high_to_low, horizon, dt, params.use_antithetic && (i%2==1), shocks, rng)
+            }
+            //simulate rejects unknown models before any path is generated
+            _ => P::with_capacity(0),
+        }
+    }
+
+    fn chunk_rng(&self, c: usize) -> PathRng {
+        let mut rng = PathRng::seed_from_u64(self.params.seed as u64);
+        rng.set_stream(c as u64);
+        rng
+    }
+
+    //fast mode step: chunks are even sized, so an antithetic partner replays a clone of the chunk
+    //keystream taken before its even path
+    fn generate_in_chunk<P: PathSink>(&self, i: usize, rng: &mut PathRng, partner: &mut Option<PathRng>) -> P {
+        if self.paths_per_stream == 2 {
+            if i.is_multiple_of(2) {
+                *partner = Some(rng.clone());
+            } else if let Some(mut partner_rng) = partner.take() {
+                return self.generate(i, &mut partner_rng);
+            }
+        }
+        self.generate(i, rng)
+    }
+
+    //regenerates path i with its step diagnostics, from the same draws as in run. Fast mode has
+    //to replay the chunk's earlier paths to reach i's position in the shared keystream
+    fn diagnose(&self, i: usize) -> Vec<StepDiagnostics> {
+        let path: DiagnosticPath = if self.params.fast_mode {
+            let c = i / PROGRESS_CHUNK;
+            let mut rng = self.chunk_rng(c);
+            let mut partner = None;
+            for j in c * PROGRESS_CHUNK..i {
+                self.generate_in_chunk::<PathSummary>(j, &mut rng, &mut partner);
+            }
+            self.generate_in_chunk(i, &mut rng, &mut partner)
+        } else {
+            self.generate(i, &mut self.path_rng(i))
+        };
+        path.0
+    }
+
+    //chunks run in parallel, each checks the cancel flag once and bumps the shared counter once
+    fn run<P: PathSink + Send>(&self, num_paths: usize, control: &RunControl) -> Result<Vec<P>> {
+        let completed = AtomicUsize::new(0);
+        let chunks: Option<Vec<Vec<P>>> = (0..num_paths.div_ceil(PROGRESS_CHUNK)).into_par_iter().map(|c| {
+            if control.is_cancelled() {
+                return None;
+            }
+            let start = c * PROGRESS_CHUNK;
+            let end = (start + PROGRESS_CHUNK).min(num_paths);
+            let chunk: Vec<P> = if self.params.fast_mode {
+                //fast mode: one keystream per chunk instead of one per path. Still deterministic
+                //for a given seed, but paths depend on their chunk neighbours
+                let mut rng = self.chunk_rng(c);
+                let mut partner = None;
+                (start..end).map(|i| self.generate_in_chunk(i, &mut rng, &mut partner)).collect()
+            } else {
+                (start..end).map(|i| self.generate(i, &mut self.path_rng(i))).collect()
+            };
+            let before = completed.fetch_add(end - start, Ordering::Relaxed);
+            control.report(before, before + end - start, num_paths);
+            Some(chunk)
+        }).collect();
+        Ok(chunks
+            .ok_or_else(|| anyhow!("Simulation cancelled"))?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+}
+
+//per-path overrides for the normal draws of a generator, k is the draw index within the path
+#[derive(Clone, Copy)]
+struct ShockSource<'a> {
+    quasi_normals: Option<&'a [f64]>,
+    first_shock: Option<f64>,
+    //t distribution and the factor rescaling it to unit variance, replaces pseudo-random normals
+    student_t: Option<(StudentT<f64>, f64)>,
+    //drift-only preview: every draw is 0, which also makes antithetic negation a no-op
+    deterministic: bool,
+}
+
+impl ShockSource<'_> {
+    fn normal(&self, k: usize, normal: &Normal<f64>, rng: &mut PathRng) -> f64 {
+        if self.deterministic {
+            return 0.0;
+        }
+        if let Some(q) = self.quasi_normals {
+            return q[k];
+        }
+        if k == 0 && let Some(z) = self.first_shock {
+            return z;
+        }
+        if let Some((t, scale)) = self.student_t {
+            return t.sample(rng) * scale;
+        }
+        normal.sample(rng)
+    }
+}
+
+//comma or whitespace separated per-step values, blank means no schedule
+pub fn parse_schedule(text: &str) -> Result<Vec<f64>> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let value: f64 = s.parse().map_err(|_| anyhow!("Invalid schedule value: {}", s))?;
+            if value.is_finite() { Ok(value) } else { Err(anyhow!("Schedule values must be finite")) }
+        })
+        .collect()
+}
+
+//first num_paths Sobol points (origin skipped) mapped to standard normals by inverse CDF
+fn sobol_normals(num_paths: usize, dims: usize) -> Result<Vec<Vec<f64>>> {
+    let sobol_params = JoeKuoD6::standard();
+    if dims == 0 || dims > sobol_params.max_dims {
+        return Err(anyhow!("Quasi-random sampling supports 1 to {} dimensions, got {}", sobol_params.max_dims, dims));
+    }
+    let std_normal = StatNormal::new(0.0, 1.0)?;
+
+    Ok(Sobol::<f64>::new(dims, &sobol_params)
+        .skip(1)
+        .take(num_paths)
+        .map(|point| point.iter().map(|&u| std_normal.inverse_cdf(u.clamp(1e-12, 1.0 - 1e-12))).collect())
+        .collect())
+}
+
+//steps per stack buffer in generate_gbm_path, long enough for tight loops with no heap allocation
+//per path
+const GBM_CHUNK: usize = 64;
+
+//mu_schedule/sigma_schedule override the scalars step by step and may be empty or shorter than
+//steps. The antithetic partner walks the same schedule and only negates the shock, so the pair
+//mirrors around each step's own drift.
+//Steps go in fixed-size chunks of three passes: shocks in step order (same draws as a per-step
+//loop, so seeds reproduce), then growth factors, then the serial price product
+#[allow(clippy::too_many_arguments)]
+fn generate_gbm_path<P: PathSink>(init_price: f64, mu: f64, sigma: f64, mu_schedule: &[f64], sigma_schedule: &[f64], steps: usize, dt: f64, is_antithetic: bool, shocks: ShockSource, rng: &mut PathRng,) -> P {
+    //plus 1 for init_price
+    let mut path = P::with_capacity(steps+1);
+    path.push(init_price);
+
+    let sqrt_dt = dt.sqrt();
+    let normal = Normal::new(0.0, 1.0).unwrap();
+    //negating the diffusion is exactly negating z, sign flips are exact in floating point
+    let sign = if is_antithetic { -1.0 } else { 1.0 };
+    let scheduled = !mu_schedule.is_empty() || !sigma_schedule.is_empty();
+    //(drift, sigma) of one step
+    let step_params = |step: usize| {
+        let mu = mu_schedule.get(step).copied().unwrap_or(mu);
+        let sigma = sigma_schedule.get(step).copied().unwrap_or(sigma);
+        ((mu - 0.5 * sigma.powi(2)) * dt, sigma)
+    };
+    let drift = (mu - 0.5 * sigma.powi(2)) * dt;
+    let diffusion = sign * sigma * sqrt_dt;
+
+    let mut raw_shocks = [0.0; GBM_CHUNK];
+    let mut growth = [0.0; GBM_CHUNK];
+    let mut current_price = init_price;
+    for start in (0..steps).step_by(GBM_CHUNK) {
+        let len = GBM_CHUNK.min(steps - start);
+        for (offset, z) in raw_shocks[..len].iter_mut().enumerate() {
+            *z = shocks.normal(start + offset, &normal, rng);
+        }
+        if scheduled {
+            for (offset, (g, z)) in growth[..len].iter_mut().zip(&raw_shocks[..len]).enumerate() {
+                let (drift, sigma) = step_params(start + offset);
+                *g = (drift + sign * sigma * sqrt_dt * z).exp();
+            }
+        } else {
+            for (g, z) in growth[..len].iter_mut().zip(&raw_shocks[..len]) {
+                *g = (drift + diffusion * z).exp();
+            }
+        }
+
+        for (offset, g) in growth[..len].iter().enumerate() {
+            current_price *= g;
+            path.push(current_price);
+            if P::RECORDS {
+                let step = start + offset;
+                let (drift, sigma) = step_params(step);
+                path.record(StepDiagnostics { step, price: current_price, variance: sigma.powi(2), drift, shock: sign * raw_shocks[offset], jump: 0.0 });
+            }
+        }
+    }
+    path
+}
+
+//moving-block bootstrap: each block starts at a random index and then walks forward through the
+//history, wrapping at the end. block_size 1 is the plain iid resample. The antithetic partner
+//uses 2*mean - r, i.e. the negated demeaned return with the drift added back
+fn generate_bootstrap_path<P: PathSink>(init_price: f64, steps: usize, log_returns: &[f64], mean: f64, block_size: usize, is_antithetic: bool, rng: &mut PathRng) -> P {
+    let mut path = P::with_capacity(steps+1);
+    path.push(init_price);
+    if log_returns.is_empty() {
+        for _ in 0..steps {
+            path.push(init_price);
+        }
+        return path;
+    }
+
+    let block_size = block_size.max(1);
+    let mut current_price = init_price;
+    let mut idx = 0;
+
+    for step in 0..steps {
+        if step % block_size == 0 {
+            idx = rng.random_range(0..log_returns.len());
+        } else {
+            idx = (idx + 1) % log_returns.len();
+        }
+        let log_return = if is_antithetic { 2.0 * mean - log_returns[idx] } else { log_returns[idx] };
+        let next_price = current_price * log_return.exp();
+        path.push(next_price);
+        path.record(StepDiagnostics { step, price: next_price, variance: 0.0, drift: 0.0, shock: log_return, jump: 0.0 });
+        current_price = next_price;
+    }
+    path
+}
+
+//resampled horizons behind historical_var, and the fixed seed that keeps it stable across runs
+const HISTORICAL_VAR_PATHS: usize = 10_000;
+const HISTORICAL_VAR_SEED: u64 = 0x5EED;
+
+//no-model reference VaR: iid bootstrap paths of the historical log returns over the horizon
+//(in bars of the history), read at the same 1 - confidence percentile as the
+//model VaR. A loss fraction of the initial price like var95, 0.0 for no returns
+pub fn historical_var(log_returns: &[f64], horizon: usize, confidence: f64) -> f64 {
+    if log_returns.is_empty() {
+        return 0.0;
+    }
+    let returns: Vec<f64> = (0..HISTORICAL_VAR_PATHS).into_par_iter().map(|i| {
+        let mut rng = PathRng::seed_from_u64(HISTORICAL_VAR_SEED);
+        rng.set_stream(i as u64);
+        let path: PathSummary = generate_bootstrap_path(1.0, horizon, log_returns, 0.0, 1, false, &mut rng);
+        path.terminal - 1.0
+    }).collect();
+    -Data::new(returns).quantile(1.0 - confidence)
+}
+
+//resamples behind the VaR confidence interval, and the fixed seed that keeps it stable across runs
+const VAR_CI_RESAMPLES: usize = 1_000;
+const BOOTSTRAP_CI_SEED: u64 = 0xB007;
+
+//percentile bootstrap interval for a statistic of the terminal prices: the statistic is recomputed
+//on n_resamples draws of the prices with replacement and the interval is the middle confidence
+//share of those values. Plain fns like a mean or median work as well as closures such as a VaR
+//at a chosen level. (0.0, 0.0) for no prices
+pub fn bootstrap_ci<F>(terminal_prices: &[f64], statistic: F, n_resamples: usize, confidence: f64) -> (f64, f64)
+where
+    F: Fn(&[f64]) -> f64 + Sync,
+{
+    if terminal_prices.is_empty() {
+        return (0.0, 0.0);
+    }
+    if n_resamples == 0 {
+        let value = statistic(terminal_prices);
+        return (value, value);
+    }
+    let n = terminal_prices.len();
+    let estimates: Vec<f64> = (0..n_resamples).into_par_iter().map(|i| {
+        let mut rng = PathRng::seed_from_u64(BOOTSTRAP_CI_SEED);
+        rng.set_stream(i as u64);
+        let sample: Vec<f64> = (0..n).map(|_| terminal_prices[rng.random_range(0..n)]).collect();
+        statistic(&sample)
+    }).collect();
+    let tail = (1.0 - confidence) / 2.0;
+    let mut estimates = Data::new(estimates);
+    (estimates.quantile(tail), estimates.quantile(1.0 - tail))
+}
+
+//95% bootstrap interval of the VaR at confidence, a loss fraction of init_price like var95
+fn var_ci(terminal_prices: &[f64], init_price: f64, confidence: f64) -> (f64, f64) {
+    let tail = 1.0 - confidence;
+    let var = |prices: &[f64]| -Data::new(prices.iter().map(|&price| (price - init_price) / init_price).collect::<Vec<f64>>()).quantile(tail);
+    bootstrap_ci(terminal_prices, var, VAR_CI_RESAMPLES, 0.95)
+}
+
+pub fn estimate_paramaters(log_returns: &[f64]) -> Result<(f64, f64)> {
+    if log_returns.len() < 2 {
+        return Err(anyhow!("Not enough data to estimate parameters. Neet at least 2 log returns."));
+    }
+    let data = Data::new(log_returns.to_vec());
+    let mu = data.mean().unwrap_or(0.0);
+    let sigma = data.std_dev().unwrap_or(0.0);
+
+    Ok((mu, sigma))
+}
+
+//sample std dev of each window of consecutive returns, per step like estimate_paramaters.
+//Entry i covers returns i..i + window, empty when there are fewer returns than the window
+pub fn rolling_volatility(log_returns: &[f64], window: usize) -> Vec<f64> {
+    if window < 2 {
+        return Vec::new();
+    }
+    log_returns
+        .windows(window)
+        .map(|w| {
+            let mean = w.iter().sum::<f64>() / window as f64;
+            (w.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (window - 1) as f64).sqrt()
+        })
+        .collect()
+}
+
+//clamps values below the tail_pct percentile and above the (100 - tail_pct) one to those
+//percentiles, so a single bad tick can't inflate the estimated sigma. Keeps length and order
+pub fn winsorize_returns(log_returns: &[f64], tail_pct: f64) -> Vec<f64> {
+    if log_returns.is_empty() {
+        return Vec::new();
+    }
+    let mut sorted = log_returns.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let percentile = |p: f64| {
+        let pos = p * (sorted.len() - 1) as f64;
+        let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+        sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+    };
+    let tail = tail_pct.clamp(0.0, 50.0) / 100.0;
+    let (floor, cap) = (percentile(tail), percentile(1.0 - tail));
+    log_returns.iter().map(|r| r.clamp(floor, cap)).collect()
+}
+
+//price series rebuilt from winsorized step-to-step changes, for the OU fit which works on
+//levels rather than returns. Starts at the first observed price
+pub fn winsorize_price_changes(prices: &[f64], tail_pct: f64) -> Vec<f64> {
+    let Some(&first) = prices.first() else {
+        return Vec::new();
+    };
+    let changes: Vec<f64> = prices.windows(2).map(|w| w[1] - w[0]).collect();
+    std::iter::once(first)
+        .chain(winsorize_returns(&changes, tail_pct).into_iter().scan(first, |level, change| {
+            *level += change;
+            Some(*level)
+        }))
+        .collect()
+}
+
+//returns further than this many std devs from the mean are treated as jumps
+const JUMP_THRESHOLD_STD: f64 = 3.0;
+
+//Merton jump-diffusion calibration: returns (mu, sigma, lambda, mu_j, sigma_j) per step
+pub fn estimate_jump_params(log_returns: &[f64]) -> Result<(f64, f64, f64, f64, f64)> {
+    let (mean, std) = estimate_paramaters(log_returns)?;
+    let threshold = JUMP_THRESHOLD_STD * std;
+
+    let (jumps, diffusive): (Vec<f64>, Vec<f64>) = log_returns.iter().partition(|&&r| (r - mean).abs() > threshold);
+    let (mu, sigma) = if diffusive.len() >= 2 {
+        estimate_paramaters(&diffusive)?
+    } else {
+        (mean, std)
+    };
+
+    //intensity = fraction of steps with a jump, jump size = excess over the diffusive drift
+    let lambda = jumps.len() as f64 / log_returns.len() as f64;
+    let jump_sizes: Vec<f64> = jumps.iter().map(|r| r - mu).collect();
+    let mu_j = if jump_sizes.is_empty() {
+        0.0
+    } else {
+        jump_sizes.iter().sum::<f64>() / jump_sizes.len() as f64
+    };
+    //method of moments: Var(J) = Var(jump returns) - sigma^2, floored at zero
+    let sigma_j = if jump_sizes.len() >= 2 {
+        let var_j = jump_sizes.iter().map(|j| (j - mu_j).powi(2)).sum::<f64>() / (jump_sizes.len() - 1) as f64;
+        (var_j - sigma.powi(2)).max(0.0).sqrt()
+    } else {
+        0.0
+    };
+
+    Ok((mu, sigma, lambda, mu_j, sigma_j))
+}
+
+//OU fit by OLS of dS = a + b*S + e: theta = -b/dt, long-term mean = -a/b, sigma = std(e)/sqrt(dt)
+pub fn estimate_ou_params(prices: &[f64], dt: f64) -> Result<(f64, f64, f64)> {
+    if prices.len() < 3 {
+        return Err(anyhow!("Not enough data to estimate OU parameters. Need at least 3 prices."));
+    }
+
+    let levels = &prices[..prices.len() - 1];
+    let increments: Vec<f64> = prices.windows(2).map(|w| w[1] - w[0]).collect();
+    let n = levels.len() as f64;
+
+    let mean_x = levels.iter().sum::<f64>() / n;
+    let mean_y = increments.iter().sum::<f64>() / n;
+    let sxx: f64 = levels.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = levels.iter().zip(&increments).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    if sxx <= 0.0 {
+        return Err(anyhow!("Prices are constant, cannot estimate OU parameters."));
+    }
+
+    let b = sxy / sxx;
+    let a = mean_y - b * mean_x;
+    if b >= 0.0 {
+        return Err(anyhow!("No mean reversion detected in price history (slope {:.6} >= 0).", b));
+    }
+
+    let residual_var = levels.iter().zip(&increments)
+        .map(|(x, y)| (y - a - b * x).powi(2))
+        .sum::<f64>() / (n - 2.0).max(1.0);
+
+    let theta = -b / dt;
+    let mu_long_term = -a / b;
+    let sigma = residual_var.sqrt() / dt.sqrt();
+
+    Ok((theta, mu_long_term, sigma))
+}
+
+//GARCH(1,1) fit by Gaussian maximum likelihood, returns (omega, alpha, beta)
+pub fn estimate_garch_params(log_returns: &[f64]) -> Result<(f64, f64, f64)> {
+    if log_returns.len() < 10 {
+        return Err(anyhow!("Not enough data to estimate GARCH parameters. Need at least 10 log returns."));
+    }
+    let (mean, std) = estimate_paramaters(log_returns)?;
+    let sample_var = std.powi(2);
+    if sample_var <= 0.0 {
+        return Err(anyhow!("Log returns have zero variance, cannot estimate GARCH parameters."));
+    }
+    let residuals: Vec<f64> = log_returns.iter().map(|r| r - mean).collect();
+
+    //start from a typical persistence and match the unconditional variance
+    let start = [sample_var * 0.1, 0.1, 0.8];
+    let best = nelder_mead(|p| garch_neg_log_likelihood(&residuals, sample_var, p), start, 1000, 1e-12);
+
+    let (omega, alpha, beta) = (best[0], best[1], best[2]);
+    if !(omega > 0.0 && alpha >= 0.0 && beta >= 0.0 && alpha + beta < 1.0) {
+        return Err(anyhow!("GARCH estimation did not converge to a stationary solution."));
+    }
+    Ok((omega, alpha, beta))
+}
+
+//infinite outside the stationary region so the optimizer stays inside it
+fn garch_neg_log_likelihood(residuals: &[f64], init_var: f64, p: &[f64; 3]) -> f64 {
+    let (omega, alpha, beta) = (p[0], p[1], p[2]);
+    if omega <= 0.0 || alpha < 0.0 || beta < 0.0 || alpha + beta >= 1.0 {
+        return f64::INFINITY;
+    }
+
+    let mut variance = init_var;
+    let mut nll = 0.0;
+    for (t, r) in residuals.iter().enumerate() {
+        if t > 0 {
+            variance = omega + alpha * residuals[t - 1].powi(2) + beta * variance;
+        }
+        nll += 0.5 * (variance.ln() + r.powi(2) / variance);
+    }
+    nll
+}
+
+//gradient-free minimizer over 3 parameters (standard reflection/expansion/contraction/shrink)
+fn nelder_mead<F: Fn(&[f64; 3]) -> f64>(f: F, start: [f64; 3], max_iter: usize, tol: f64) -> [f64; 3] {
+    let mut simplex: Vec<([f64; 3], f64)> = Vec::with_capacity(4);
+    simplex.push((start, f(&start)));
+    for i in 0..3 {
+        let mut point = start;
+        point[i] = if point[i] != 0.0 { point[i] * 1.5 } else { 0.05 };
+        simplex.push((point, f(&point)));
+    }
+
+    for _ in 0..max_iter {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        if (simplex[3].1 - simplex[0].1).abs() < tol {
+            break;
+        }
+
+        let mut centroid = [0.0; 3];
+        for (point, _) in simplex.iter().take(3) {
+            for (c, x) in centroid.iter_mut().zip(point) {
+                *c += x / 3.0;
+            }
+        }
+        let along = |t: f64| -> [f64; 3] {
+            let worst = simplex[3].0;
+            [
+                centroid[0] + t * (worst[0] - centroid[0]),
+                centroid[1] + t * (worst[1] - centroid[1]),
+                centroid[2] + t * (worst[2] - centroid[2]),
+            ]
+        };
+
+        let reflected = along(-1.0);
+        let f_reflected = f(&reflected);
+        if f_reflected < simplex[0].1 {
+            let expanded = along(-2.0);
+            let f_expanded = f(&expanded);
+            simplex[3] = if f_expanded < f_reflected { (expanded, f_expanded) } else { (reflected, f_reflected) };
+        } else if f_reflected < simplex[2].1 {
+            simplex[3] = (reflected, f_reflected);
+        } else {
+            let contracted = along(0.5);
+            let f_contracted = f(&contracted);
+            if f_contracted < simplex[3].1 {
+                simplex[3] = (contracted, f_contracted);
+            } else {
+                let best = simplex[0].0;
+                for entry in simplex.iter_mut().skip(1) {
+                    for (x, b) in entry.0.iter_mut().zip(best) {
+                        *x = b + 0.5 * (*x - b);
+                    }
+                    entry.1 = f(&entry.0);
+                }
+            }
+        }
+    }
+
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    simplex[0].0
+}
+
+#[allow(clippy::too_many_arguments)]
+fn calculate_statistics(terminal_prices: &mut [f64], max_drawdowns: &[f64], model: &str, paths: usize, horizon: usize, init_price: f64, confidence_level: f64, output_mode: OutputMode) -> Result<SimStats> {
+    if terminal_prices.is_empty() {
+        return Err(anyhow!("No terminal prcies to analyze"));
+    }
+
+    let returns: Vec<f64> = terminal_prices.iter()
+        .map(|&price| (price - init_price) / init_price)
+        .collect();
+    let values = match output_mode {
+        OutputMode::Return => returns.clone(),
+        _ => terminal_prices.to_vec(),
+    };
+
+    let data = Data::new(values.clone());
+    let mean = data.mean().unwrap_or(0.0);
+    let std_dev = data.std_dev().unwrap_or(0.0);
+    let median = data.median();
+
+    let mut ordered_data = Data::new(values);
+    let p5 = ordered_data.percentile(5);
+    let p25 = ordered_data.percentile(25);
+    let p75 = ordered_data.percentile(75);
+    let p95 = ordered_data.percentile(95);
+    
+    //VaR percentile is (1 - confidence_level) * 100, e.g. 5th for 95%
+    let mut returns_data = Data::new(returns.clone());
+    let tail_return = returns_data.quantile(1.0 - confidence_level);
+    let var95 = -tail_return;
+
+    //expected shortfall: mean loss of the returns at or below the VaR threshold
+    let tail: Vec<f64> = returns.iter().copied().filter(|&r| r <= tail_return).collect();
+    let cvar95 = if tail.is_empty() {
+        //with very few paths the interpolated percentile can sit below every sample, fall back to the worst return
+        -returns.iter().copied().fold(f64::INFINITY, f64::min)
+    } else {
+        -(tail.iter().sum::<f64>() / tail.len() as f64)
+    };
+    let tail_effective_samples = tail.len() as f64;
+
+    let mut drawdown_data = Data::new(max_drawdowns.to_vec());
+    let mean_max_drawdown = drawdown_data.mean().unwrap_or(0.0);
+    let p95_max_drawdown = drawdown_data.percentile(95);
+
+    let (skewness, kurtosis) = skewness_kurtosis(terminal_prices);
+
+    Ok(SimStats { mean, mean_std_error: std_dev / (terminal_prices.len() as f64).sqrt(), std_dev, median, p5, p25, p75, p95, var95, cvar95, tail_effective_samples, mean_max_drawdown, p95_max_drawdown, skewness, kurtosis, ..SimStats::base(model, output_mode, paths, terminal_prices.len(), horizon, confidence_level) })
+
+}
+
+//mu with S0·e^{μT} = target_mean, T = horizon·dt, i.e. ln(target/S0)/T. The lognormal mean
+//doesn't depend on sigma (the -σ²/2 in the log drift cancels the convexity), it is taken only to
+//mirror gbm_analytic_stats. Per time unit like mu itself
+pub fn solve_drift_for_target(init_price: f64, _sigma: f64, horizon: usize, dt: f64, target_mean: f64) -> f64 {
+    (target_mean / init_price).ln() / (horizon as f64 * dt)
+}
+
+//closed-form mean, median and variance of the GBM terminal price S0·exp((μ - σ²/2)T + σ√T·Z)
+//with T = horizon·dt: mean S0·e^{μT}, median S0·e^{(μ - σ²/2)T}, variance mean²·(e^{σ²T} - 1)
+pub fn gbm_analytic_stats(init_price: f64, mu: f64, sigma: f64, horizon: usize, dt: f64) -> (f64, f64, f64) {
+    let t = horizon as f64 * dt;
+    let mean = init_price * (mu * t).exp();
+    let median = init_price * ((mu - 0.5 * sigma.powi(2)) * t).exp();
+    let variance = mean.powi(2) * (sigma.powi(2) * t).exp_m1();
+    (mean, median, variance)
+}
+
+//discounted mean payoff of a European call/put; rate and horizon are in the same units as dt
+pub fn price_european_option(terminal_prices: &[f64], strike: f64, is_call: bool, discount_rate: f64, horizon: f64) -> f64 {
+    if terminal_prices.is_empty() {
+        return 0.0;
+    }
+    let payoffs = european_payoffs(terminal_prices, strike, is_call, (-discount_rate * horizon).exp());
+    payoffs.iter().sum::<f64>() / payoffs.len() as f64
+}
+
+//knock-out call/put: payoff is zeroed if the path ever touches the barrier (including at t=0,
+//so a barrier at the initial price knocks out every path immediately). Only the path's
+//extremes matter, so this works the same whether or not the full paths were kept
+pub fn price_barrier_option(paths: &[PathSummary], strike: f64, barrier: f64, is_up_and_out: bool, is_call: bool, discount_rate: f64, horizon: f64) -> f64 {
+    if paths.is_empty() {
+        return 0.0;
+    }
+    let discount_factor = (-discount_rate * horizon).exp();
+
+    let total: f64 = paths.iter().map(|path| {
+        let knocked_out = if is_up_and_out { path.high >= barrier } else { path.low <= barrier };
+        if knocked_out {
+            0.0
+        } else {
+            let payoff = if is_call { (path.terminal - strike).max(0.0) } else { (strike - path.terminal).max(0.0) };
+            payoff * discount_factor
+        }
+    }).sum();
+
+    total / paths.len() as f64
+}
+
+fn european_payoffs(terminal_prices: &[f64], strike: f64, is_call: bool, discount_factor: f64) -> Vec<f64> {
+    terminal_prices.iter()
+        .map(|&price| if is_call { (price - strike).max(0.0) } else { (strike - price).max(0.0) })
+        .map(|payoff| payoff * discount_factor)
+        .collect()
+}
+
+//GBM control variate: ln(S_T) has the closed-form mean ln(S_0) + (mu - sigma^2/2)T
+//(the log of E[S_T] = S_0*exp(mu*T) less the convexity term), and is highly correlated
+//with S_T without being identical to it. log_drift is that (mu - sigma^2/2)T, summed per
+//step when a schedule is set. The prices are left as they are, returns the adjusted mean, its
+//standard error (over antithetic pair means when paired) and the variance ratio.
+fn control_variate_mean(terminal_prices: &[f64], init_price: f64, log_drift: f64, paired: bool) -> (f64, f64, f64) {
+    let n = terminal_prices.len() as f64;
+    let expected_log = init_price.ln() + log_drift;
+    let controls: Vec<f64> = terminal_prices.iter().map(|p| p.ln()).collect();
+
+    let mean_x = terminal_prices.iter().sum::<f64>() / n;
+    let mean_y = controls.iter().sum::<f64>() / n;
+    let var_x = terminal_prices.iter().map(|x| (x - mean_x).powi(2)).sum::<f64>() / n;
+    let var_y = controls.iter().map(|y| (y - mean_y).powi(2)).sum::<f64>() / n;
+    //a degenerate control leaves the prices unadjusted
+    let b = if var_y > 0.0 && var_x > 0.0 {
+        terminal_prices.iter().zip(&controls).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>() / n / var_y
+    } else {
+        0.0
+    };
+    let adjusted: Vec<f64> = terminal_prices.iter().zip(&controls).map(|(price, control)| price - b * (control - expected_log)).collect();
+
+    let mean_adj = adjusted.iter().sum::<f64>() / n;
+    let var_adj = adjusted.iter().map(|x| (x - mean_adj).powi(2)).sum::<f64>() / n;
+    let std_error = if paired {
+        paired_std_error(&adjusted)
+    } else {
+        estimate_paramaters(&adjusted).map(|(_, sd)| sd / n.sqrt()).unwrap_or(0.0)
+    };
+    let ratio = if b == 0.0 { 1.0 } else if var_adj > 0.0 { var_x / var_adj } else { f64::INFINITY };
+    (mean_adj, std_error, ratio)
+}
+
+//below this many paths the exact sort is cheap and P-square estimates are still rough
+const APPROX_QUANTILE_MIN_PATHS: usize = 10_000;
+
+//same stats as calculate_statistics without copying or sorting: quantiles come from P-square
+//estimators fed in one pass, CVaR takes a second pass once the VaR threshold is known
+#[allow(clippy::too_many_arguments)]
+fn approximate_statistics(terminal_prices: &[f64], max_drawdowns: &[f64], model: &str, paths: usize, horizon: usize, init_price: f64, confidence_level: f64, output_mode: OutputMode) -> Result<SimStats> {
+    if terminal_prices.is_empty() {
+        return Err(anyhow!("No terminal prcies to analyze"));
+    }
+
+    let to_return = |price: f64| (price - init_price) / init_price;
+    let is_return = output_mode == OutputMode::Return;
+
+    //returns are an increasing affine map of prices, so the VaR quantile can be read off either
+    let mut estimators: Vec<P2Quantile> = [0.05, 0.25, 0.5, 0.75, 0.95, 1.0 - confidence_level]
+        .iter()
+        .map(|&p| P2Quantile::new(p))
+        .collect();
+    let (mut count, mut mean, mut m2) = (0.0, 0.0, 0.0);
+    for &price in terminal_prices {
+        let value = if is_return { to_return(price) } else { price };
+        //Welford update for the mean and sum of squared deviations
+        count += 1.0;
+        let delta = value - mean;
+        mean += delta / count;
+        m2 += delta * (value - mean);
+        for estimator in estimators.iter_mut() {
+            estimator.push(value);
+        }
+    }
+    let std_dev = if count > 1.0 { (m2 / (count - 1.0)).sqrt() } else { 0.0 };
+    let [p5, p25, median, p75, p95, tail_value] = [0, 1, 2, 3, 4, 5].map(|k| estimators[k].estimate());
+
+    let tail_return = if is_return { tail_value } else { to_return(tail_value) };
+    let var95 = -tail_return;
+    let (tail_sum, tail_count, worst) = terminal_prices.iter()
+        .map(|&price| to_return(price))
+        .fold((0.0, 0usize, f64::INFINITY), |(sum, n, worst), r| {
+            if r <= tail_return { (sum + r, n + 1, worst.min(r)) } else { (sum, n, worst.min(r)) }
+        });
+    let cvar95 = if tail_count == 0 { -worst } else { -(tail_sum / tail_count as f64) };
+    let tail_effective_samples = tail_count as f64;
+
+    let mut drawdown_p95 = P2Quantile::new(0.95);
+    for &dd in max_drawdowns {
+        drawdown_p95.push(dd);
+    }
+    let mean_max_drawdown = if max_drawdowns.is_empty() { 0.0 } else { max_drawdowns.iter().sum::<f64>() / max_drawdowns.len() as f64 };
+    let p95_max_drawdown = drawdown_p95.estimate();
+
+    let (skewness, kurtosis) = skewness_kurtosis(terminal_prices);
+
+    Ok(SimStats { mean, mean_std_error: std_dev / (terminal_prices.len() as f64).sqrt(), std_dev, median, p5, p25, p75, p95, var95, cvar95, tail_effective_samples, mean_max_drawdown, p95_max_drawdown, skewness, kurtosis, ..SimStats::base(model, output_mode, paths, terminal_prices.len(), horizon, confidence_level) })
+}
+
+//likelihood ratio of plain over shifted sampling for one GBM path. It only depends on the shock
+//sum, recovered from the terminal price as Σz' = (ln(S_T/S0) - n(μ - σ²/2)dt) / (σ√dt), and is
+//exp(-θΣz' + nθ²/2)
+fn importance_weight(terminal_price: f64, init_price: f64, mu: f64, sigma: f64, horizon: usize, dt: f64, theta: f64) -> f64 {
+    let n = horizon as f64;
+    let shock_sum = ((terminal_price / init_price).ln() - n * (mu - 0.5 * sigma.powi(2)) * dt) / (sigma * dt.sqrt());
+    (-theta * shock_sum + 0.5 * n * theta.powi(2)).exp()
+}
+
+//p-quantile of the weighted empirical distribution: the smallest value whose cumulative weight
+//reaches p·N. Likelihood ratios average 1, so dividing by N instead of Σw keeps a lower quantile
+//free of the few huge weights far above it. `order` indexes values in ascending order
+fn weighted_quantile(values: &[f64], weights: &[f64], order: &[usize], p: f64) -> f64 {
+    let target = p * weights.len() as f64;
+    let mut cumulative = 0.0;
+    for &i in order {
+        cumulative += weights[i];
+        if cumulative >= target {
+            return values[i];
+        }
+    }
+    order.last().map_or(0.0, |&i| values[i])
+}
+
+//calculate_statistics for importance-sampled runs: every moment and quantile uses the likelihood
+//ratios as self-normalised weights. Skewness and kurtosis are the plain weighted moment ratios
+#[allow(clippy::too_many_arguments)]
+fn weighted_statistics(terminal_prices: &[f64], weights: &[f64], max_drawdowns: &[f64], model: &str, paths: usize, horizon: usize, init_price: f64, confidence_level: f64, output_mode: OutputMode) -> Result<SimStats> {
+    if terminal_prices.is_empty() {
+        return Err(anyhow!("No terminal prcies to analyze"));
+    }
+
+    let returns: Vec<f64> = terminal_prices.iter()
+        .map(|&price| (price - init_price) / init_price)
+        .collect();
+    let values = match output_mode {
+        OutputMode::Return => &returns,
+        _ => terminal_prices,
+    };
+    //returns and prices share one ordering, the map between them is increasing
+    let mut order: Vec<usize> = (0..terminal_prices.len()).collect();
+    order.sort_by(|&a, &b| terminal_prices[a].total_cmp(&terminal_prices[b]));
+
+    let total: f64 = weights.iter().sum();
+    let moment = |k: i32, center: f64| values.iter().zip(weights).map(|(v, w)| w * (v - center).powi(k)).sum::<f64>() / total;
+    let mean = moment(1, 0.0);
+    let variance = moment(2, mean);
+    let std_dev = variance.sqrt();
+    let mean_std_error = values.iter().zip(weights).map(|(v, w)| (w * (v - mean)).powi(2)).sum::<f64>().sqrt() / total;
+    let (skewness, kurtosis) = if variance > 0.0 {
+        (moment(3, mean) / variance.powf(1.5), moment(4, mean) / variance.powi(2) - 3.0)
+    } else {
+        (0.0, 0.0)
+    };
+
+    let quantile = |p: f64| weighted_quantile(values, weights, &order, p);
+    let (p5, p25, median, p75, p95) = (quantile(0.05), quantile(0.25), quantile(0.5), quantile(0.75), quantile(0.95));
+
+    let tail_return = weighted_quantile(&returns, weights, &order, 1.0 - confidence_level);
+    let var95 = -tail_return;
+    let (tail_weight, tail_sum, tail_square) = returns.iter().zip(weights)
+        .filter(|&(&r, _)| r <= tail_return)
+        .fold((0.0, 0.0, 0.0), |(tw, ts, tq), (r, w)| (tw + w, ts + w * r, tq + w * w));
+    let cvar95 = if tail_weight > 0.0 { -(tail_sum / tail_weight) } else { var95 };
+    let tail_effective_samples = if tail_square > 0.0 { tail_weight.powi(2) / tail_square } else { 0.0 };
+
+    let mean_max_drawdown = max_drawdowns.iter().zip(weights).map(|(dd, w)| w * dd).sum::<f64>() / total;
+    let mut drawdown_order: Vec<usize> = (0..max_drawdowns.len()).collect();
+    drawdown_order.sort_by(|&a, &b| max_drawdowns[a].total_cmp(&max_drawdowns[b]));
+    let p95_max_drawdown = weighted_quantile(max_drawdowns, weights, &drawdown_order, 0.95);
+
+    Ok(SimStats { mean, mean_std_error, std_dev, median, p5, p25, p75, p95, var95, cvar95, tail_effective_samples, mean_max_drawdown, p95_max_drawdown, skewness, kurtosis, ..SimStats::base(model, output_mode, paths, terminal_prices.len(), horizon, confidence_level) })
+}
+
+//P-square streaming quantile (Jain & Chlamtac 1985): five markers whose heights track the
+//min, p/2, p, (1+p)/2 quantiles and max, adjusted by piecewise-parabolic interpolation
+struct P2Quantile {
+    p: f64,
+    count: usize,
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5],
+}
+
+impl P2Quantile {
+    fn new(p: f64) -> Self {
+        let p = p.clamp(0.0, 1.0);
+        P2Quantile {
+            p,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        }
+    }
+
+    fn push(&mut self, x: f64) {
+        //the first five observations seed the markers directly
+        if self.count < 5 {
+            self.heights[self.count] = x;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(|a, b| a.total_cmp(b));
+            }
+            return;
+        }
+        self.count += 1;
+
+        let q = &mut self.heights;
+        let k = if x < q[0] {
+            q[0] = x;
+            0
+        } else if x >= q[4] {
+            q[4] = x;
+            3
+        } else {
+            (0..4).find(|&i| x < q[i + 1]).unwrap_or(3)
+        };
+        for n in self.positions.iter_mut().skip(k + 1) {
+            *n += 1.0;
+        }
+        for (d, inc) in self.desired.iter_mut().zip(self.increments) {
+            *d += inc;
+        }
+
+        let n = &mut self.positions;
+        for i in 1..4 {
+            let offset = self.desired[i] - n[i];
+            if (offset >= 1.0 && n[i + 1] - n[i] > 1.0) || (offset <= -1.0 && n[i - 1] - n[i] < -1.0) {
+                let d = offset.signum();
+                let parabolic = q[i] + d / (n[i + 1] - n[i - 1])
+                    * ((n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                        + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]));
+                q[i] = if q[i - 1] < parabolic && parabolic < q[i + 1] {
+                    parabolic
+                } else {
+                    //parabola overshot a neighbour, fall back to linear towards it
+                    let j = if d > 0.0 { i + 1 } else { i - 1 };
+                    q[i] + d * (q[j] - q[i]) / (n[j] - n[i])
+                };
+                n[i] += d;
+            }
+        }
+    }
+
+    fn estimate(&self) -> f64 {
+        if self.count >= 5 {
+            return self.heights[2];
+        }
+        if self.count == 0 {
+            return 0.0;
+        }
+        //too few samples for the markers, closest-rank interpolation on what we have
+        let mut seen = self.heights[..self.count].to_vec();
+        seen.sort_by(|a, b| a.total_cmp(b));
+        let pos = self.p * (seen.len() - 1) as f64;
+        let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+        seen[lo] + (seen[hi] - seen[lo]) * (pos - lo as f64)
+    }
+}
+
+//fraction of terminal prices strictly above the threshold, 0.0 for no prices
+pub fn probability_above(terminal_prices: &[f64], threshold: f64) -> f64 {
+    if terminal_prices.is_empty() {
+        return 0.0;
+    }
+    terminal_prices.iter().filter(|&&price| price > threshold).count() as f64 / terminal_prices.len() as f64
+}
+
+//fraction of terminal prices strictly below the threshold, 0.0 for no prices
+pub fn probability_below(terminal_prices: &[f64], threshold: f64) -> f64 {
+    if terminal_prices.is_empty() {
+        return 0.0;
+    }
+    terminal_prices.iter().filter(|&&price| price < threshold).count() as f64 / terminal_prices.len() as f64
+}
+
+//weighted share of terminal prices matching the predicate, 0.0 for no prices
+fn weighted_share(terminal_prices: &[f64], weights: &[f64], hit: impl Fn(f64) -> bool) -> f64 {
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    terminal_prices.iter().zip(weights).filter(|&(&price, _)| hit(price)).map(|(_, w)| w).sum::<f64>() / total
+}
+
+//steps per year when dt is one trading day
+const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+
+impl StepFrequency {
+    //steps in a year for the calendar frequencies, None for raw per-step dt
+    pub fn steps_per_year(self) -> Option<f64> {
+        match self {
+            StepFrequency::PerStep => None,
+            StepFrequency::Daily => Some(TRADING_DAYS_PER_YEAR),
+            StepFrequency::Weekly => Some(52.0),
+            StepFrequency::Monthly => Some(12.0),
+        }
+    }
+}
+
+//dt the generators step by. A calendar frequency makes mu, sigma and every other rate annualized
+//and one step a fraction of a year, 1/252 for daily. PerStep keeps the raw dt in trading days
+//with per-day rates
+pub fn step_dt(params: &CoreSimParams) -> f64 {
+    match params.step_frequency.steps_per_year() {
+        Some(steps) => 1.0 / steps,
+        None => params.dt as f64,
+    }
+}
+
+//trading days covered by one step, for windows measured in days like the VaR backtest
+pub fn step_trading_days(params: &CoreSimParams) -> f64 {
+    match params.step_frequency.steps_per_year() {
+        Some(steps) => TRADING_DAYS_PER_YEAR / steps,
+        None => params.dt as f64,
+    }
+}
+
+//the horizon in bars of the history, at least one: a weekly history covers a 20-day horizon in 4 bars
+pub fn horizon_history_bars(params: &CoreSimParams) -> usize {
+    params.history_frequency.bars_for_trading_days(params.horizon.max(1) as f64 * step_trading_days(params))
+}
+
+//time units (what dt counts) per year: trading days for PerStep, years otherwise
+fn units_per_year(params: &CoreSimParams) -> f64 {
+    if params.step_frequency == StepFrequency::PerStep { TRADING_DAYS_PER_YEAR } else { 1.0 }
+}
+
+//Sharpe and Sortino of the horizon returns in excess of the risk-free return over the same
+//horizon (risk_free_rate is per time unit, like discount_rate). Both are annualized by
+//sqrt(units_per_year / (horizon * dt)), i.e. assuming independent horizon-length periods. Sortino
+//uses the downside deviation of excess returns below zero. A zero deviation gives +inf for a
+//positive mean excess return and 0.0 otherwise, for both ratios
+fn risk_adjusted_ratios(terminal_prices: &[f64], weights: Option<&[f64]>, init_price: f64, risk_free_rate: f64, horizon: f64, units_per_year: f64) -> (f64, f64) {
+    if terminal_prices.is_empty() || horizon <= 0.0 {
+        return (0.0, 0.0);
+    }
+    let risk_free_return = risk_free_rate * horizon;
+    let excess: Vec<f64> = terminal_prices.iter()
+        .map(|&price| (price - init_price) / init_price - risk_free_return)
+        .collect();
+    let (mean, std_dev, downside_dev) = match weights {
+        //importance-sampled runs use likelihood-ratio weighted moments
+        Some(weights) => {
+            let total: f64 = weights.iter().sum();
+            let mean = excess.iter().zip(weights).map(|(r, w)| w * r).sum::<f64>() / total;
+            let variance = excess.iter().zip(weights).map(|(r, w)| w * (r - mean).powi(2)).sum::<f64>() / total;
+            let downside = excess.iter().zip(weights).map(|(r, w)| w * r.min(0.0).powi(2)).sum::<f64>() / total;
+            (mean, variance.sqrt(), downside.sqrt())
+        }
+        None => {
+            let n = excess.len() as f64;
+            let mean = excess.iter().sum::<f64>() / n;
+            let std_dev = if excess.len() > 1 {
+                (excess.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+            } else {
+                0.0
+            };
+            (mean, std_dev, (excess.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / n).sqrt())
+        }
+    };
+
+    let annualize = (units_per_year / horizon).sqrt();
+    let ratio = |deviation: f64| {
+        if deviation > 0.0 {
+            mean / deviation * annualize
+        } else if mean > 0.0 {
+            f64::INFINITY
+        } else {
+            0.0
+        }
+    };
+    (ratio(std_dev), ratio(downside_dev))
+}
+
+//standard error of the mean treating consecutive (i, i+1) paths as one draw
+fn paired_std_error(terminal_prices: &[f64]) -> f64 {
+    let pair_means: Vec<f64> = terminal_prices.chunks(2).map(|pair| pair.iter().sum::<f64>() / pair.len() as f64).collect();
+    estimate_paramaters(&pair_means).map(|(_, sd)| sd / (pair_means.len() as f64).sqrt()).unwrap_or(0.0)
+}
+
+//bias-corrected sample skewness (G1) and excess kurtosis (G2), 0.0 when variance is zero
+fn skewness_kurtosis(data: &[f64]) -> (f64, f64) {
+    let n = data.len() as f64;
+    let mean = data.iter().sum::<f64>() / n;
+    let m2 = data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+    if m2 <= 0.0 {
+        return (0.0, 0.0);
+    }
+    let m3 = data.iter().map(|x| (x - mean).powi(3)).sum::<f64>() / n;
+    let m4 = data.iter().map(|x| (x - mean).powi(4)).sum::<f64>() / n;
+
+    let skewness = if data.len() > 2 {
+        (n * (n - 1.0)).sqrt() / (n - 2.0) * m3 / m2.powf(1.5)
+    } else {
+        0.0
+    };
+    let kurtosis = if data.len() > 3 {
+        (n - 1.0) / ((n - 2.0) * (n - 3.0)) * ((n + 1.0) * m4 / m2.powi(2) - 3.0 * (n - 1.0))
+    } else {
+        0.0
+    };
+    (skewness, kurtosis)
+}
+
+//receives a path's prices in order; generators are generic over it so the same code
+//either keeps the whole path (Vec) or only the running summary
+trait PathSink {
+    //generators skip work that only feeds record when this is false
+    const RECORDS: bool = false;
+
+    fn with_capacity(capacity: usize) -> Self;
+    fn push(&mut self, price: f64);
+    fn record(&mut self, _step: StepDiagnostics) {}
+}
 
-    for _ in 0..steps {
-        let mut z = normal.sample(rng);
-        if is_antithetic {
-            z = -z;
-        }
+impl PathSink for Vec<f64> {
+    fn with_capacity(capacity: usize) -> Self {
+        Vec::with_capacity(capacity)
+    }
 
-        let next_price = current_price * (drift + diffusion * z).exp();
-        path.push(next_price);
-        current_price = next_price;
+    fn push(&mut self, price: f64) {
+        Vec::push(self, price);
     }
-    path
 }
 
-fn generate_bootstrap_path(init_price: f64, steps: usize, log_returns: &[f64], rng: &mut StdRng) -> Vec<f64> {
-    if log_returns.is_empty() {
-        return vec![init_price; steps+1];
-    }
+//everything the statistics and barrier pricing need from a path, 32 bytes regardless of horizon
+#[derive(Debug, Clone, Copy)]
+pub struct PathSummary {
+    terminal: f64,
+    high: f64,
+    low: f64,
+    //largest peak-to-trough decline so far, as a fraction of the peak
+    max_drawdown: f64,
+}
 
-    let mut path = Vec::with_capacity(steps+1);
-    path.push(init_price);
-    let mut current_price = init_price;
+impl PathSummary {
+    //high/low start at finite sentinels, so any inf/NaN price shows up in at least one field
+    fn is_diverged(&self) -> bool {
+        !(self.terminal.is_finite() && self.high.is_finite() && self.low.is_finite() && self.max_drawdown.is_finite())
+    }
 
-    for _ in 0..steps {
-        let idx = rng.random_range(0..log_returns.len());
-        let log_return = log_returns[idx];
-        let next_price = current_price * log_return.exp();
-        path.push(next_price);
-        current_price = next_price;
+    fn of(path: &[f64]) -> Self {
+        let mut summary = <Self as PathSink>::with_capacity(path.len());
+        for &price in path {
+            summary.push(price);
+        }
+        summary
     }
-    path
 }
 
-pub fn estimate_paramaters(log_returns: &[f64]) -> Result<(f64, f64)> {
-    if log_returns.len() < 2 {
-        return Err(anyhow!("Not enough data to estimate parameters. Neet at least 2 log returns."));
+impl PathSink for PathSummary {
+    fn with_capacity(_capacity: usize) -> Self {
+        PathSummary { terminal: 0.0, high: f64::MIN, low: f64::MAX, max_drawdown: 0.0 }
     }
-    let data = Data::new(log_returns.to_vec());
-    let mu = data.mean().unwrap_or(0.0);
-    let sigma = data.std_dev().unwrap_or(0.0);
 
-    Ok((mu, sigma))
+    fn push(&mut self, price: f64) {
+        self.terminal = price;
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        if self.high > 0.0 {
+            self.max_drawdown = self.max_drawdown.max((self.high - price) / self.high);
+        }
+    }
 }
 
-fn calculate_statistics(terminal_prices: &mut [f64], model: &str, paths: usize, horizon: usize, init_price: f64) -> Result<SimStats> {
-    if terminal_prices.is_empty() {
-        return Err(anyhow!("No terminal prcies to analyze"));
-    }
+//internal state behind one step of a path. variance is what drives the step per unit of time
+//(σ², the GARCH or Heston variance, σ²x for CIR, (αF^β)² for SABR), drift is the deterministic
+//part of the step (log return for the price models, level change for MeanReversion, CIR and
+//Vasicek) and shock the normal draw after any antithetic flip. jump is the summed log jump
+//size, 0 outside the jump models. Bootstrap has neither: its shock is the resampled return
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct StepDiagnostics {
+    pub step: usize,
+    pub price: f64,
+    pub variance: f64,
+    pub drift: f64,
+    pub shock: f64,
+    pub jump: f64,
+}
 
-    let data = Data::new(terminal_prices.to_vec());
-    let mean = data.mean().unwrap_or(0.0);
-    let std_dev = data.std_dev().unwrap_or(0.0);
-    let median = data.median();
+//keeps only the diagnostics, each already carries its step's price
+struct DiagnosticPath(Vec<StepDiagnostics>);
 
-    let mut ordered_data = Data::new(terminal_prices.to_vec());
-    let p5 = ordered_data.percentile(5);
-    let p25 = ordered_data.percentile(25);
-    let p75 = ordered_data.percentile(75);
-    let p95 = ordered_data.percentile(95);
+impl PathSink for DiagnosticPath {
+    const RECORDS: bool = true;
 
-    let returns: Vec<f64> = terminal_prices.iter()
-        .map(|&price| (price - init_price) / init_price)
-        .collect();
-    
-    let mut returns_data = Data::new(returns);
-    let p5_return = returns_data.percentile(5);
-    let var95 = -p5_return;
+    fn with_capacity(capacity: usize) -> Self {
+        DiagnosticPath(Vec::with_capacity(capacity))
+    }
 
-    Ok(SimStats { model: model.to_string(), paths, horizon, mean, std_dev, median, p5, p25, p75, p95, var95 })
+    fn push(&mut self, _price: f64) {}
 
+    fn record(&mut self, step: StepDiagnostics) {
+        self.0.push(step);
+    }
 }
 
-// Helper function to create ModelParams from Slint's SimParams
+// Helper function to create ModelParams from CoreSimParams
 pub fn create_model_params(model_type: &str, mu: f64, sigma: f64) -> ModelParams {
     match model_type {
-        "GBM" => ModelParams::GBM { mu, sigma },
-        "Bootstrap" => ModelParams::Bootstrap {},
+        "GBM" => ModelParams::GBM { mu, sigma, mu_schedule: None, sigma_schedule: None },
+        "Bootstrap" => ModelParams::Bootstrap { block_size: 1, demean: false },
         "MeanReversion" => ModelParams::MeanReversion {
             theta: 0.1,           // Default value
             mu_long_term: 100.0,  // Default value
@@ -212,17 +2514,62 @@ pub fn create_model_params(model_type: &str, mu: f64, sigma: f64) -> ModelParams
             mu_j: -0.02,      // Default: small negative jump
             sigma_j: 0.05,    // Default: 5% jump volatility
         },
+        "HawkesJump" => ModelParams::HawkesJump {
+            mu,
+            sigma,
+            lambda: 2.0,      // Default: baseline of 2 jumps per year
+            excitation: 1.0,  // Default: each jump adds 1 to the intensity
+            decay: 2.0,       // Default: half a jump per jump, excess halves in ~0.35 years
+            mu_j: -0.02,
+            sigma_j: 0.05,
+        },
         "GARCH" => ModelParams::GARCH {
             omega: 0.00001,   // Default: small constant
             alpha: 0.1,       // Default: ARCH coefficient
             beta: 0.85,       // Default: GARCH coefficient
         },
-        _ => ModelParams::GBM { mu, sigma }, // Default fallback
+        "Heston" => ModelParams::Heston {
+            mu,
+            v0: sigma.powi(2),    // Default: start at current variance
+            kappa: 2.0,           // Default: reversion speed of variance
+            theta: sigma.powi(2), // Default: long-run variance
+            xi: 0.3,              // Default: vol of vol
+            rho: -0.7,            // Default: leverage effect
+        },
+        "CIR" => ModelParams::CIR {
+            kappa: 0.1,           // Default: reversion speed
+            theta: 100.0,         // Default: long-run level
+            sigma,
+        },
+        "Vasicek" => ModelParams::Vasicek {
+            kappa: 0.1,           // Default: reversion speed
+            theta: 0.03,          // Default: long-run rate
+            sigma,
+        },
+        "SABR" => ModelParams::SABR {
+            alpha: sigma,         // Default: lognormal-equivalent vol when beta = 1
+            beta: 1.0,            // Default: lognormal backbone
+            rho: -0.3,            // Default: mild negative skew
+            nu: 0.4,              // Default: vol of vol
+        },
+        "LocalVol" => ModelParams::LocalVol {
+            mu,
+            surface: VolSurface::flat(sigma),  // Default: flat surface at the current vol
+        },
+        "RegimeSwitching" => ModelParams::RegimeSwitching {
+            mu,
+            sigma_low: sigma,         // Default: calm regime at current vol
+            sigma_high: 2.0 * sigma,  // Default: stressed regime doubles it
+            p_low_to_high: 0.02,      // Default: ~50 days between stress episodes
+            p_high_to_low: 0.1,       // Default: ~10 day stress episodes
+        },
+        _ => ModelParams::GBM { mu, sigma, mu_schedule: None, sigma_schedule: None }, // Default fallback
     }
 }
 
 
-fn generate_mean_reversion_path(
+#[allow(clippy::too_many_arguments)]
+fn generate_mean_reversion_path<P: PathSink>(
     init_price: f64,
     theta: f64,        // Speed of reversion
     mu_long_term: f64, // Long-term mean price
@@ -230,17 +2577,18 @@ fn generate_mean_reversion_path(
     steps: usize,
     dt: f64,
     is_antithetic: bool,
-    rng: &mut StdRng,
-) -> Vec<f64> {
-    let mut path = Vec::with_capacity(steps + 1);
+    shocks: ShockSource,
+    rng: &mut PathRng,
+) -> P {
+    let mut path = P::with_capacity(steps + 1);
     path.push(init_price);
     let mut current_price = init_price;
 
     let diffusion = sigma * dt.sqrt();
     let normal = Normal::new(0.0, 1.0).unwrap();
 
-    for _ in 0..steps {
-        let mut z = normal.sample(rng);
+    for step in 0..steps {
+        let mut z = shocks.normal(step, &normal, rng);
         if is_antithetic {
             z = -z;
         }
@@ -255,6 +2603,7 @@ fn generate_mean_reversion_path(
         let next_price = next_price.max(0.01);
         
         path.push(next_price);
+        path.record(StepDiagnostics { step, price: next_price, variance: sigma * sigma, drift, shock: z, jump: 0.0 });
         current_price = next_price;
     }
     
@@ -262,19 +2611,30 @@ fn generate_mean_reversion_path(
 }
 
 
-fn generate_jump_diffusion_path(
+//Merton jump-diffusion, with Hawkes self-exciting jumps when excitation > 0: the intensity is
+//λ_t = λ + Σ excitation·e^{-decay(t - t_i)} over past jumps t_i, stepped as a Poisson count at the
+//step's starting intensity, after which the excess decays by e^{-decay·dt} and each new jump adds
+//excitation. excitation 0 is the plain constant-intensity Poisson.
+//An antithetic partner replays the same stream, so its jump counts are common random numbers
+//(identical counts, and so an identical Hawkes intensity path, which a discrete count can't
+//mirror without changing its distribution) and each jump size is reflected around mu_j
+#[allow(clippy::too_many_arguments)]
+fn generate_jump_diffusion_path<P: PathSink>(
     init_price: f64,
     mu: f64,           // Drift
     sigma: f64,        // Diffusion volatility
-    lambda: f64,       // Jump intensity (average jumps per unit time)
+    lambda: f64,       // Baseline jump intensity (average jumps per unit time)
+    excitation: f64,   // Intensity added by each jump
+    decay: f64,        // Exponential decay rate of the added intensity
     mu_j: f64,         // Mean of jump size (in log space)
     sigma_j: f64,      // Std dev of jump size (in log space)
     steps: usize,
     dt: f64,
     is_antithetic: bool,
-    rng: &mut StdRng,
-) -> Vec<f64> {
-    let mut path = Vec::with_capacity(steps + 1);
+    shocks: ShockSource,
+    rng: &mut PathRng,
+) -> P {
+    let mut path = P::with_capacity(steps + 1);
     path.push(init_price);
     let mut current_price = init_price;
 
@@ -283,14 +2643,16 @@ fn generate_jump_diffusion_path(
     let diffusion = sigma * dt.sqrt();
     let normal = Normal::new(0.0, 1.0).unwrap();
 
-    // Jump components
+    // Jump components (no jumps at all when the intensity is zero or in a drift-only preview)
     use rand_distr::Poisson;
-    let poisson = Poisson::new(lambda * dt).unwrap();
+    let poisson = Poisson::new(lambda * dt).ok().filter(|_| !shocks.deterministic);
     let jump_normal = Normal::new(mu_j, sigma_j).unwrap();
+    let decay_factor = (-decay * dt).exp();
+    let mut excess_intensity = 0.0;
 
-    for _ in 0..steps {
-        // Diffusion part (GBM)
-        let mut z = normal.sample(rng);
+    for step in 0..steps {
+        // Diffusion part (GBM), jumps always come from the RNG
+        let mut z = shocks.normal(step, &normal, rng);
         if is_antithetic {
             z = -z;
         }
@@ -298,13 +2660,18 @@ fn generate_jump_diffusion_path(
         let gbm_return = drift + diffusion * z;
 
         // Jump part
-        let num_jumps = poisson.sample(rng) as usize;
+        let num_jumps = if excess_intensity > 0.0 && !shocks.deterministic {
+            Poisson::new((lambda + excess_intensity) * dt).map_or(0, |p| p.sample(rng) as usize)
+        } else {
+            poisson.as_ref().map_or(0, |p| p.sample(rng) as usize)
+        };
+        excess_intensity = excess_intensity * decay_factor + excitation * num_jumps as f64;
         let mut jump_effect = 0.0;
         
         for _ in 0..num_jumps {
-            // Jump size in log space
+            // Jump size in log space, mirrored like z for the antithetic partner
             let jump_size = jump_normal.sample(rng);
-            jump_effect += jump_size;
+            jump_effect += if is_antithetic { 2.0 * mu_j - jump_size } else { jump_size };
         }
 
         // Combine: S_{t+1} = S_t * exp(gbm_return + jump_effect)
@@ -312,6 +2679,7 @@ fn generate_jump_diffusion_path(
         let next_price = current_price * total_return.exp();
         
         path.push(next_price);
+        path.record(StepDiagnostics { step, price: next_price, variance: sigma * sigma, drift, shock: z, jump: jump_effect });
         current_price = next_price;
     }
     
@@ -319,7 +2687,8 @@ fn generate_jump_diffusion_path(
 }
 
 
-fn generate_garch_path(
+#[allow(clippy::too_many_arguments)]
+fn generate_garch_path<P: PathSink>(
     init_price: f64,
     omega: f64,        // Constant term
     alpha: f64,        // ARCH coefficient
@@ -327,9 +2696,10 @@ fn generate_garch_path(
     steps: usize,
     dt: f64,
     is_antithetic: bool,
-    rng: &mut StdRng,
-) -> Vec<f64> {
-    let mut path = Vec::with_capacity(steps + 1);
+    shocks: ShockSource,
+    rng: &mut PathRng,
+) -> P {
+    let mut path = P::with_capacity(steps + 1);
     path.push(init_price);
     let mut current_price = init_price;
 
@@ -343,9 +2713,9 @@ fn generate_garch_path(
     let mut prev_return: f64 = 0.0;
     let normal = Normal::new(0.0, 1.0).unwrap();
 
-    for _ in 0..steps {
+    for step in 0..steps {
         // Generate random shock
-        let mut epsilon = normal.sample(rng);
+        let mut epsilon = shocks.normal(step, &normal, rng);
         if is_antithetic {
             epsilon = -epsilon;
         }
@@ -358,6 +2728,7 @@ fn generate_garch_path(
         let next_price = current_price * return_t.exp();
         
         path.push(next_price);
+        path.record(StepDiagnostics { step, price: next_price, variance, drift: 0.0, shock: epsilon, jump: 0.0 });
 
         // Update variance for next step: σ²_{t+1} = ω + α·r²_t + β·σ²_t
         variance = omega + alpha * prev_return.powi(2) + beta * variance;
@@ -371,3 +2742,705 @@ fn generate_garch_path(
     
     path
 }
+
+
+#[allow(clippy::too_many_arguments)]
+fn generate_heston_path<P: PathSink>(
+    init_price: f64,
+    mu: f64,           // Drift
+    v0: f64,           // Initial variance
+    kappa: f64,        // Speed of variance reversion
+    theta: f64,        // Long-run variance
+    xi: f64,           // Volatility of variance
+    rho: f64,          // Correlation between price and variance shocks
+    steps: usize,
+    dt: f64,
+    is_antithetic: bool,
+    shocks: ShockSource,
+    rng: &mut PathRng,
+) -> P {
+    let mut path = P::with_capacity(steps + 1);
+    path.push(init_price);
+    let mut current_price = init_price;
+    let mut variance = v0;
+
+    let sqrt_dt = dt.sqrt();
+    let rho_perp = (1.0 - rho.powi(2)).max(0.0).sqrt();
+    let normal = Normal::new(0.0, 1.0).unwrap();
+
+    for step in 0..steps {
+        // Two independent shocks, negated together so the variance shock mirrors too
+        let mut z1 = shocks.normal(2 * step, &normal, rng);
+        let mut z_perp = shocks.normal(2 * step + 1, &normal, rng);
+        if is_antithetic {
+            z1 = -z1;
+            z_perp = -z_perp;
+        }
+        // Correlated variance shock: z2 = ρ·z1 + √(1-ρ²)·z⊥
+        let z2 = rho * z1 + rho_perp * z_perp;
+
+        // Full truncation: use v⁺ = max(v, 0) in drift and diffusion
+        let v_plus = variance.max(0.0);
+
+        // Price: S_{t+1} = S_t * exp((μ - ½v⁺)dt + √v⁺·√dt·z1)
+        let next_price = current_price * ((mu - 0.5 * v_plus) * dt + v_plus.sqrt() * sqrt_dt * z1).exp();
+
+        // Variance: v_{t+1} = v_t + κ(θ - v⁺)dt + ξ√v⁺·√dt·z2
+        variance = variance + kappa * (theta - v_plus) * dt + xi * v_plus.sqrt() * sqrt_dt * z2;
+
+        path.push(next_price);
+        path.record(StepDiagnostics { step, price: next_price, variance: v_plus, drift: (mu - 0.5 * v_plus) * dt, shock: z1, jump: 0.0 });
+        current_price = next_price;
+    }
+
+    path
+}
+
+//Cox-Ingersoll-Ross: dx = κ(θ - x)dt + σ√x dW. Unlike the additive MeanReversion model the
+//noise shrinks towards zero, and full truncation (x⁺ = max(x, 0) in drift and diffusion)
+//keeps the reported path non-negative even when the Feller condition 2κθ > σ² fails
+#[allow(clippy::too_many_arguments)]
+fn generate_cir_path<P: PathSink>(
+    init_value: f64,
+    kappa: f64,        // Speed of mean reversion
+    theta: f64,        // Long-run level
+    sigma: f64,        // Volatility scale of √x
+    steps: usize,
+    dt: f64,
+    is_antithetic: bool,
+    shocks: ShockSource,
+    rng: &mut PathRng,
+) -> P {
+    let mut path = P::with_capacity(steps + 1);
+    path.push(init_value);
+    let mut current = init_value;
+
+    let sqrt_dt = dt.sqrt();
+    let normal = Normal::new(0.0, 1.0).unwrap();
+
+    for step in 0..steps {
+        let mut z = shocks.normal(step, &normal, rng);
+        if is_antithetic {
+            z = -z;
+        }
+
+        let x_plus = current.max(0.0);
+        let drift = kappa * (theta - x_plus) * dt;
+        current = current + drift + sigma * x_plus.sqrt() * sqrt_dt * z;
+        path.push(current.max(0.0));
+        path.record(StepDiagnostics { step, price: current.max(0.0), variance: sigma * sigma * x_plus, drift, shock: z, jump: 0.0 });
+    }
+
+    path
+}
+
+//Vasicek short rate: dr = κ(θ - r)dt + σ dW, stepped with the exact Gaussian transition
+//r' = θ + (r - θ)e^{-κdt} + σ√((1 - e^{-2κdt}) / 2κ)·z so the discretisation adds no bias to
+//the mean or variance. Starts from init_value as the initial rate and, unlike MeanReversion,
+//is not floored: rates can go negative
+#[allow(clippy::too_many_arguments)]
+fn generate_vasicek_path<P: PathSink>(
+    init_value: f64,
+    kappa: f64,        // Speed of mean reversion
+    theta: f64,        // Long-run rate
+    sigma: f64,        // Rate volatility
+    steps: usize,
+    dt: f64,
+    is_antithetic: bool,
+    shocks: ShockSource,
+    rng: &mut PathRng,
+) -> P {
+    let mut path = P::with_capacity(steps + 1);
+    path.push(init_value);
+    let mut current = init_value;
+
+    let decay = (-kappa * dt).exp();
+    let step_sd = sigma * ((1.0 - decay * decay) / (2.0 * kappa)).sqrt();
+    let normal = Normal::new(0.0, 1.0).unwrap();
+
+    for step in 0..steps {
+        let mut z = shocks.normal(step, &normal, rng);
+        if is_antithetic {
+            z = -z;
+        }
+
+        let drift = (theta - current) * (1.0 - decay);
+        current = theta + (current - theta) * decay + step_sd * z;
+        path.push(current);
+        path.record(StepDiagnostics { step, price: current, variance: sigma * sigma, drift, shock: z, jump: 0.0 });
+    }
+
+    path
+}
+
+//SABR forward: dF = α F^β dW₁, dα = ν α dW₂, corr(dW₁, dW₂) = ρ, starting from init_value as
+//f0 and alpha as the initial vol. The vol steps exactly (lognormal, martingale) and the forward
+//takes an Euler step with α from the start of the step. Zero is absorbing: a forward that
+//would cross it stays at 0, the usual boundary for β < 1
+#[allow(clippy::too_many_arguments)]
+fn generate_sabr_path<P: PathSink>(
+    init_value: f64,
+    alpha: f64,        // Initial volatility
+    beta: f64,         // CEV exponent of the forward
+    rho: f64,          // Correlation between forward and vol shocks
+    nu: f64,           // Volatility of volatility
+    steps: usize,
+    dt: f64,
+    is_antithetic: bool,
+    shocks: ShockSource,
+    rng: &mut PathRng,
+) -> P {
+    let mut path = P::with_capacity(steps + 1);
+    path.push(init_value);
+    let mut forward = init_value;
+    let mut vol = alpha;
+
+    let sqrt_dt = dt.sqrt();
+    let rho_perp = (1.0 - rho.powi(2)).max(0.0).sqrt();
+    let normal = Normal::new(0.0, 1.0).unwrap();
+
+    for step in 0..steps {
+        // Same two-draw layout as Heston, negated together for the antithetic partner
+        let mut z1 = shocks.normal(2 * step, &normal, rng);
+        let mut z_perp = shocks.normal(2 * step + 1, &normal, rng);
+        if is_antithetic {
+            z1 = -z1;
+            z_perp = -z_perp;
+        }
+        let z2 = rho * z1 + rho_perp * z_perp;
+
+        let local_vol = if forward > 0.0 { vol * forward.powf(beta) } else { 0.0 };
+        if forward > 0.0 {
+            forward = (forward + local_vol * sqrt_dt * z1).max(0.0);
+        }
+        vol *= (nu * sqrt_dt * z2 - 0.5 * nu * nu * dt).exp();
+
+        path.push(forward);
+        path.record(StepDiagnostics { step, price: forward, variance: local_vol * local_vol, drift: 0.0, shock: z1, jump: 0.0 });
+    }
+
+    path
+}
+
+//local volatility: dS/S = μ dt + σ(S, t) dW, stepped in logs like GBM with σ read at the price
+//and time (step * dt) at the start of each step. sigma is any surface, e.g. VolSurface::sigma
+#[allow(clippy::too_many_arguments)]
+fn generate_local_vol_path<P: PathSink>(
+    init_price: f64,
+    mu: f64,
+    sigma: impl Fn(f64, f64) -> f64,
+    steps: usize,
+    dt: f64,
+    is_antithetic: bool,
+    shocks: ShockSource,
+    rng: &mut PathRng,
+) -> P {
+    let mut path = P::with_capacity(steps + 1);
+    path.push(init_price);
+    let mut current_price = init_price;
+
+    let sqrt_dt = dt.sqrt();
+    let normal = Normal::new(0.0, 1.0).unwrap();
+
+    for step in 0..steps {
+        let mut z = shocks.normal(step, &normal, rng);
+        if is_antithetic {
+            z = -z;
+        }
+        let vol = sigma(current_price, step as f64 * dt);
+        let drift = (mu - 0.5 * vol * vol) * dt;
+        current_price *= (drift + vol * sqrt_dt * z).exp();
+        path.push(current_price);
+        path.record(StepDiagnostics { step, price: current_price, variance: vol * vol, drift, shock: z, jump: 0.0 });
+    }
+
+    path
+}
+
+//two-state Markov regime GBM: volatility is sigma_low or sigma_high and the regime flips
+//with the given per-step probabilities. The first regime is drawn from the stationary mix
+//(P(high) = p_lh / (p_lh + p_hl)). Regime draws are uniforms, so antithetic partners share
+//the same regime sequence and only the normal shocks are mirrored
+#[allow(clippy::too_many_arguments)]
+fn generate_regime_switching_path<P: PathSink>(
+    init_price: f64,
+    mu: f64,
+    sigma_low: f64,
+    sigma_high: f64,
+    p_low_to_high: f64,
+    p_high_to_low: f64,
+    steps: usize,
+    dt: f64,
+    is_antithetic: bool,
+    shocks: ShockSource,
+    rng: &mut PathRng,
+) -> P {
+    let mut path = P::with_capacity(steps + 1);
+    path.push(init_price);
+    let mut current_price = init_price;
+
+    let switch_total = p_low_to_high + p_high_to_low;
+    let stationary_high = if switch_total > 0.0 { p_low_to_high / switch_total } else { 0.0 };
+    let mut in_high = rng.random::<f64>() < stationary_high;
+    let normal = Normal::new(0.0, 1.0).unwrap();
+
+    for step in 0..steps {
+        let sigma = if in_high { sigma_high } else { sigma_low };
+        let mut z = shocks.normal(step, &normal, rng);
+        if is_antithetic {
+            z = -z;
+        }
+
+        let drift = (mu - 0.5 * sigma.powi(2)) * dt;
+        let next_price = current_price * (drift + sigma * dt.sqrt() * z).exp();
+        path.push(next_price);
+        path.record(StepDiagnostics { step, price: next_price, variance: sigma.powi(2), drift, shock: z, jump: 0.0 });
+        current_price = next_price;
+
+        let switch_prob = if in_high { p_high_to_low } else { p_low_to_high };
+        if rng.random::<f64>() < switch_prob {
+            in_high = !in_high;
+        }
+    }
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //a small valid GBM run with per-step rates, the base every test changes what it needs from
+    fn gbm_params() -> CoreSimParams {
+        CoreSimParams {
+            initial_price: 100.0,
+            horizon: 20,
+            num_paths: 2_000,
+            mu: 0.0005,
+            sigma: 0.02,
+            seed: 42,
+            dt: 1,
+            model_type: "GBM".into(),
+            confidence_level: 0.95,
+            block_size: 1,
+            student_t_df: 5.0,
+            ..Default::default()
+        }
+    }
+
+    fn stats_of(params: CoreSimParams, hist_log_returns: &[f64]) -> Result<SimStats> {
+        simulate_stats(params, hist_log_returns, &RunControl::default())
+    }
+
+    fn run(params: &CoreSimParams) -> SimRun {
+        simulate(params, &[], &RunControl::default()).unwrap()
+    }
+
+    //spread of a statistic over independent reruns, the empirical standard error of its estimator
+    fn rerun_spread(params: &CoreSimParams, runs: u64, statistic: impl Fn(&SimStats) -> f64) -> f64 {
+        let values: Vec<f64> = (0..runs)
+            .map(|run| statistic(&stats_of(CoreSimParams { seed: 1_000 + run as i32, ..params.clone() }, &[]).unwrap()))
+            .collect();
+        estimate_paramaters(&values).unwrap().1
+    }
+
+    #[test]
+    fn validate_params_rejects_empty_paths_and_zero_horizon() {
+        assert!(validate_params(&gbm_params()).is_ok());
+        let no_paths = CoreSimParams { num_paths: 0, ..gbm_params() };
+        assert!(validate_params(&no_paths).is_err());
+        assert!(run_simulation(no_paths, Vec::new()).is_err());
+        let no_horizon = CoreSimParams { horizon: 0, ..gbm_params() };
+        assert!(validate_params(&no_horizon).is_err());
+        assert!(run_simulation(no_horizon, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn max_drawdown_of_rising_path_is_zero() {
+        let summary = PathSummary::of(&[100.0, 101.0, 103.0, 103.0, 110.0]);
+        assert_eq!(summary.max_drawdown, 0.0);
+    }
+
+    #[test]
+    fn max_drawdown_of_v_shaped_path_is_peak_to_trough() {
+        //120 -> 90 is the deepest fall, the recovery to 130 and dip to 117 is only 10%
+        let summary = PathSummary::of(&[100.0, 120.0, 105.0, 90.0, 130.0, 117.0]);
+        assert!((summary.max_drawdown - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn stratified_first_step_lowers_standard_error() {
+        //one step, so the stratified shock is the whole path
+        let plain = CoreSimParams { horizon: 1, num_paths: 500, ..gbm_params() };
+        let stratified = CoreSimParams { variance_reduction: VarianceReduction::Stratified, ..plain.clone() };
+        let plain_error = rerun_spread(&plain, 30, |s| s.mean);
+        let stratified_error = rerun_spread(&stratified, 30, |s| s.mean);
+        assert!(stratified_error < plain_error / 5.0, "stratified {} plain {}", stratified_error, plain_error);
+    }
+
+    #[test]
+    fn up_and_out_call_is_worthless_when_every_path_breaches() {
+        //each path ends in the money but touched 120 on the way
+        let paths: Vec<PathSummary> = [[100.0, 125.0, 110.0], [100.0, 121.0, 115.0], [100.0, 140.0, 105.0]]
+            .iter()
+            .map(|path| PathSummary::of(path))
+            .collect();
+        assert_eq!(price_barrier_option(&paths, 100.0, 120.0, true, true, 0.0, 1.0), 0.0);
+        //a barrier none of them reached leaves the plain call payoff
+        let price = price_barrier_option(&paths, 100.0, 150.0, true, true, 0.0, 1.0);
+        assert!((price - 10.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn barrier_at_initial_price_knocks_out_immediately() {
+        for is_up_and_out in [true, false] {
+            let params = CoreSimParams {
+                price_option: true,
+                strike: 100.0,
+                is_call: true,
+                price_barrier: true,
+                barrier: 100.0,
+                is_up_and_out,
+                ..gbm_params()
+            };
+            let stats = stats_of(params, &[]).unwrap();
+            assert_eq!(stats.barrier_option_price, Some(0.0));
+            assert!(stats.option_price.unwrap() > 0.0);
+        }
+    }
+
+    #[test]
+    fn antithetic_partner_negates_every_shock() {
+        let params = CoreSimParams { use_antithetic: true, record_diagnostics: true, ..gbm_params() };
+        let shocks = |path: i32| run(&CoreSimParams { diagnostic_path: path, ..params.clone() }).3.iter().map(|step| step.shock).collect::<Vec<f64>>();
+        let (base, partner) = (shocks(4), shocks(5));
+        assert_eq!(base.len(), params.horizon as usize);
+        assert!(base.iter().zip(&partner).all(|(a, b)| *a == -*b));
+    }
+
+    #[test]
+    fn antithetic_pair_mean_is_closer_to_expectation_than_independent_pair() {
+        let plain = CoreSimParams { num_paths: 4_000, ..gbm_params() };
+        let antithetic = CoreSimParams { use_antithetic: true, ..plain.clone() };
+        let expected = 100.0 * (0.0005_f64 * 20.0).exp();
+        let pair_error = |params: &CoreSimParams| {
+            let prices = run(params).2;
+            prices.chunks(2).map(|pair| ((pair[0] + pair[1]) / 2.0 - expected).powi(2)).sum::<f64>() / (prices.len() / 2) as f64
+        };
+        let (antithetic_error, plain_error) = (pair_error(&antithetic), pair_error(&plain));
+        assert!(antithetic_error < plain_error / 10.0, "antithetic {} plain {}", antithetic_error, plain_error);
+    }
+
+    #[test]
+    fn antithetic_mean_varies_less_than_plain_across_reruns() {
+        let plain = CoreSimParams { num_paths: 1_000, ..gbm_params() };
+        let antithetic = CoreSimParams { use_antithetic: true, ..plain.clone() };
+        let stats = stats_of(antithetic.clone(), &[]).unwrap();
+        assert_eq!((stats.paths, stats.effective_samples), (1_000, 500));
+        let (plain_spread, antithetic_spread) = (rerun_spread(&plain, 30, |s| s.mean), rerun_spread(&antithetic, 30, |s| s.mean));
+        assert!(antithetic_spread < plain_spread / 3.0, "antithetic {} plain {}", antithetic_spread, plain_spread);
+    }
+
+    fn jump_dominated_params() -> CoreSimParams {
+        CoreSimParams { model_type: "JumpDiffusion".into(), sigma: 0.002, lambda: 0.2, mu_j: 0.0, sigma_j: 0.1, num_paths: 1_000, use_antithetic: true, ..gbm_params() }
+    }
+
+    #[test]
+    fn antithetic_partner_mirrors_jump_sizes() {
+        //mu_j 0, so reflection around it is plain negation, with the counts shared
+        let params = CoreSimParams { record_diagnostics: true, ..jump_dominated_params() };
+        let jumps = |path: i32| run(&CoreSimParams { diagnostic_path: path, ..params.clone() }).3.iter().map(|step| step.jump).collect::<Vec<f64>>();
+        let (base, partner) = (jumps(6), jumps(7));
+        assert!(base.iter().any(|jump| *jump != 0.0));
+        assert!(base.iter().zip(&partner).all(|(a, b)| (a + b).abs() < 1e-12));
+    }
+
+    #[test]
+    fn jump_mirroring_reduces_variance_when_jumps_dominate() {
+        //with the diffusion this small, negating z alone would leave the pair means about as
+        //noisy as plain sampling; the spread only drops if the jump sizes are mirrored too
+        let antithetic = jump_dominated_params();
+        let plain = CoreSimParams { use_antithetic: false, ..antithetic.clone() };
+        let (plain_spread, antithetic_spread) = (rerun_spread(&plain, 30, |s| s.mean), rerun_spread(&antithetic, 30, |s| s.mean));
+        assert!(antithetic_spread < plain_spread / 3.0, "antithetic {} plain {}", antithetic_spread, plain_spread);
+    }
+
+    #[test]
+    fn p_square_quantiles_track_exact_ones() {
+        let mut rng = PathRng::seed_from_u64(11);
+        let normal = Normal::new(0.0_f64, 0.2).unwrap();
+        let mut prices: Vec<f64> = (0..APPROX_QUANTILE_MIN_PATHS * 5).map(|_| 100.0 * normal.sample(&mut rng).exp()).collect();
+        let drawdowns = vec![0.0; prices.len()];
+
+        let approx = approximate_statistics(&prices, &drawdowns, "GBM", prices.len(), 1, 100.0, 0.95, OutputMode::Price).unwrap();
+        let exact = calculate_statistics(&mut prices, &drawdowns, "GBM", drawdowns.len(), 1, 100.0, 0.95, OutputMode::Price).unwrap();
+        let errors = [
+            approx.p5 - exact.p5,
+            approx.p25 - exact.p25,
+            approx.median - exact.median,
+            approx.p75 - exact.p75,
+            approx.p95 - exact.p95,
+            100.0 * (approx.var95 - exact.var95),
+        ];
+        //in units of the distribution's spread, so the bound doesn't depend on the price level
+        let max_error = errors.iter().map(|e| e.abs()).fold(0.0, f64::max) / exact.std_dev;
+        eprintln!("max P-square quantile error: {:.5} std devs", max_error);
+        assert!(max_error < 0.02, "max quantile error {} std devs", max_error);
+    }
+
+    fn correlation(xs: &[f64], ys: &[f64]) -> f64 {
+        let (mx, my) = (xs.iter().sum::<f64>() / xs.len() as f64, ys.iter().sum::<f64>() / ys.len() as f64);
+        let cov: f64 = xs.iter().zip(ys).map(|(x, y)| (x - mx) * (y - my)).sum();
+        let (vx, vy): (f64, f64) = (xs.iter().map(|x| (x - mx).powi(2)).sum(), ys.iter().map(|y| (y - my).powi(2)).sum());
+        cov / (vx * vy).sqrt()
+    }
+
+    #[test]
+    fn adjacent_paths_have_uncorrelated_first_shocks() {
+        //one step with zero drift, so each log return is sigma times the path's first shock
+        let params = CoreSimParams { horizon: 1, num_paths: 10_000, mu: 0.0, ..gbm_params() };
+        for fast_mode in [false, true] {
+            let prices = run(&CoreSimParams { fast_mode, ..params.clone() }).2;
+            let shocks: Vec<f64> = prices.iter().map(|p| ((p / 100.0).ln() + 0.5 * 0.02 * 0.02) / 0.02).collect();
+            let r = correlation(&shocks[..shocks.len() - 1], &shocks[1..]);
+            //4 standard errors of a zero correlation at this sample size
+            assert!(r.abs() < 0.04, "adjacent shock correlation {} (fast mode {})", r, fast_mode);
+        }
+    }
+
+    //log returns of a bootstrap path drawn straight from the generator
+    fn bootstrap_returns(history: &[f64], steps: usize, block_size: usize, seed: u64) -> Vec<f64> {
+        let mut rng = PathRng::seed_from_u64(seed);
+        let path: Vec<f64> = generate_bootstrap_path(100.0, steps, history, 0.0, block_size, false, &mut rng);
+        path.windows(2).map(|w| (w[1] / w[0]).ln()).collect()
+    }
+
+    #[test]
+    fn block_bootstrap_keeps_autocorrelation() {
+        //AR(1) history with lag-1 autocorrelation 0.6
+        let mut rng = PathRng::seed_from_u64(3);
+        let normal = Normal::new(0.0, 0.01).unwrap();
+        let mut previous = 0.0;
+        let history: Vec<f64> = (0..2_000).map(|_| {
+            previous = 0.6 * previous + normal.sample(&mut rng);
+            previous
+        }).collect();
+        let lag_1 = |returns: &[f64]| correlation(&returns[..returns.len() - 1], &returns[1..]);
+
+        let blocks = lag_1(&bootstrap_returns(&history, 20_000, 20, 5));
+        let iid = lag_1(&bootstrap_returns(&history, 20_000, 1, 5));
+        assert!(blocks > 0.45, "block bootstrap autocorrelation {}", blocks);
+        assert!(iid.abs() < 0.05, "iid bootstrap autocorrelation {}", iid);
+    }
+
+    #[test]
+    fn bootstrap_blocks_wrap_around_the_history() {
+        //each return names its own index, so the walk through the history can be read back
+        let history = [0.0, 0.01, 0.02, 0.03, 0.04];
+        let indices: Vec<usize> = bootstrap_returns(&history, 600, 3, 9).iter().map(|r| (r / 0.01).round() as usize).collect();
+        let mut wrapped = false;
+        for block in indices.chunks(3) {
+            for pair in block.windows(2) {
+                assert_eq!(pair[1], (pair[0] + 1) % history.len(), "block {:?} is not contiguous", block);
+                wrapped |= pair[1] == 0;
+            }
+        }
+        assert!(wrapped, "no block ran past the end of the history");
+    }
+
+    //one step with mu cancelling the -sigma²/2 drift, so ln(S1/S0) = sigma·z and exp overflows
+    //for shocks above roughly 705 / sigma
+    fn overflowing_params(sigma: f32) -> CoreSimParams {
+        CoreSimParams { horizon: 1, num_paths: 4_000, sigma, mu: 0.5 * sigma * sigma, chart_width: 320, chart_height: 240, ..gbm_params() }
+    }
+
+    #[test]
+    fn widespread_overflow_is_a_clean_error() {
+        //every shock above about 0.5, a third of the paths
+        let error = simulate(&overflowing_params(1_400.0), &[], &RunControl::default()).unwrap_err().to_string();
+        assert!(error.contains("diverged to non-finite prices"), "{}", error);
+    }
+
+    #[test]
+    fn rare_overflow_is_counted_and_left_out() {
+        //shocks above about 2.5, around 0.6% of the paths against the 1% limit
+        let output = run_simulation(overflowing_params(282.0), Vec::new()).unwrap();
+        assert!(output.stats.diverged_paths > 0);
+        assert_eq!(output.terminal_prices.len() + output.stats.diverged_paths, 4_000);
+        assert!(output.terminal_prices.iter().all(|p| p.is_finite()));
+        assert!(output.stats.mean.is_finite() && output.stats.p95.is_finite());
+    }
+
+    #[test]
+    fn garch_estimate_recovers_known_parameters() {
+        let (omega, alpha, beta): (f64, f64, f64) = (1e-5, 0.1, 0.85);
+        let mut rng = PathRng::seed_from_u64(7);
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        let mut variance = omega / (1.0 - alpha - beta);
+        let returns: Vec<f64> = (0..5_000).map(|_| {
+            let r = variance.sqrt() * normal.sample(&mut rng);
+            variance = omega + alpha * r * r + beta * variance;
+            r
+        }).collect();
+
+        let (omega_hat, alpha_hat, beta_hat) = estimate_garch_params(&returns).unwrap();
+        assert!((alpha_hat - alpha).abs() < 0.04, "alpha {}", alpha_hat);
+        assert!((beta_hat - beta).abs() < 0.05, "beta {}", beta_hat);
+        assert!((omega_hat / omega - 1.0).abs() < 0.5, "omega {}", omega_hat);
+    }
+
+    #[test]
+    fn annualized_gbm_mean_hits_the_annual_target() {
+        //a year of steps at each frequency, with annual mu and sigma
+        let target = 100.0 * 0.08_f64.exp();
+        for (step_frequency, steps) in [(StepFrequency::Daily, 252), (StepFrequency::Weekly, 52), (StepFrequency::Monthly, 12)] {
+            let params = CoreSimParams { step_frequency, horizon: steps, num_paths: 10_000, mu: 0.08, sigma: 0.2, ..gbm_params() };
+            assert!((step_dt(&params) * steps as f64 - 1.0).abs() < 1e-12);
+            let stats = run(&params).0;
+            assert!((stats.mean - target).abs() < 4.0 * stats.mean_std_error, "{:?} mean {} target {}", step_frequency, stats.mean, target);
+        }
+    }
+
+    #[test]
+    fn vasicek_mean_converges_to_negative_theta() {
+        //kappa·T = 25, so the start at 5% is long forgotten and rates sit around -0.5%
+        let params = CoreSimParams {
+            model_type: "Vasicek".into(),
+            initial_price: 0.05,
+            horizon: 50,
+            vasicek_kappa: 0.5,
+            vasicek_theta: -0.005,
+            sigma: 0.01,
+            ..gbm_params()
+        };
+        let stats = run(&params).0;
+        assert!((stats.mean + 0.005).abs() < 4.0 * stats.mean_std_error, "mean {} se {}", stats.mean, stats.mean_std_error);
+        //stationary spread sigma / sqrt(2 kappa), unclamped on both sides of zero
+        assert!((stats.std_dev - 0.01).abs() < 0.001, "std dev {}", stats.std_dev);
+        assert!(stats.p5 < 0.0 && stats.p95 > 0.0);
+    }
+
+    #[test]
+    fn gbm_mean_converges_to_the_analytic_mean() {
+        //16x the paths should land about 4x closer, allow for noise with a 4 SE band at each size
+        let errors: Vec<f64> = [500, 8_000]
+            .into_iter()
+            .map(|num_paths| {
+                let stats = run(&CoreSimParams { num_paths, ..gbm_params() }).0;
+                let analytic = stats.analytic_mean.unwrap();
+                assert!((stats.mean - analytic).abs() < 4.0 * stats.mean_std_error, "{num_paths} paths: mean {} analytic {analytic}", stats.mean);
+                stats.mean_std_error
+            })
+            .collect();
+        assert!(errors[1] < errors[0] / 3.0, "standard errors {errors:?}");
+    }
+
+    #[test]
+    fn importance_sampling_tightens_deep_tail_var() {
+        //at 99% plain sampling has ~5 of 500 paths in the tail, the shifted drift puts about half there
+        let plain = CoreSimParams { num_paths: 500, confidence_level: 0.99, ..gbm_params() };
+        let shifted = CoreSimParams { variance_reduction: VarianceReduction::ImportanceSampling, ..plain.clone() };
+        let (plain_spread, shifted_spread) = (rerun_spread(&plain, 30, |s| s.var95), rerun_spread(&shifted, 30, |s| s.var95));
+        assert!(shifted_spread < plain_spread / 2.0, "plain {plain_spread} importance {shifted_spread}");
+    }
+
+    #[test]
+    fn winsorized_sigma_ignores_a_bad_tick() {
+        let mut rng = PathRng::seed_from_u64(11);
+        let normal = Normal::new(0.0005, 0.01).unwrap();
+        let clean: Vec<f64> = (0..1_000).map(|_| normal.sample(&mut rng)).collect();
+        let mut dirty = clean.clone();
+        //a misplaced decimal point, price x10 for one close
+        dirty[500] = 10f64.ln();
+
+        let (_, clean_sigma) = estimate_paramaters(&clean).unwrap();
+        let (_, naive_sigma) = estimate_paramaters(&dirty).unwrap();
+        let (_, robust_sigma) = estimate_paramaters(&winsorize_returns(&dirty, 1.0)).unwrap();
+        assert!(naive_sigma > 5.0 * clean_sigma, "naive sigma {naive_sigma} clean {clean_sigma}");
+        assert!((robust_sigma - clean_sigma).abs() < 0.1 * clean_sigma, "robust sigma {robust_sigma} clean {clean_sigma}");
+    }
+
+    #[test]
+    fn winsorized_price_changes_ignore_a_bad_tick() {
+        //an OU level series around 50 with one print at 500
+        let mut rng = PathRng::seed_from_u64(12);
+        let normal = Normal::new(0.0, 0.5).unwrap();
+        let mut level = 50.0;
+        let clean: Vec<f64> = (0..1_000).map(|_| {
+            level += 0.1 * (50.0 - level) + normal.sample(&mut rng);
+            level
+        }).collect();
+        let mut dirty = clean.clone();
+        dirty[500] = 500.0;
+
+        let (_, _, clean_sigma) = estimate_ou_params(&clean, 1.0).unwrap();
+        let (_, _, naive_sigma) = estimate_ou_params(&dirty, 1.0).unwrap();
+        let (_, _, robust_sigma) = estimate_ou_params(&winsorize_price_changes(&dirty, 1.0), 1.0).unwrap();
+        assert!(naive_sigma > 5.0 * clean_sigma, "naive sigma {naive_sigma} clean {clean_sigma}");
+        assert!((robust_sigma - clean_sigma).abs() < 0.2 * clean_sigma, "robust sigma {robust_sigma} clean {clean_sigma}");
+    }
+
+    #[test]
+    fn horizon_history_bars_follow_the_history_frequency() {
+        let daily_steps = CoreSimParams { step_frequency: StepFrequency::Daily, horizon: 20, ..gbm_params() };
+        let bars = |history_frequency| horizon_history_bars(&CoreSimParams { history_frequency, ..daily_steps.clone() });
+        assert_eq!((bars(Frequency::Daily), bars(Frequency::Weekly), bars(Frequency::Monthly)), (20, 4, 1));
+    }
+
+    #[test]
+    fn ensemble_solves_the_drift_target_per_model() {
+        //every default ensemble model runs, GBM calibrated to the target and JumpDiffusion on its own mu
+        let base = CoreSimParams { target_mean_price: 110.0, num_paths: 4_000, ..gbm_params() };
+        let history: Vec<f64> = (0..100).map(|i| if i % 2 == 0 { 0.01 } else { -0.009 }).collect();
+        let results = run_model_ensemble(base.clone(), &["GBM", "JumpDiffusion", "Bootstrap"], &history).unwrap();
+        let (_, gbm) = &results[0];
+        assert!((gbm.mean - 110.0).abs() < 4.0 * gbm.mean_std_error, "GBM mean {}", gbm.mean);
+        assert!(gbm.implied_mu.is_some());
+        assert_eq!(results[1].1.implied_mu, None);
+
+        let sweep = ParamSweep { field: SweepField::Sigma, start: 0.01, end: 0.03, step: 0.01 };
+        for (sigma, stats) in run_sweep(base.clone(), sweep, &[]).unwrap() {
+            assert!((stats.mean - 110.0).abs() < 4.0 * stats.mean_std_error, "sigma {} mean {}", sigma, stats.mean);
+        }
+        assert!(run_sweep(base, ParamSweep { field: SweepField::Mu, ..sweep }, &[]).is_err());
+    }
+
+    #[test]
+    fn unknown_model_is_an_error_not_a_panic() {
+        let params = CoreSimParams { model_type: "Brownian Bridge".into(), ..gbm_params() };
+        let error = validate_params(&params).unwrap_err();
+        assert!(error.to_string().contains("Brownian Bridge"), "{error}");
+        assert!(simulate(&params, &[], &RunControl::default()).is_err());
+    }
+
+    #[test]
+    fn hawkes_jump_counts_are_over_dispersed() {
+        //no diffusion and fixed 0.1 log jumps, so each terminal price reads back as a jump count
+        let dispersion = |excitation: f32| {
+            let params = CoreSimParams {
+                model_type: "HawkesJump".into(),
+                mu: 0.0,
+                sigma: 0.0,
+                lambda: 0.05,
+                mu_j: 0.1,
+                sigma_j: 0.0,
+                hawkes_excitation: excitation,
+                hawkes_decay: 1.0,
+                horizon: 100,
+                ..gbm_params()
+            };
+            let counts: Vec<f64> = run(&params).2.iter().map(|price| ((price / 100.0).ln() / 0.1).round()).collect();
+            let (mean, std_dev) = estimate_paramaters(&counts).unwrap();
+            std_dev.powi(2) / mean
+        };
+        let (poisson, hawkes) = (dispersion(0.0), dispersion(0.5));
+        assert!((poisson - 1.0).abs() < 0.15, "constant intensity variance / mean {poisson}");
+        assert!(hawkes > 1.5, "self-exciting variance / mean {hawkes}");
+    }
+
+    #[test]
+    fn bootstrap_without_history_is_an_error() {
+        let params = CoreSimParams { model_type: "Bootstrap".into(), ..gbm_params() };
+        assert!(run_simulation(params, Vec::new()).is_err());
+    }
+}
@@ -1,79 +1,653 @@
-use anyhow::Result;
-use chrono::NaiveDate;
+use anyhow::{Result, anyhow};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
+use parquet::data_type::{DoubleType, Int64Type};
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
 use serde::{Deserialize};
-use std::{collections::BTreeMap, path::PathBuf};
+use serde_json::json;
+use std::{collections::{BTreeMap, BTreeSet}, fs::{self, File}, path::{Path, PathBuf}, sync::Arc};
 
-#[derive(Debug, Deserialize, Clone)]
+use crate::core_sim::{SimStats, StepDiagnostics, VolSurface};
+use crate::plotting::{BAND_QUANTILES, compute_quantile_bands};
+
+#[derive(Debug, Clone)]
 pub struct StockRecord {
-    #[serde(rename = "<Ticker>")]
     pub ticker: String,
-    #[serde(rename = "<DTYYYYMMDD>", deserialize_with = "deserialize_date")]
     pub date: chrono::NaiveDate,
-    #[serde(rename = "<Open>")]
+    //full timestamp of an intraday bar, None for daily files
+    pub datetime: Option<NaiveDateTime>,
     pub open: f64,
-    #[serde(rename = "<High>")]
     pub high: f64,
-    #[serde(rename = "<Low>")]
     pub low: f64,
-    #[serde(rename = "<Close>")]
     pub close: f64,
-    #[serde(rename = "<Volume>")]
     pub volume: i64,
+    //split/dividend adjusted close, only present when the source has an "Adj Close" column
+    pub adj_close: Option<f64>,
+}
+
+impl StockRecord {
+    //sort key, daily records count as midnight
+    pub fn timestamp(&self) -> NaiveDateTime {
+        self.datetime.unwrap_or(self.date.and_time(NaiveTime::MIN))
+    }
+}
+
+//Stooq export layout. Intraday files add a <TIME> column (HHMMSS), some tools instead write
+//the time into the date cell as "YYYYMMDD HHMMSS"
+#[derive(Debug, Deserialize)]
+struct StooqRecord {
+    #[serde(rename = "<Ticker>")]
+    ticker: String,
+    #[serde(rename = "<DTYYYYMMDD>", deserialize_with = "deserialize_timestamp")]
+    date: Timestamp,
+    #[serde(rename = "<TIME>", default, deserialize_with = "deserialize_time")]
+    time: Option<NaiveTime>,
+    #[serde(rename = "<Open>")]
+    open: f64,
+    #[serde(rename = "<High>")]
+    high: f64,
+    #[serde(rename = "<Low>")]
+    low: f64,
+    #[serde(rename = "<Close>")]
+    close: f64,
+    #[serde(rename = "<Volume>")]
+    volume: i64,
+    #[serde(rename = "Adj Close", default)]
+    adj_close: Option<f64>,
+}
+
+impl StooqRecord {
+    fn into_stock_record(self) -> StockRecord {
+        let (date, time) = self.date;
+        StockRecord {
+            ticker: self.ticker,
+            date,
+            datetime: self.time.or(time).map(|t| date.and_time(t)),
+            open: self.open,
+            high: self.high,
+            low: self.low,
+            close: self.close,
+            volume: self.volume,
+            adj_close: self.adj_close,
+        }
+    }
+}
+
+//Yahoo Finance export layout: Date,Open,High,Low,Close,Adj Close,Volume with no ticker
+//column. Yahoo writes "null" for missing rows, those parse to None and get skipped
+#[derive(Debug, Deserialize)]
+struct YahooRecord {
+    #[serde(rename = "Date", deserialize_with = "deserialize_timestamp")]
+    date: Timestamp,
+    #[serde(rename = "Open", deserialize_with = "csv::invalid_option")]
+    open: Option<f64>,
+    #[serde(rename = "High", deserialize_with = "csv::invalid_option")]
+    high: Option<f64>,
+    #[serde(rename = "Low", deserialize_with = "csv::invalid_option")]
+    low: Option<f64>,
+    #[serde(rename = "Close", deserialize_with = "csv::invalid_option")]
+    close: Option<f64>,
+    #[serde(rename = "Adj Close", default, deserialize_with = "csv::invalid_option")]
+    adj_close: Option<f64>,
+    #[serde(rename = "Volume", default, deserialize_with = "csv::invalid_option")]
+    volume: Option<i64>,
+}
+
+impl YahooRecord {
+    fn into_stock_record(self, ticker: &str) -> Option<StockRecord> {
+        let close = self.close?;
+        let (date, time) = self.date;
+        Some(StockRecord {
+            ticker: ticker.to_string(),
+            date,
+            datetime: time.map(|t| date.and_time(t)),
+            open: self.open.unwrap_or(close),
+            high: self.high.unwrap_or(close),
+            low: self.low.unwrap_or(close),
+            close,
+            volume: self.volume.unwrap_or(0),
+            adj_close: self.adj_close,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CsvSchema {
+    Stooq,
+    Yahoo,
+}
+
+fn detect_schema(headers: &csv::StringRecord) -> Result<CsvSchema> {
+    let has = |name: &str| headers.iter().any(|h| h.trim() == name);
+
+    if has("<Ticker>") && has("<DTYYYYMMDD>") && has("<Close>") {
+        Ok(CsvSchema::Stooq)
+    } else if has("Date") && has("Close") {
+        Ok(CsvSchema::Yahoo)
+    } else {
+        Err(anyhow!(
+            "unrecognised CSV header [{}], expected Stooq (<Ticker>,<DTYYYYMMDD>,...,<Close>) or Yahoo (Date,...,Close) columns",
+            headers.iter().collect::<Vec<_>>().join(",")
+        ))
+    }
+}
 
+//tried in order, first match wins. Stooq style first, then ISO, then US/EU slashed dates
+//(MM/DD before DD/MM, so ambiguous days like 03/04 read as March 4th)
+const DATE_FORMATS: [&str; 5] = ["%Y%m%d", "%Y-%m-%d", "%m/%d/%Y", "%d/%m/%Y", "%d.%m.%Y"];
+
+//time of day formats, Stooq's HHMMSS first
+const TIME_FORMATS: [&str; 4] = ["%H%M%S", "%H:%M:%S", "%H:%M", "%H%M"];
+
+//a date cell with its optional time of day
+type Timestamp = (NaiveDate, Option<NaiveTime>);
+
+fn deserialize_timestamp<'de, D>(deserializer : D) -> Result<Timestamp, D::Error>
+where 
+    D: serde::Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    parse_timestamp(s.trim()).map_err(serde::de::Error::custom)
 }
 
-fn deserialize_date<'de, D>(deserializer : D) -> Result<NaiveDate, D::Error>
+fn deserialize_time<'de, D>(deserializer : D) -> Result<Option<NaiveTime>, D::Error>
 where 
     D: serde::Deserializer<'de>,
 {
     let s = String::deserialize(deserializer)?;
-    NaiveDate::parse_from_str(&s, "%Y%m%d").map_err(serde::de::Error::custom)
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok(None);
+    }
+    parse_time(s).map(Some).map_err(serde::de::Error::custom)
 }
 
+//"YYYYMMDD HHMMSS", "YYYY-MM-DD HH:MM:SS", "YYYY-MM-DDTHH:MM:SS" or a plain date
+fn parse_timestamp(s: &str) -> Result<Timestamp> {
+    match s.split_once([' ', 'T']) {
+        Some((date, time)) => Ok((parse_date(date.trim())?, Some(parse_time(time.trim())?))),
+        None => Ok((parse_date(s)?, None)),
+    }
+}
+
+fn parse_time(s: &str) -> Result<NaiveTime> {
+    //numeric exports drop the leading zero before 10am, 93000 is 09:30:00
+    let padded = if s.len() == 5 && s.bytes().all(|b| b.is_ascii_digit()) { format!("0{}", s) } else { s.to_string() };
+    TIME_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveTime::parse_from_str(&padded, fmt).ok())
+        .ok_or_else(|| anyhow!("unrecognised time '{}', tried formats: {}", s, TIME_FORMATS.join(", ")))
+}
+
+fn parse_date(s: &str) -> Result<NaiveDate> {
+    DATE_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(s, fmt).ok())
+        .ok_or_else(|| anyhow!("unrecognised date '{}', tried formats: {}", s, DATE_FORMATS.join(", ")))
+}
+
+//rows between progress callbacks while streaming a file
+const PROGRESS_ROWS: usize = 100_000;
+
 pub fn load_all_records(path: PathBuf) -> Result<(Vec<StockRecord>, Vec<String>)> {
-    let mut reader = csv::Reader::from_path(path)?;
+    load_records_streaming(path, None, |_| {})
+}
+
+//load_all_records for files too big to hold whole. Rows are read one at a time and, with a
+//ticker filter, rows of other tickers are dropped before they are deserialized, so memory stays
+//bounded by the kept symbols. progress gets the running row count every PROGRESS_ROWS rows and
+//once more at the end. The ticker list only names tickers that were kept
+pub fn load_records_streaming(path: PathBuf, tickers_wanted: Option<&[String]>, mut progress: impl FnMut(usize)) -> Result<(Vec<StockRecord>, Vec<String>)> {
+    let wanted = |ticker: &str| tickers_wanted.is_none_or(|list| list.iter().any(|t| t == ticker));
+
+    //Yahoo files carry no ticker column, the file name (e.g. AAPL.csv) stands in for it
+    let file_ticker = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_uppercase())
+        .unwrap_or_default();
+
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::Headers).from_path(path)?;
+    let headers = reader.headers()?.clone();
+    let schema = detect_schema(&headers)?;
     let mut records = Vec::new();
     //use BtreeMap instead of Hashmap for better:
     //Sorted data, lower memory usage, O(logn) as avg,
     let mut tickers = BTreeMap::new();
+    let mut row = csv::StringRecord::new();
+    let mut rows = 0;
 
-    for result in reader.deserialize() {
-        let record: StockRecord = result?;
-        tickers.insert(record.ticker.clone(), true);
-        records.push(record);
+    match schema {
+        CsvSchema::Stooq => {
+            //detect_schema guarantees the column
+            let ticker_column = headers.iter().position(|h| h.trim() == "<Ticker>").unwrap_or(0);
+            while reader.read_record(&mut row)? {
+                rows += 1;
+                if rows % PROGRESS_ROWS == 0 {
+                    progress(rows);
+                }
+                if !wanted(row.get(ticker_column).unwrap_or("")) {
+                    continue;
+                }
+                let parsed: StooqRecord = row.deserialize(Some(&headers))?;
+                tickers.insert(parsed.ticker.clone(), true);
+                records.push(parsed.into_stock_record());
+            }
+        }
+        //one ticker per file, so the filter keeps or skips the whole file
+        CsvSchema::Yahoo if wanted(&file_ticker) => {
+            while reader.read_record(&mut row)? {
+                rows += 1;
+                if rows % PROGRESS_ROWS == 0 {
+                    progress(rows);
+                }
+                let parsed: YahooRecord = row.deserialize(Some(&headers))?;
+                if let Some(record) = parsed.into_stock_record(&file_ticker) {
+                    records.push(record);
+                }
+            }
+            if !records.is_empty() {
+                tickers.insert(file_ticker, true);
+            }
+        }
+        CsvSchema::Yahoo => {}
     }
+    progress(rows);
 
-    records.sort_by_key(|r| r.date);
+    //timestamps keep intraday bars of one day in order, daily files sort by date as before
+    records.sort_by_key(|r| r.timestamp());
 
     let ticker_list = tickers.keys().cloned().collect();
     Ok((records, ticker_list))
 }
 
-pub fn get_ticker_info(all_data: &[StockRecord], ticker: &str) -> (String, Vec<f64>) {
-    let ticker_data: Vec<&StockRecord> = all_data.iter().filter(|r| r.ticker == ticker).collect();
+//several files (e.g. one per ticker) merged into one record set. A (ticker, timestamp) seen in
+//more than one file keeps the record from the file loaded last, so overlapping ranges don't
+//double up bars
+pub fn load_all_records_multi(paths: &[PathBuf]) -> Result<(Vec<StockRecord>, Vec<String>)> {
+    let mut merged = BTreeMap::new();
+    for path in paths {
+        let (records, _) = load_all_records(path.clone()).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+        for record in records {
+            merged.insert((record.ticker.clone(), record.timestamp()), record);
+        }
+    }
+
+    let mut records: Vec<StockRecord> = merged.into_values().collect();
+    let ticker_list: Vec<String> = records.iter().map(|r| r.ticker.clone()).collect::<BTreeSet<_>>().into_iter().collect();
+    records.sort_by_key(|r| r.timestamp());
+    Ok((records, ticker_list))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Frequency {
+    #[default]
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Frequency {
+    //unknown names fall back to Daily, the granularity of the source data
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "Weekly" => Frequency::Weekly,
+            "Monthly" => Frequency::Monthly,
+            _ => Frequency::Daily,
+        }
+    }
+
+    //bars per year, for annualizing per-bar statistics
+    pub fn periods_per_year(self) -> f64 {
+        match self {
+            Frequency::Daily => 252.0,
+            Frequency::Weekly => 52.0,
+            Frequency::Monthly => 12.0,
+        }
+    }
+
+    //bars of this frequency covering the given number of trading days, at least one.
+    //A week is 5 and a month 21 trading days
+    pub fn bars_for_trading_days(self, days: f64) -> usize {
+        let days_per_bar = match self {
+            Frequency::Daily => 1.0,
+            Frequency::Weekly => 5.0,
+            Frequency::Monthly => 21.0,
+        };
+        (days / days_per_bar).round().max(1.0) as usize
+    }
+}
+
+//groups records per ticker into ISO weeks or calendar months. Each bar keeps the first open,
+//highest high, lowest low, last close/adj close, summed volume and the date of its last record
+pub fn resample_records(records: &[StockRecord], freq: Frequency) -> Vec<StockRecord> {
+    if freq == Frequency::Daily {
+        return records.to_vec();
+    }
+
+    let period_key = |date: NaiveDate| match freq {
+        Frequency::Weekly => (date.iso_week().year(), date.iso_week().week()),
+        _ => (date.year(), date.month()),
+    };
+
+    let mut sorted: Vec<&StockRecord> = records.iter().collect();
+    sorted.sort_by(|a, b| a.ticker.cmp(&b.ticker).then(a.timestamp().cmp(&b.timestamp())));
+
+    let mut resampled: Vec<StockRecord> = Vec::new();
+    for record in sorted {
+        match resampled.last_mut() {
+            Some(bar) if bar.ticker == record.ticker && period_key(bar.date) == period_key(record.date) => {
+                bar.date = record.date;
+                bar.datetime = record.datetime;
+                bar.high = bar.high.max(record.high);
+                bar.low = bar.low.min(record.low);
+                bar.close = record.close;
+                bar.adj_close = record.adj_close;
+                bar.volume += record.volume;
+            }
+            _ => resampled.push(record.clone()),
+        }
+    }
+
+    resampled.sort_by_key(|r| r.timestamp());
+    resampled
+}
+
+//missing weekdays tolerated between consecutive records before it counts as a gap,
+//enough to ride over exchange holidays and long weekends
+pub const MAX_MISSING_TRADING_DAYS: i64 = 3;
+
+//(previous date, next date) for every pair of consecutive records with more than
+//max_missing_days weekdays between them. Weekends are never counted as missing.
+pub fn check_data_continuity(records: &[StockRecord], max_missing_days: i64) -> Vec<(NaiveDate, NaiveDate)> {
+    records
+        .windows(2)
+        .map(|w| (w[0].date, w[1].date))
+        .filter(|&(prev, next)| {
+            let missing_weekdays = prev
+                .iter_days()
+                .skip(1)
+                .take_while(|d| *d < next)
+                .filter(|d| d.weekday().number_from_monday() <= 5)
+                .count() as i64;
+            missing_weekdays > max_missing_days
+        })
+        .collect()
+}
+
+pub fn get_ticker_info(all_data: &[StockRecord], ticker: &str, frequency: Frequency) -> (String, Vec<f64>) {
+    let ticker_records: Vec<StockRecord> = all_data.iter().filter(|r| r.ticker == ticker).cloned().collect();
+    //continuity is checked on the raw daily records, resampled bars always look gappy
+    let gaps = check_data_continuity(&ticker_records, MAX_MISSING_TRADING_DAYS);
+    let gap_summary = match gaps.iter().map(|(prev, next)| (*next - *prev).num_days()).max() {
+        Some(largest) => format!("{} gaps detected, largest {} days", gaps.len(), largest),
+        None => "No gaps detected".to_string(),
+    };
+    let ticker_data = resample_records(&ticker_records, frequency);
     
     if ticker_data.is_empty() {
         return ("No data for this ticker.".to_string(), Vec::new());
     }
 
-    let start_date = ticker_data.first().unwrap().date;
-    let end_date = ticker_data.last().unwrap().date;
+    //intraday bars show their times, and at Daily the returns are bar to bar
+    let intraday = ticker_data.iter().any(|r| r.datetime.is_some());
+    let stamp = |r: &StockRecord| if intraday { r.timestamp().to_string() } else { r.date.to_string() };
+    let start_date = stamp(ticker_data.first().unwrap());
+    let end_date = stamp(ticker_data.last().unwrap());
+    let frequency_label = match bar_interval(&ticker_data) {
+        Some(seconds) if frequency == Frequency::Daily => {
+            let interval = if seconds % 60 == 0 { format!("{} min", seconds / 60) } else { format!("{} s", seconds) };
+            format!("Intraday ({} bars, estimates are per bar)", interval)
+        }
+        _ => format!("{:?}", frequency),
+    };
     let count = ticker_data.len();
     let last_price = ticker_data.last().unwrap().close;
 
+    //preference order for returns: adjusted close, then raw close. Adjusted prices strip the
+    //spurious ex-dividend/split jumps, but are only used when every record has one since
+    //mixing adjusted and raw closes would fake a jump where they meet
+    let use_adjusted = ticker_data.iter().all(|r| r.adj_close.is_some());
+    let price = |r: &StockRecord| if use_adjusted { r.adj_close.unwrap_or(r.close) } else { r.close };
+
     let mut log_returns = Vec::new();
     for window in ticker_data.windows(2) {
-        let s1 = window[0].close;
-        let s2 = window[1].close;
+        let s1 = price(&window[0]);
+        let s2 = price(&window[1]);
         if s1 > 0.0 && s2 > 0.0 {
             log_returns.push((s2 / s1).ln());
         }
     }
 
     let info = format!(
-        "Ticker: {}\nDate Range: {} to {}\nRecord Count: {}\nLast Close Price: {:.2}\nLog Returns Computed: {}",
-        ticker, start_date, end_date, count, last_price, log_returns.len()
+        "Ticker: {}\nDate Range: {} to {}\nRecord Count: {}\nData Gaps: {}\nLast Close Price: {:.2}\nLog Returns Computed: {} ({})\nFrequency: {}",
+        ticker, start_date, end_date, count, gap_summary, last_price, log_returns.len(),
+        if use_adjusted { "adjusted close" } else { "raw close" },
+        frequency_label
     );
 
     (info, log_returns)
-}
\ No newline at end of file
+}
+
+//history split for a hindcast at the selected frequency: log returns of the bars dated before
+//start (the estimation window) and the realized closes from the last bar before start to the
+//end, so realized[0] is the price a simulation starting at start begins from. Closes follow the
+//same adjusted-close preference as get_ticker_info
+pub fn split_at_date(all_data: &[StockRecord], ticker: &str, start: NaiveDate, frequency: Frequency) -> Result<(Vec<f64>, Vec<f64>)> {
+    let ticker_records: Vec<StockRecord> = all_data.iter().filter(|r| r.ticker == ticker).cloned().collect();
+    let ticker_data = resample_records(&ticker_records, frequency);
+    let use_adjusted = ticker_data.iter().all(|r| r.adj_close.is_some());
+    let closes: Vec<f64> = ticker_data.iter()
+        .map(|r| if use_adjusted { r.adj_close.unwrap_or(r.close) } else { r.close })
+        .collect();
+
+    let split = ticker_data.partition_point(|r| r.date < start);
+    if split == 0 {
+        return Err(anyhow!("No {} history before {}", ticker, start));
+    }
+    if split == ticker_data.len() {
+        return Err(anyhow!("No {} history on or after {} to compare against", ticker, start));
+    }
+    let log_returns = closes[..split].windows(2)
+        .filter(|w| w[0] > 0.0 && w[1] > 0.0)
+        .map(|w| (w[1] / w[0]).ln())
+        .collect();
+    Ok((log_returns, closes[split - 1..].to_vec()))
+}
+
+//median spacing in seconds between consecutive bars of the same day, None without intraday bars.
+//The median rides over the overnight gap and the odd missing bar
+fn bar_interval(records: &[StockRecord]) -> Option<i64> {
+    let mut spacings: Vec<i64> = records
+        .windows(2)
+        .filter_map(|w| Some((w[0].datetime?, w[1].datetime?)))
+        .filter(|(prev, next)| prev.date() == next.date())
+        .map(|(prev, next)| (next - prev).num_seconds())
+        .filter(|&seconds| seconds > 0)
+        .collect();
+    if spacings.is_empty() {
+        return None;
+    }
+    spacings.sort_unstable();
+    Some(spacings[spacings.len() / 2])
+}
+
+//local vol grid: the header row is a label cell then the times, each following row is a spot
+//level then σ at each time, e.g.
+//  spot,0.0,0.5,1.0
+//  80,0.32,0.30,0.29
+//  100,0.25,0.24,0.24
+pub fn load_vol_surface(path: &Path) -> Result<VolSurface> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_path(path)
+        .map_err(|e| anyhow!("Cannot read vol surface {:?}: {}", path, e))?;
+    let parse = |cell: &str| cell.parse::<f64>().map_err(|_| anyhow!("Vol surface cell '{}' is not a number", cell));
+
+    let times = reader.headers()?.iter().skip(1).map(parse).collect::<Result<Vec<f64>>>()?;
+    let mut spots = Vec::new();
+    let mut sigmas = Vec::new();
+    for row in reader.records() {
+        let row = row?;
+        let mut cells = row.iter();
+        spots.push(parse(cells.next().unwrap_or(""))?);
+        sigmas.push(cells.map(parse).collect::<Result<Vec<f64>>>()?);
+    }
+    VolSurface::new(spots, times, sigmas)
+}
+
+//screening results ranked by VaR, riskiest ticker first. VaR, CVaR and drawdowns are loss
+//fractions, so tickers at different price levels compare directly
+pub fn write_ticker_ranking_csv(path: &Path, results: &[(String, SimStats)]) -> Result<()> {
+    let mut ranked: Vec<&(String, SimStats)> = results.iter().collect();
+    ranked.sort_by(|(_, a), (_, b)| b.var95.total_cmp(&a.var95));
+
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["rank", "ticker", "var", "cvar", "loss_probability", "mean_max_drawdown", "sharpe_ratio", "mean", "std_dev", "confidence_level"])?;
+    for (rank, (ticker, stats)) in ranked.into_iter().enumerate() {
+        writer.write_record([
+            (rank + 1).to_string(),
+            ticker.clone(),
+            stats.var95.to_string(),
+            stats.cvar95.to_string(),
+            stats.loss_probability.to_string(),
+            stats.mean_max_drawdown.to_string(),
+            stats.sharpe_ratio.to_string(),
+            stats.mean.to_string(),
+            stats.std_dev.to_string(),
+            stats.confidence_level.to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+//full results as one JSON document: stats, every terminal price and optionally every path
+pub fn write_results_json(path: &Path, stats: &SimStats, terminal_prices: &[f64], paths: Option<&[Vec<f64>]>) -> Result<()> {
+    let mut doc = json!({
+        "stats": stats.to_json()?,
+        "terminal_prices": terminal_prices,
+    });
+    if let Some(paths) = paths {
+        doc["paths"] = json!(paths);
+    }
+    fs::write(path, serde_json::to_string(&doc)?)?;
+    Ok(())
+}
+
+//terminal prices as a (path, terminal_price) table in a single row group, readable by
+//pandas.read_parquet / polars.read_parquet. Written uncompressed
+pub fn write_terminal_prices_parquet(path: &Path, terminal_prices: &[f64]) -> Result<()> {
+    let schema = parse_message_type("
+        message terminal_prices {
+            REQUIRED INT64 path;
+            REQUIRED DOUBLE terminal_price;
+        }
+    ")?;
+    let mut writer = SerializedFileWriter::new(File::create(path)?, Arc::new(schema), Default::default())?;
+    let mut row_group = writer.next_row_group()?;
+
+    let indices: Vec<i64> = (0..terminal_prices.len() as i64).collect();
+    let mut column = row_group.next_column()?.ok_or_else(|| anyhow!("Parquet schema is missing the path column"))?;
+    column.typed::<Int64Type>().write_batch(&indices, None, None)?;
+    column.close()?;
+    let mut column = row_group.next_column()?.ok_or_else(|| anyhow!("Parquet schema is missing the terminal_price column"))?;
+    column.typed::<DoubleType>().write_batch(terminal_prices, None, None)?;
+    column.close()?;
+
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+//per-step quantile bands as step,q05,q25,q50,q75,q95, the numbers behind the fan chart
+pub fn write_quantile_bands_csv(path: &Path, paths: &[Vec<f64>]) -> Result<()> {
+    let bands = compute_quantile_bands(paths, &BAND_QUANTILES);
+    let mut writer = csv::Writer::from_path(path)?;
+    let header = std::iter::once("step".to_string()).chain(BAND_QUANTILES.iter().map(|q| format!("q{:02}", (q * 100.0).round() as u32)));
+    writer.write_record(header)?;
+    let steps = bands.first().map_or(0, |b| b.len());
+    for t in 0..steps {
+        let row = std::iter::once(t.to_string()).chain(bands.iter().map(|band| band[t].to_string()));
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+//one diagnosed path as step,price,variance,drift,shock,jump, one row per step
+pub fn write_diagnostics_csv(path: &Path, steps: &[StepDiagnostics]) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for step in steps {
+        writer.serialize(step)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+//raw paths as a wide CSV. by_step: one row per time step with a column per path,
+//otherwise one row per path with a column per step. Headers carry the indices either way
+pub fn write_paths_csv(path: &Path, paths: &[Vec<f64>], by_step: bool) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    let steps = paths.iter().map(|p| p.len()).max().unwrap_or(0);
+
+    if by_step {
+        let header = std::iter::once("step".to_string()).chain((0..paths.len()).map(|i| format!("path_{}", i)));
+        writer.write_record(header)?;
+        for t in 0..steps {
+            //shorter paths leave their cell empty rather than shifting columns
+            let row = std::iter::once(t.to_string())
+                .chain(paths.iter().map(|p| p.get(t).map(|v| v.to_string()).unwrap_or_default()));
+            writer.write_record(row)?;
+        }
+    } else {
+        let header = std::iter::once("path".to_string()).chain((0..steps).map(|t| format!("step_{}", t)));
+        writer.write_record(header)?;
+        for (i, p) in paths.iter().enumerate() {
+            let row = std::iter::once(i.to_string())
+                .chain((0..steps).map(|t| p.get(t).map(|v| v.to_string()).unwrap_or_default()));
+            writer.write_record(row)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::value::{Error as ValueError, StrDeserializer};
+
+    #[test]
+    fn parse_date_accepts_every_supported_format() {
+        let march_5 = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        for (text, format) in [("20240305", "%Y%m%d"), ("2024-03-05", "%Y-%m-%d"), ("03/05/2024", "%m/%d/%Y"), ("05.03.2024", "%d.%m.%Y")] {
+            assert_eq!(parse_date(text).unwrap(), march_5, "{} as {}", text, format);
+        }
+        //day-first slashes are only reached when the first field cannot be a month
+        assert_eq!(parse_date("25/03/2024").unwrap(), NaiveDate::from_ymd_opt(2024, 3, 25).unwrap());
+    }
+
+    #[test]
+    fn timestamps_carry_an_optional_time() {
+        let nine_thirty = NaiveTime::from_hms_opt(9, 30, 0);
+        assert_eq!(parse_timestamp("20240305 93000").unwrap().1, nine_thirty);
+        assert_eq!(parse_timestamp("2024-03-05T09:30:00").unwrap().1, nine_thirty);
+        assert_eq!(parse_timestamp("2024-03-05").unwrap().1, None);
+    }
+
+    #[test]
+    fn unparseable_date_lists_the_formats_tried() {
+        let error = deserialize_timestamp(StrDeserializer::<ValueError>::new("2024/13/45")).unwrap_err().to_string();
+        assert!(error.contains("unrecognised date '2024/13/45'"), "{}", error);
+        for format in DATE_FORMATS {
+            assert!(error.contains(format), "{} missing from {}", format, error);
+        }
+    }
+
+    #[test]
+    fn trading_days_convert_to_bars_of_each_frequency() {
+        assert_eq!(Frequency::Daily.bars_for_trading_days(20.0), 20);
+        assert_eq!(Frequency::Weekly.bars_for_trading_days(20.0), 4);
+        assert_eq!(Frequency::Monthly.bars_for_trading_days(63.0), 3);
+        //a horizon shorter than one bar still spans a whole bar
+        assert_eq!(Frequency::Monthly.bars_for_trading_days(5.0), 1);
+    }
+}
@@ -0,0 +1,8 @@
+//the simulation engine without the GUI: models and statistics, charts, data and setup files.
+//The Slint app and the headless CLI in main.rs are both built on it
+pub mod backtest;
+pub mod batch;
+pub mod config;
+pub mod core_sim;
+pub mod data_io;
+pub mod plotting;
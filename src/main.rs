@@ -1,38 +1,119 @@
 use anyhow::Result;
 use image::{ImageEncoder, codecs::png::PngEncoder};
+use plotters::style::RGBColor;
 use rfd::FileDialog;
-use slint::{Image, ModelRc, PlatformError, SharedString, VecModel};
-use std::{cell::RefCell, fs::{self, File}, rc::Rc, thread, time::Instant};
+use slint::{Image, ModelRc, PlatformError, SharedString, StandardListViewItem, Timer, TimerMode, VecModel};
+use std::{cell::RefCell, collections::{HashMap, VecDeque}, fs::{self, File}, path::Path, rc::Rc, thread, time::Instant};
+use std::sync::{Arc, mpsc, atomic::{AtomicBool, AtomicU32, Ordering}};
 
-use crate::core_sim::{SimStats as rustSimStats, estimate_paramaters, run_simulation};
-use crate::data_io::{get_ticker_info, load_all_records}; 
+use monte_carlo::core_sim::{self, CoreSimParams, SimStats as rustSimStats, estimate_garch_params, estimate_jump_params, estimate_ou_params, estimate_paramaters, rolling_volatility, RunControl, winsorize_price_changes, winsorize_returns, run_hindcast, run_model_ensemble_overlay, run_simulation_with_control, step_trading_days, StepDiagnostics, is_stressed, stress_factor};
+use monte_carlo::batch::{BatchProgress, run_all_tickers, ticker_histories};
+use monte_carlo::data_io::{Frequency, get_ticker_info, load_all_records_multi, resample_records, split_at_date}; 
+use monte_carlo::plotting::{ChartConfig, plot_price_paths_overlay, plot_qq, plot_rolling_volatility};
 use crate::slint_generatedAppWindow::SimStats as slintSimStats;
 
 
 slint::include_modules!();
-mod data_io;
-mod core_sim;
-mod plotting;
-mod config;
+mod cli;
+
+//largest number of path values (paths x steps) kept after a run for export, ~80 MB of f64
+const MAX_RETAINED_PATH_VALUES: usize = 10_000_000;
+
+//completed runs kept for the comparison table, oldest dropped first
+const MAX_RUN_HISTORY: usize = 5;
+
+//one finished run in the comparison table
+#[derive(Debug, Clone)]
+struct RunRecord {
+    model: String,
+    params: String,
+    stats: rustSimStats,
+}
+
+//what selecting a ticker at one frequency computes, plus its GBM estimate once asked for
+#[derive(Debug, Clone)]
+struct TickerCache {
+    info: String,
+    last_price: f64,
+    prices: Vec<f64>,
+    log_returns: Vec<f64>,
+    estimate: Option<(f64, f64)>,
+}
 
 #[derive(Default, Debug, Clone)]
 struct AppState {
-    all_data: Vec<crate::data_io::StockRecord>,
+    all_data: Vec<monte_carlo::data_io::StockRecord>,
     tickers: Vec<String>,
+    //keyed by (ticker, frequency), cleared whenever a new CSV loads
+    ticker_cache: HashMap<(String, Frequency), TickerCache>,
     selected_ticker: String,
     selected_ticker_last_price: f64,
+    selected_ticker_prices: Vec<f64>,
     selected_ticker_log_returns: Vec<f64>,
-    last_paths_chart_png_raw: (Vec<u8>, u32, u32),
+    last_paths_chart_png_raw: Option<(Vec<u8>, u32, u32)>,
     last_hist_chart_png_raw: (Vec<u8>, u32, u32),
+    last_convergence_chart_png_raw: Option<(Vec<u8>, u32, u32)>,
+    //raw results of the last run, for the structured exports
+    last_stats: Option<rustSimStats>,
+    last_terminal_prices: Vec<f64>,
+    last_paths: Vec<Vec<f64>>,
+    last_diagnostics: Vec<StepDiagnostics>,
+    //model of the last run, labels its ensemble in the scenario overlay
+    last_model: String,
+    //setup of the last run, the base the model ensemble reruns under other models
+    last_params: Option<CoreSimParams>,
+    //a run set aside as the baseline scenario, drawn against the latest run
+    pinned_scenario: Option<(String, Vec<Vec<f64>>)>,
+    //horizon in trading days (steps x dt) of the last run, the VaR backtest counts its window in bars
+    last_horizon_days: usize,
+    run_history: VecDeque<RunRecord>,
+    cancel_flag: Arc<AtomicBool>,
+    //dialog locations and window size, saved on exit
+    settings: monte_carlo::config::AppSettings,
+    //a cancelled ticker screen, resumed by the next screen until a new CSV loads
+    screen_progress: Option<BatchProgress>,
+    screen_cancel: Arc<AtomicBool>,
 }
 
 fn main() -> Result<(), PlatformError> {
+    //any command-line arguments mean a headless batch run, no window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(e) = cli::run(&args) {
+            eprintln!("Headless run failed: {} - main.rs:36", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let main_window = AppWindow::new()?;
-    let app_state = Rc::new(RefCell::new(AppState::default()));
+    let settings = monte_carlo::config::load_settings();
+    if let Some((width, height)) = settings.window_size {
+        main_window.window().set_size(slint::LogicalSize::new(width, height));
+    }
+    let app_state = Rc::new(RefCell::new(AppState { settings, ..AppState::default() }));
+
+    setup_callbacks(&main_window, app_state.clone());
+
+    main_window.run()?;
 
-    setup_callbacks(&main_window, app_state);
+    //the window still reports its last size after the event loop ends
+    let window = main_window.window();
+    let size = window.size().to_logical(window.scale_factor());
+    let mut settings = app_state.borrow().settings.clone();
+    settings.window_size = Some((size.width, size.height));
+    if let Err(e) = monte_carlo::config::save_settings(&settings) {
+        eprintln!("Error saving settings: {} - main.rs:104", e);
+    }
+    Ok(())
+}
 
-    main_window.run()
+//file dialog starting in dir when one is remembered
+fn dialog_in(dir: Option<&Path>) -> FileDialog {
+    match dir {
+        Some(dir) => FileDialog::new().set_directory(dir),
+        None => FileDialog::new(),
+    }
 }
 
 fn setup_callbacks(main_window: &AppWindow, app_state: Rc<RefCell<AppState>>) {
@@ -43,22 +124,32 @@ fn setup_callbacks(main_window: &AppWindow, app_state: Rc<RefCell<AppState>>) {
         let mw_weak = main_window_weak.clone();
         let app_state = app_state.clone();
         move || {
-            if let Some(path) = FileDialog::new().add_filter("CSV file", &["csv"]).pick_file() {
-                match load_all_records(path) {
+            //several files (e.g. one per ticker) merge into one ticker list
+            let data_dir = app_state.borrow().settings.data_dir.clone();
+            if let Some(paths) = dialog_in(data_dir.as_deref()).add_filter("CSV file", &["csv"]).pick_files() {
+                match load_all_records_multi(&paths) {
                     Ok((all_records, tickers)) => {
                         let mut state = app_state.borrow_mut();
+                        state.settings.data_dir = paths[0].parent().map(Path::to_path_buf);
+                        state.screen_progress = None;
                         state.all_data = all_records;
                         state.tickers = tickers.clone();
+                        state.ticker_cache.clear();
 
                         let ticker_shared: Vec<SharedString> = tickers.into_iter().map(SharedString::from).collect();
                         let model: ModelRc<SharedString> = ModelRc::from(Rc::new(VecModel::from(ticker_shared)));
 
                         if let Some(mw) = mw_weak.upgrade() {
+                            mw.set_screen_resumable(false);
                             mw.set_ticker_list(model);
+                            clear_error(&mw);
                         }
                     }
                     Err(e) => {
                         eprintln!("Failed to load CSV {:?} - main.rs:61",e);
+                        if let Some(mw) = mw_weak.upgrade() {
+                            show_error(&mw, format!("Failed to load CSV: {}", e));
+                        }
                     }
                 }
             }
@@ -76,20 +167,51 @@ fn setup_callbacks(main_window: &AppWindow, app_state: Rc<RefCell<AppState>>) {
 
                 let selected_ticker = mw.get_selected_ticker();
                 state.selected_ticker = selected_ticker.to_string().clone();
-                let (info, log_returns) = get_ticker_info(&state.all_data, &selected_ticker);
-                
-                if let Some(last_record) = state.all_data.iter().filter(|r| r.ticker == state.selected_ticker).last() {
-                    state.selected_ticker_last_price = last_record.close;
-                }
+                let frequency = Frequency::from_name(&mw.get_data_frequency());
+                let key = (state.selected_ticker.clone(), frequency);
 
-                state.selected_ticker_log_returns = log_returns;
+                if !state.ticker_cache.contains_key(&key) {
+                    let (info, log_returns) = get_ticker_info(&state.all_data, &selected_ticker, frequency);
+                    let last_price = state.all_data.iter().rfind(|r| r.ticker == state.selected_ticker).map(|r| r.close);
+                    let ticker_records: Vec<_> = state.all_data.iter().filter(|r| r.ticker == state.selected_ticker).cloned().collect();
+                    let prices = resample_records(&ticker_records, frequency).iter().map(|r| r.close).collect();
+                    let entry = TickerCache {
+                        info,
+                        //unknown tickers keep the previous price, as before the cache
+                        last_price: last_price.unwrap_or(state.selected_ticker_last_price),
+                        prices,
+                        log_returns,
+                        estimate: None,
+                    };
+                    state.ticker_cache.insert(key.clone(), entry);
+                }
+                let cached = state.ticker_cache[&key].clone();
+                state.selected_ticker_last_price = cached.last_price;
+                state.selected_ticker_prices = cached.prices;
+                state.selected_ticker_log_returns = cached.log_returns;
+                let info = cached.info;
                 
                 let lines: Vec<&str> = info.lines().collect();
                 let date_range: SharedString = SharedString::from(*lines.get(1).unwrap_or(&""));
                 let record_count: SharedString = SharedString::from(*lines.get(2).unwrap_or(&""));
+                let data_gaps: SharedString = SharedString::from(*lines.get(3).unwrap_or(&""));
 
                 mw.set_date_range(date_range);
                 mw.set_record_count(record_count);
+                mw.set_data_gaps(data_gaps);
+
+                //recent vs full-history sigma, per step for the suggestion and annualized for the chart
+                let window = mw.get_vol_window().max(2) as usize;
+                let rolling = rolling_volatility(&state.selected_ticker_log_returns, window);
+                let full_sample = estimate_paramaters(&state.selected_ticker_log_returns).map(|(_, sigma)| sigma).unwrap_or(0.0);
+                mw.set_recent_sigma(rolling.last().copied().unwrap_or(0.0) as f32);
+                mw.set_full_sample_sigma(full_sample as f32);
+                let annualize = frequency.periods_per_year().sqrt();
+                let annualized: Vec<f64> = rolling.iter().map(|s| s * annualize).collect();
+                match plot_rolling_volatility(&annualized, full_sample * annualize, window, gui_chart_config(&mw)) {
+                    Ok((buf, w, h)) => mw.set_vol_chart(Image::from_rgb8(slint::SharedPixelBuffer::clone_from_slice(&buf, w, h))),
+                    Err(e) => show_error(&mw, format!("Rolling volatility chart error: {}", e)),
+                }
             }
         }
     });
@@ -100,136 +222,733 @@ fn setup_callbacks(main_window: &AppWindow, app_state: Rc<RefCell<AppState>>) {
         let app_state = app_state.clone();
         move || {
             if let Some(mw) = mw_weak.upgrade() {
-                let state = app_state.borrow();
+                let mut state = app_state.borrow_mut();
                 if state.selected_ticker_log_returns.is_empty() {
+                    show_error(&mw, "No returns to estimate from, load a CSV and select a ticker first".to_string());
+                    return;
+                }
+
+                //optionally clip outliers before fitting. Jump calibration always sees the raw
+                //returns, clipping would erase the jumps it is looking for
+                let winsorize = mw.get_winsorize_returns();
+                let tail_pct = mw.get_winsorize_pct() as f64;
+                let (log_returns, prices) = if winsorize {
+                    (winsorize_returns(&state.selected_ticker_log_returns, tail_pct), winsorize_price_changes(&state.selected_ticker_prices, tail_pct))
+                } else {
+                    (state.selected_ticker_log_returns.clone(), state.selected_ticker_prices.clone())
+                };
+
+                //Hawkes shares the Merton fit, the estimated lambda becomes its baseline intensity
+                if mw.get_model_type() == "JumpDiffusion" || mw.get_model_type() == "HawkesJump" {
+                    match estimate_jump_params(&state.selected_ticker_log_returns) {
+                        Ok((mu, sigma, lambda, mu_j, sigma_j)) => {
+                            mw.set_mu(mu as f32);
+                            mw.set_sigma(sigma as f32);
+                            mw.set_lambda(lambda as f32);
+                            mw.set_mu_j(mu_j as f32);
+                            mw.set_sigma_j(sigma_j as f32);
+
+                            mw.set_initial_price(state.selected_ticker_last_price as f32);
+                            clear_error(&mw);
+                        }
+                        Err(e) => {
+                            eprintln!("Error estimating jump params: {} - main.rs:120", e);
+                            show_error(&mw, format!("Error estimating jump params: {}", e));
+                        }
+                    }
+                    return;
+                }
+
+                if mw.get_model_type() == "MeanReversion" {
+                    match estimate_ou_params(&prices, 1.0) {
+                        Ok((theta, mu_long_term, sigma)) => {
+                            mw.set_theta(theta as f32);
+                            mw.set_mu_long_term(mu_long_term as f32);
+                            mw.set_sigma(sigma as f32);
+
+                            mw.set_initial_price(state.selected_ticker_last_price as f32);
+                            clear_error(&mw);
+                        }
+                        Err(e) => {
+                            eprintln!("Error estimating OU params: {} - main.rs:138", e);
+                            show_error(&mw, format!("Error estimating OU params: {}", e));
+                        }
+                    }
+                    return;
+                }
+
+                if mw.get_model_type() == "GARCH" {
+                    match estimate_garch_params(&log_returns) {
+                        Ok((omega, alpha, beta)) => {
+                            mw.set_omega(omega as f32);
+                            mw.set_alpha(alpha as f32);
+                            mw.set_beta(beta as f32);
+
+                            mw.set_initial_price(state.selected_ticker_last_price as f32);
+                            clear_error(&mw);
+                        }
+                        Err(e) => {
+                            eprintln!("Error estimating GARCH params: {} - main.rs:154", e);
+                            show_error(&mw, format!("Error estimating GARCH params: {}", e));
+                        }
+                    }
                     return;
                 }
 
-                match estimate_paramaters(&state.selected_ticker_log_returns) {
+                //repeat presses for the same ticker and frequency reuse the cached estimate,
+                //which only ever holds the raw-returns fit
+                let key = (state.selected_ticker.clone(), Frequency::from_name(&mw.get_data_frequency()));
+                let cached = state.ticker_cache.get(&key).and_then(|c| c.estimate).filter(|_| !winsorize);
+                let estimate = match cached {
+                    Some(estimate) => Ok(estimate),
+                    None => estimate_paramaters(&log_returns),
+                };
+                if !winsorize && let (Ok(estimate), Some(entry)) = (&estimate, state.ticker_cache.get_mut(&key)) {
+                    entry.estimate = Some(*estimate);
+                }
+                match estimate {
                     Ok((mu, sigma)) => {
                         mw.set_mu(mu as f32);
                         mw.set_sigma(sigma as f32);
 
                         mw.set_initial_price(state.selected_ticker_last_price as f32);
+                        clear_error(&mw);
                     }
                     Err(e) => {
                         eprintln!("Error estimating: {} - main.rs:116", e);
+                        show_error(&mw, format!("Error estimating: {}", e));
                     }
                 }
             }
         }
     });
 
-    //run sim and display png
+    //run sim on a worker thread and display png. params and returns move into the thread;
+    //results come back through a UI timer rather than invoke_from_event_loop because
+    //AppState lives in an Rc, which can't be sent to the event loop closure
+    let sim_timer = Rc::new(Timer::default());
     main_window.on_run_simulation_pressed({
         let mw_weak = main_window_weak.clone();
         let app_state = app_state.clone();
+        let sim_timer = sim_timer.clone();
         move |params| {
             if let Some(mw) = mw_weak.upgrade() {
+                //the button is disabled while running, this also covers any other caller
+                if mw.get_running() {
+                    return;
+                }
+                let params = CoreSimParams::from(params);
                 let start_time = Instant::now();
 
-                let hist_log_returns = app_state.borrow().selected_ticker_log_returns.clone();
+                let mut hist_log_returns = app_state.borrow().selected_ticker_log_returns.clone();
+
+                //a hindcast start date estimates from the bars before it and replays the rest
+                let hindcast_start = mw.get_hindcast_start().trim().to_string();
+                let realized = if hindcast_start.is_empty() {
+                    None
+                } else {
+                    let state = app_state.borrow();
+                    let split = chrono::NaiveDate::parse_from_str(&hindcast_start, "%Y-%m-%d")
+                        .map_err(|_| anyhow::anyhow!("Hindcast start must be a YYYY-MM-DD date"))
+                        .and_then(|start| split_at_date(&state.all_data, &state.selected_ticker, start, Frequency::from_name(&mw.get_data_frequency())));
+                    match split {
+                        Ok((before, realized)) => {
+                            hist_log_returns = before;
+                            Some(realized)
+                        }
+                        Err(e) => {
+                            show_error(&mw, format!("Hindcast error: {}", e));
+                            return;
+                        }
+                    }
+                };
 
                 if hist_log_returns.is_empty() && params.model_type == "Bootstrap" {
+                    show_error(&mw, "Bootstrap needs historical returns, select a ticker first".to_string());
                     return;
                 }
 
-                match run_simulation(params, hist_log_returns){
-                    Ok((stats, (paths_buf, paths_w, paths_h), (hist_buf, hist_w, hist_h))) => {
-                        let duration = start_time.elapsed().as_millis();
-                        mw.set_exec_time(format!("{} ms", duration).into());
-
-                        let ui_stats = slintSimStats{
-                            mean: stats.mean as f32,
-                            std_dev: stats.std_dev as f32,
-                            median: stats.median as f32,
-                            p5: stats.p5 as f32,
-                            p25: stats.p25 as f32,
-                            p75: stats.p75 as f32,
-                            p95: stats.p95 as f32,
-                            var95: stats.var95 as f32,
-                        };
-                        mw.set_stats(ui_stats);
+                //progress is an f32 stored as bits so the worker never blocks on the UI
+                let progress_bits = Arc::new(AtomicU32::new(0));
+                let control = RunControl {
+                    progress: Some(Arc::new({
+                        let progress_bits = progress_bits.clone();
+                        move |fraction: f32| progress_bits.store(fraction.to_bits(), Ordering::Relaxed)
+                    })),
+                    cancel: Arc::new(AtomicBool::new(false)),
+                };
+                app_state.borrow_mut().cancel_flag = control.cancel.clone();
+                mw.set_progress(0.0);
+                mw.set_running(true);
 
-                        let paths_pixel_buffer = slint::SharedPixelBuffer::clone_from_slice(&paths_buf, paths_w, paths_h);
-                        mw.set_price_chart(Image::from_rgb8(paths_pixel_buffer));
+                let run_model = params.model_type.to_string();
+                let run_params = params.clone();
+                let horizon_days = (params.horizon.max(1) as f64 * step_trading_days(&params)).round().max(1.0) as usize;
+                let (sender, receiver) = mpsc::channel();
+                thread::spawn(move || {
+                    let _ = sender.send(match realized {
+                        Some(realized) => run_hindcast(params, hist_log_returns, &realized, &control),
+                        None => run_simulation_with_control(params, hist_log_returns, &control),
+                    });
+                });
 
-                        let hist_pixel_buffer = slint::SharedPixelBuffer::clone_from_slice(&hist_buf, hist_w, hist_h);
-                        mw.set_hist_chart(Image::from_rgb8(hist_pixel_buffer));
+                let mw_weak = mw_weak.clone();
+                let app_state = app_state.clone();
+                let timer_weak = Rc::downgrade(&sim_timer);
+                sim_timer.start(TimerMode::Repeated, std::time::Duration::from_millis(50), move || {
+                    let Some(mw) = mw_weak.upgrade() else { return };
+                    mw.set_progress(f32::from_bits(progress_bits.load(Ordering::Relaxed)));
 
-                        let mut state = app_state.borrow_mut();
-                        state.last_paths_chart_png_raw = (paths_buf, paths_w, paths_h);
-                        state.last_hist_chart_png_raw = (hist_buf, hist_w, hist_h);
+                    let result = match receiver.try_recv() {
+                        Ok(result) => result,
+                        Err(mpsc::TryRecvError::Empty) => return,
+                        Err(mpsc::TryRecvError::Disconnected) => Err(anyhow::anyhow!("Simulation thread stopped unexpectedly")),
+                    };
+                    if let Some(timer) = timer_weak.upgrade() {
+                        timer.stop();
                     }
-                    Err(e) => {
-                        eprintln!("Simulation error: {} - main.rs:165", e);
+                    mw.set_running(false);
+
+                    match result {
+                        Ok(output) => {
+                            clear_error(&mw);
+                            let stats = &output.stats;
+                            let paths_chart = output.paths_chart;
+                            let (hist_buf, hist_w, hist_h) = output.hist_chart;
+                            let convergence = output.convergence_chart;
+                            let duration = start_time.elapsed().as_millis();
+                            mw.set_exec_time(format!("{} ms", duration).into());
+
+                            let ui_stats = slintSimStats{
+                                is_return: stats.output_mode == "Return",
+                                mean: stats.mean as f32,
+                                mean_std_error: stats.mean_std_error as f32,
+                                effective_samples: stats.effective_samples as i32,
+                                std_dev: stats.std_dev as f32,
+                                median: stats.median as f32,
+                                p5: stats.p5 as f32,
+                                p25: stats.p25 as f32,
+                                p75: stats.p75 as f32,
+                                p95: stats.p95 as f32,
+                                confidence_level: stats.confidence_level as f32,
+                                var95: stats.var95 as f32,
+                                cvar95: stats.cvar95 as f32,
+                                tail_effective_samples: stats.tail_effective_samples as f32,
+                                mean_max_drawdown: stats.mean_max_drawdown as f32,
+                                p95_max_drawdown: stats.p95_max_drawdown as f32,
+                                skewness: stats.skewness as f32,
+                                kurtosis: stats.kurtosis as f32,
+                                sharpe_ratio: stats.sharpe_ratio as f32,
+                                sortino_ratio: stats.sortino_ratio as f32,
+                                has_target: stats.prob_above_target.is_some(),
+                                prob_above_target: stats.prob_above_target.unwrap_or(0.0) as f32,
+                                loss_probability: stats.loss_probability as f32,
+                                variance_reduction_ratio: stats.variance_reduction_ratio as f32,
+                                has_cv_mean: stats.cv_mean.is_some(),
+                                cv_mean: stats.cv_mean.unwrap_or(0.0) as f32,
+                                cv_std_error: stats.cv_std_error.unwrap_or(0.0) as f32,
+                                diverged_paths: stats.diverged_paths as i32,
+                                seed: stats.seed as i32,
+                                has_option: stats.option_price.is_some(),
+                                option_price: stats.option_price.unwrap_or(0.0) as f32,
+                                option_std_error: stats.option_std_error.unwrap_or(0.0) as f32,
+                                has_barrier: stats.barrier_option_price.is_some(),
+                                barrier_option_price: stats.barrier_option_price.unwrap_or(0.0) as f32,
+                                has_greeks: stats.delta.is_some() || stats.vega.is_some(),
+                                delta: stats.delta.unwrap_or(0.0) as f32,
+                                vega: stats.vega.unwrap_or(0.0) as f32,
+                                has_analytic: stats.analytic_mean.is_some(),
+                                analytic_mean: stats.analytic_mean.unwrap_or(0.0) as f32,
+                                analytic_median: stats.analytic_median.unwrap_or(0.0) as f32,
+                                analytic_std_dev: stats.analytic_std_dev.unwrap_or(0.0) as f32,
+                                has_realized: stats.realized_percentile.is_some(),
+                                realized_percentile: stats.realized_percentile.unwrap_or(0.0) as f32,
+                                has_implied_mu: stats.implied_mu.is_some(),
+                                implied_mu: stats.implied_mu.unwrap_or(0.0) as f32,
+                                has_historical_var: stats.historical_var.is_some(),
+                                historical_var: stats.historical_var.unwrap_or(0.0) as f32,
+                                has_var95_ci: stats.var95_ci.is_some(),
+                                var95_ci_low: stats.var95_ci.map_or(0.0, |(low, _)| low) as f32,
+                                var95_ci_high: stats.var95_ci.map_or(0.0, |(_, high)| high) as f32,
+                            };
+                            mw.set_stats(ui_stats);
+
+                            //a run without stored paths has no chart, the last run's would be misleading
+                            mw.set_price_chart(match &paths_chart {
+                                Some((buf, w, h)) => Image::from_rgb8(slint::SharedPixelBuffer::clone_from_slice(buf, *w, *h)),
+                                None => Image::default(),
+                            });
+
+                            let hist_pixel_buffer = slint::SharedPixelBuffer::clone_from_slice(&hist_buf, hist_w, hist_h);
+                            mw.set_hist_chart(Image::from_rgb8(hist_pixel_buffer));
+
+                            //cleared when not requested so a stale trace isn't shown next to new results
+                            mw.set_convergence_chart(match &convergence {
+                                Some((buf, w, h)) => Image::from_rgb8(slint::SharedPixelBuffer::clone_from_slice(buf, *w, *h)),
+                                None => Image::default(),
+                            });
+
+                            let mut state = app_state.borrow_mut();
+                            state.last_paths_chart_png_raw = paths_chart;
+                            state.last_hist_chart_png_raw = (hist_buf, hist_w, hist_h);
+                            state.last_convergence_chart_png_raw = convergence;
+                            //history records the seed actually used, so auto-seeded runs can be repeated
+                            let params = describe_params(&CoreSimParams { seed: stats.seed as i32, ..run_params.clone() });
+                            state.run_history.push_back(RunRecord { model: run_model.clone(), params, stats: output.stats.clone() });
+                            if state.run_history.len() > MAX_RUN_HISTORY {
+                                state.run_history.pop_front();
+                            }
+                            mw.set_run_history(history_rows(&state.run_history));
+                            state.last_stats = Some(output.stats);
+                            state.last_horizon_days = horizon_days;
+                            state.last_terminal_prices = output.terminal_prices;
+                            state.last_diagnostics = output.diagnostics;
+                            state.last_model = run_model.clone();
+                            state.last_params = Some(run_params.clone());
+                            //past the cap the paths are dropped, exports then only offer stats and terminal prices
+                            let values: usize = output.paths.iter().map(|p| p.len()).sum();
+                            state.last_paths = if values <= MAX_RETAINED_PATH_VALUES {
+                                output.paths
+                            } else {
+                                eprintln!("⚠️ {} path values exceed the export cap of {}, paths not kept - main.rs:322", values, MAX_RETAINED_PATH_VALUES);
+                                Vec::new()
+                            };
+                        }
+                        Err(e) => {
+                            eprintln!("Simulation error: {} - main.rs:292", e);
+                            show_error(&mw, format!("Simulation error: {}", e));
+                        }
                     }
-                }
+                });
             }
         }
     });
 
-    //save summary.csv file
+    //stop the running simulation, the worker bails at its next chunk
+    main_window.on_cancel_simulation_pressed({
+        let app_state = app_state.clone();
+        move || {
+            app_state.borrow().cancel_flag.store(true, Ordering::Relaxed);
+        }
+    });
+
+    //save results: summary CSV, full JSON or terminal-price Parquet, chosen by extension
     main_window.on_export_summary_pressed({
         let mw_weak = main_window_weak.clone();
+        let app_state = app_state.clone();
         move || {
             if let Some(mw) = mw_weak.upgrade() {
-                let stats = mw.get_stats();
-                let exec_time = mw.get_exec_time();
-                let mw_weak_clone = mw.as_weak();
-
-                let horizons = mw.get_horizon();
-                let num_paths = mw.get_num_paths();
-                let model = mw.get_model_type().to_string();
-
-                let full_stats = rustSimStats {
-                    horizon: horizons as usize,
-                    paths: num_paths as usize,
-                    model: model,
-                    mean: stats.mean as f64,
-                    std_dev: stats.std_dev as f64,
-                    median: stats.median as f64,
-                    p5: stats.p5 as f64,
-                    p25: stats.p25 as f64,
-                    p75: stats.p75 as f64,
-                    p95: stats.p95 as f64,
-                    var95: stats.var95 as f64,
+                let state = app_state.borrow();
+                let Some(full_stats) = state.last_stats.clone() else {
+                    return;
                 };
+                let exec_time = mw.get_exec_time();
+                let terminal_prices = state.last_terminal_prices.clone();
+                let paths = if mw.get_export_paths_json() { Some(state.last_paths.clone()) } else { None };
 
                 //avoid freeze
                 thread::spawn(move || {
-                    let summary_csv = format!(
-                        "Metric,Value\nExecTime,{}\nModel,{}\nHorizon,{}\nPaths,{}\nMean,{:.4}\nStdDev,{:.4}\nMedian,{:.4}\nP5,{:.4}\nP25,{:.4}\nP75,{:.4}\nP95,{:.4}\nVaR95,{:.4}\n",
-                        exec_time, full_stats.model, full_stats.horizon, full_stats.paths, full_stats.mean, full_stats.std_dev, full_stats.median, full_stats.p5, full_stats.p25, full_stats.p75, full_stats.p95, full_stats.var95
-                    );
-                    
                     let file = FileDialog::new()
                         .add_filter("CSV", &["csv"])
+                        .add_filter("JSON", &["json"])
+                        .add_filter("Parquet", &["parquet"])
                         .set_file_name("simulation_summary.csv")
                         .save_file();
 
                     if let Some(path) = file {
-                        match fs::write(path, summary_csv) {
-                            Ok(_) => {}
-                            Err(e) => {eprintln!("Error save summary file: {} - main.rs:214", e)}
+                        let extension = path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+                        let result = match extension.as_deref() {
+                            Some("json") => monte_carlo::data_io::write_results_json(&path, &full_stats, &terminal_prices, paths.as_deref()),
+                            Some("parquet") => monte_carlo::data_io::write_terminal_prices_parquet(&path, &terminal_prices),
+                            _ => fs::write(&path, summary_csv(&full_stats, &exec_time)).map_err(Into::into),
+                        };
+                        if let Err(e) = result {
+                            eprintln!("Error save summary file: {} - main.rs:214", e)
                         }
                     }
+                });
+            }
+        }
+    });
 
-                    let _ = slint::invoke_from_event_loop(move || {
-                        if let Some(mw) = mw_weak_clone.upgrade() {
+    main_window.on_clear_history_pressed({
+        let mw_weak = main_window_weak.clone();
+        let app_state = app_state.clone();
+        move || {
+            if let Some(mw) = mw_weak.upgrade() {
+                let mut state = app_state.borrow_mut();
+                state.run_history.clear();
+                mw.set_run_history(history_rows(&state.run_history));
+            }
+        }
+    });
+
+    //keep the last run's paths as scenario A for the overlay
+    main_window.on_pin_scenario_pressed({
+        let mw_weak = main_window_weak.clone();
+        let app_state = app_state.clone();
+        move || {
+            if let Some(mw) = mw_weak.upgrade() {
+                let mut state = app_state.borrow_mut();
+                if state.last_paths.is_empty() {
+                    show_error(&mw, "No run with retained paths to pin, run a simulation first".to_string());
+                    return;
+                }
+                let label = format!("A: {}", state.last_model);
+                mw.set_pinned_scenario(label.clone().into());
+                state.pinned_scenario = Some((label, state.last_paths.clone()));
+                clear_error(&mw);
+            }
+        }
+    });
+
+    //pinned scenario and last run on shared axes
+    main_window.on_overlay_scenarios_pressed({
+        let mw_weak = main_window_weak.clone();
+        let app_state = app_state.clone();
+        move || {
+            if let Some(mw) = mw_weak.upgrade() {
+                let state = app_state.borrow();
+                let Some((pinned_label, pinned_paths)) = &state.pinned_scenario else {
+                    show_error(&mw, "Pin a scenario before overlaying".to_string());
+                    return;
+                };
+                if state.last_paths.is_empty() {
+                    show_error(&mw, "The last run kept no paths to overlay".to_string());
+                    return;
+                }
+                let last_label = format!("B: {}", state.last_model);
+                let sets = [
+                    (pinned_label.as_str(), pinned_paths.as_slice(), RGBColor(255, 184, 108)),
+                    (last_label.as_str(), state.last_paths.as_slice(), RGBColor(139, 233, 253)),
+                ];
+                match plot_price_paths_overlay(&sets, mw.get_max_displayed_paths().max(0) as usize, gui_chart_config(&mw)) {
+                    Ok((buf, w, h)) => {
+                        mw.set_overlay_chart(Image::from_rgb8(slint::SharedPixelBuffer::clone_from_slice(&buf, w, h)));
+                        clear_error(&mw);
+                    }
+                    Err(e) => show_error(&mw, format!("Overlay error: {}", e)),
+                }
+            }
+        }
+    });
+
+    //last run's setup under every listed model at once, on a worker thread like a normal run
+    let ensemble_timer = Rc::new(Timer::default());
+    main_window.on_run_ensemble_pressed({
+        let mw_weak = main_window_weak.clone();
+        let app_state = app_state.clone();
+        let ensemble_timer = ensemble_timer.clone();
+        move || {
+            if let Some(mw) = mw_weak.upgrade() {
+                if mw.get_ensemble_running() {
+                    return;
+                }
+                let state = app_state.borrow();
+                let Some(base) = state.last_params.clone() else {
+                    show_error(&mw, "Run a simulation first, the ensemble reuses its setup".to_string());
+                    return;
+                };
+                //the history below is the selected ticker's at the current data frequency
+                let base = CoreSimParams { history_frequency: Frequency::from_name(&mw.get_data_frequency()), ..base };
+                let models: Vec<String> = mw.get_ensemble_models()
+                    .split(',')
+                    .map(|m| m.trim().to_string())
+                    .filter(|m| !m.is_empty())
+                    .collect();
+                let hist_log_returns = state.selected_ticker_log_returns.clone();
+                if hist_log_returns.is_empty() && models.iter().any(|m| m == "Bootstrap") {
+                    show_error(&mw, "Bootstrap needs historical returns, select a ticker first".to_string());
+                    return;
+                }
+                drop(state);
+
+                let max_displayed_paths = mw.get_max_displayed_paths().max(0) as usize;
+                let chart = gui_chart_config(&mw);
+                let (sender, receiver) = mpsc::channel();
+                thread::spawn(move || {
+                    let models: Vec<&str> = models.iter().map(String::as_str).collect();
+                    let _ = sender.send(run_model_ensemble_overlay(base, &models, &hist_log_returns, max_displayed_paths, chart));
+                });
+                mw.set_ensemble_running(true);
+
+                let mw_weak = mw_weak.clone();
+                let timer_weak = Rc::downgrade(&ensemble_timer);
+                ensemble_timer.start(TimerMode::Repeated, std::time::Duration::from_millis(50), move || {
+                    let Some(mw) = mw_weak.upgrade() else { return };
+                    let result = match receiver.try_recv() {
+                        Ok(result) => result,
+                        Err(mpsc::TryRecvError::Empty) => return,
+                        Err(mpsc::TryRecvError::Disconnected) => Err(anyhow::anyhow!("Ensemble thread stopped unexpectedly")),
+                    };
+                    if let Some(timer) = timer_weak.upgrade() {
+                        timer.stop();
+                    }
+                    mw.set_ensemble_running(false);
+
+                    match result {
+                        Ok((results, (buf, w, h))) => {
+                            mw.set_ensemble_rows(ensemble_rows(&results));
+                            mw.set_ensemble_chart(Image::from_rgb8(slint::SharedPixelBuffer::clone_from_slice(&buf, w, h)));
+                            clear_error(&mw);
                         }
-                    });
+                        Err(e) => show_error(&mw, format!("Ensemble error: {}", e)),
+                    }
                 });
             }
         }
     });
 
+    //every loaded ticker under the last run's setup, ranked by VaR into a CSV. A cancel keeps the
+    //finished tickers and the next press carries on from there
+    let screen_timer = Rc::new(Timer::default());
+    main_window.on_screen_tickers_pressed({
+        let mw_weak = main_window_weak.clone();
+        let app_state = app_state.clone();
+        let screen_timer = screen_timer.clone();
+        move || {
+            if let Some(mw) = mw_weak.upgrade() {
+                if mw.get_screening() {
+                    return;
+                }
+                let mut state = app_state.borrow_mut();
+                let Some(base) = state.last_params.clone() else {
+                    show_error(&mw, "Run a simulation first, screening reuses its setup".to_string());
+                    return;
+                };
+                let base = CoreSimParams { history_frequency: Frequency::from_name(&mw.get_data_frequency()), ..base };
+                let histories = ticker_histories(&state.all_data, &state.tickers, base.history_frequency);
+                let mut progress = state.screen_progress.take().unwrap_or_default();
+                drop(state);
+
+                let progress_bits = Arc::new(AtomicU32::new(0));
+                let control = RunControl {
+                    progress: Some(Arc::new({
+                        let progress_bits = progress_bits.clone();
+                        move |fraction: f32| progress_bits.store(fraction.to_bits(), Ordering::Relaxed)
+                    })),
+                    cancel: Arc::new(AtomicBool::new(false)),
+                };
+                app_state.borrow_mut().screen_cancel = control.cancel.clone();
+                let (sender, receiver) = mpsc::channel();
+                thread::spawn(move || {
+                    let result = run_all_tickers(&histories, &base, &mut progress, &control);
+                    let _ = sender.send((result, progress));
+                });
+                mw.set_screening(true);
+
+                let mw_weak = mw_weak.clone();
+                let app_state = app_state.clone();
+                let timer_weak = Rc::downgrade(&screen_timer);
+                screen_timer.start(TimerMode::Repeated, std::time::Duration::from_millis(50), move || {
+                    let Some(mw) = mw_weak.upgrade() else { return };
+                    let (result, progress) = match receiver.try_recv() {
+                        Ok(done) => done,
+                        Err(mpsc::TryRecvError::Empty) => {
+                            mw.set_screen_progress(f32::from_bits(progress_bits.load(Ordering::Relaxed)));
+                            return;
+                        }
+                        Err(mpsc::TryRecvError::Disconnected) => (Err(anyhow::anyhow!("Screening thread stopped unexpectedly")), BatchProgress::default()),
+                    };
+                    if let Some(timer) = timer_weak.upgrade() {
+                        timer.stop();
+                    }
+                    mw.set_screening(false);
+
+                    match result {
+                        Ok(()) => {
+                            mw.set_screen_resumable(false);
+                            mw.set_screen_progress(1.0);
+                            for (ticker, reason) in &progress.skipped {
+                                eprintln!("Skipped {}: {} - main.rs:708", ticker, reason);
+                            }
+                            clear_error(&mw);
+                            let file = FileDialog::new()
+                                .add_filter("CSV", &["csv"])
+                                .set_file_name("ticker_screen.csv")
+                                .save_file();
+                            if let Some(path) = file && let Err(e) = monte_carlo::data_io::write_ticker_ranking_csv(&path, &progress.results) {
+                                show_error(&mw, format!("Error saving ticker screen: {}", e));
+                            }
+                        }
+                        Err(e) => {
+                            let resumable = !progress.results.is_empty() || !progress.skipped.is_empty();
+                            mw.set_screen_resumable(resumable);
+                            app_state.borrow_mut().screen_progress = resumable.then_some(progress);
+                            show_error(&mw, format!("Screening stopped: {}", e));
+                        }
+                    }
+                });
+            }
+        }
+    });
+
+    main_window.on_cancel_screen_pressed({
+        let app_state = app_state.clone();
+        move || {
+            app_state.borrow().screen_cancel.store(true, Ordering::Relaxed);
+        }
+    });
+
+    //normality check of the selected ticker's log returns
+    main_window.on_qq_plot_pressed({
+        let mw_weak = main_window_weak.clone();
+        let app_state = app_state.clone();
+        move || {
+            if let Some(mw) = mw_weak.upgrade() {
+                let state = app_state.borrow();
+                if state.selected_ticker_log_returns.is_empty() {
+                    show_error(&mw, "No returns to plot, load a CSV and select a ticker first".to_string());
+                    return;
+                }
+                match plot_qq(&state.selected_ticker_log_returns, gui_chart_config(&mw)) {
+                    Ok((buf, w, h)) => {
+                        mw.set_qq_chart(Image::from_rgb8(slint::SharedPixelBuffer::clone_from_slice(&buf, w, h)));
+                        clear_error(&mw);
+                    }
+                    Err(e) => show_error(&mw, format!("Q-Q plot error: {}", e)),
+                }
+            }
+        }
+    });
+
+    //all remembered runs side by side in one CSV, oldest first
+    main_window.on_export_history_pressed({
+        let app_state = app_state.clone();
+        move || {
+            let history: Vec<RunRecord> = app_state.borrow().run_history.iter().cloned().collect();
+            if history.is_empty() {
+                return;
+            }
+
+            //avoid freeze
+            thread::spawn(move || {
+                let file = FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .set_file_name("simulation_comparison.csv")
+                    .save_file();
+
+                if let Some(path) = file && let Err(e) = fs::write(&path, history_csv(&history)) {
+                    eprintln!("Error saving comparison file: {} - main.rs:430", e);
+                }
+            });
+        }
+    });
+
+    //Kupiec test of the last run's VaR against the selected ticker's history
+    main_window.on_run_backtest_pressed({
+        let mw_weak = main_window_weak.clone();
+        let app_state = app_state.clone();
+        move || {
+            if let Some(mw) = mw_weak.upgrade() {
+                let state = app_state.borrow();
+                let Some(stats) = &state.last_stats else {
+                    return;
+                };
+                //the windows are in bars of the selected ticker's returns, at the current data frequency
+                let frequency = Frequency::from_name(&mw.get_data_frequency());
+                let horizon_bars = frequency.bars_for_trading_days(state.last_horizon_days as f64);
+                let returns = monte_carlo::backtest::horizon_returns(&state.selected_ticker_log_returns, horizon_bars);
+                if returns.is_empty() {
+                    show_error(&mw, format!("Not enough {:?} history for a {} bar VaR backtest", frequency, horizon_bars));
+                    return;
+                }
+                let result = monte_carlo::backtest::backtest_var(&returns, stats.var95, stats.confidence_level);
+                mw.set_backtest(BacktestStats {
+                    has_result: true,
+                    observations: result.observations as i32,
+                    failures: result.failures as i32,
+                    failure_rate: result.failure_rate as f32,
+                    expected_rate: result.expected_rate as f32,
+                    lr_statistic: result.lr_statistic as f32,
+                    p_value: result.p_value as f32,
+                    passed: result.passed,
+                    zone: result.zone.as_str().into(),
+                });
+                clear_error(&mw);
+            }
+        }
+    });
+
+    //save the last run's paths as a wide CSV
+    main_window.on_export_paths_pressed({
+        let mw_weak = main_window_weak.clone();
+        let app_state = app_state.clone();
+        move || {
+            if let Some(mw) = mw_weak.upgrade() {
+                let paths = app_state.borrow().last_paths.clone();
+                if paths.is_empty() {
+                    return;
+                }
+                let by_step = mw.get_paths_csv_by_step();
+
+                //avoid freeze
+                thread::spawn(move || {
+                    let file = FileDialog::new()
+                        .add_filter("CSV", &["csv"])
+                        .set_file_name("simulation_paths.csv")
+                        .save_file();
+
+                    if let Some(path) = file && let Err(e) = monte_carlo::data_io::write_paths_csv(&path, &paths, by_step) {
+                        eprintln!("Error saving paths file: {} - main.rs:402", e);
+                    }
+                });
+            }
+        }
+    });
+
+    //save the last run's per-step quantile bands as a CSV
+    main_window.on_export_bands_pressed({
+        let app_state = app_state.clone();
+        move || {
+            let paths = app_state.borrow().last_paths.clone();
+            if paths.is_empty() {
+                return;
+            }
+
+            //avoid freeze
+            thread::spawn(move || {
+                let file = FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .set_file_name("quantile_bands.csv")
+                    .save_file();
+
+                if let Some(path) = file && let Err(e) = monte_carlo::data_io::write_quantile_bands_csv(&path, &paths) {
+                    eprintln!("Error saving quantile bands file: {} - main.rs:640", e);
+                }
+            });
+        }
+    });
+
+    //save the last run's diagnosed path, step by step, as a CSV
+    main_window.on_export_diagnostics_pressed({
+        let app_state = app_state.clone();
+        move || {
+            let steps = app_state.borrow().last_diagnostics.clone();
+            if steps.is_empty() {
+                return;
+            }
+
+            //avoid freeze
+            thread::spawn(move || {
+                let file = FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .set_file_name("step_diagnostics.csv")
+                    .save_file();
+
+                if let Some(path) = file && let Err(e) = monte_carlo::data_io::write_diagnostics_csv(&path, &steps) {
+                    eprintln!("Error saving diagnostics file: {} - main.rs:796", e);
+                }
+            });
+        }
+    });
+
     //save png files
     main_window.on_export_charts_pressed({
         let mw_weak = main_window_weak.clone();
         let app_state = app_state.clone();
         move || {
-            if let Some(mw) = mw_weak.upgrade() {
+            if let Some(_mw) = mw_weak.upgrade() {
                 let state = app_state.borrow();
                 if state.last_hist_chart_png_raw.0.is_empty() || state.last_hist_chart_png_raw.0.is_empty() {
                     return;
@@ -244,15 +963,26 @@ fn setup_callbacks(main_window: &AppWindow, app_state: Rc<RefCell<AppState>>) {
                     let mut hist_path = path.clone();
                     hist_path.set_file_name(format!("{}_hist.png",hist_path.file_stem().unwrap().to_str().unwrap()));
 
-                    let (buf, w, h) = &state.last_paths_chart_png_raw;
-                    let p_res = encode_and_save_png(&paths_path, buf, *w, *h);
+                    let p_res = match &state.last_paths_chart_png_raw {
+                        Some((buf, w, h)) => encode_and_save_png(&paths_path, buf, *w, *h),
+                        None => Ok(()),
+                    };
 
                     let (buf, w, h) = &state.last_hist_chart_png_raw;
                     let h_res = encode_and_save_png(&hist_path, buf, *w, *h);
 
-                    match (p_res, h_res) {
-                        (Ok(_), Ok(_)) => {}
-                        (Err(e), _) | (_, Err(e)) => {eprintln!("Error saving charts: {} - main.rs:255", e);}
+                    let c_res = match &state.last_convergence_chart_png_raw {
+                        Some((buf, w, h)) => {
+                            let mut convergence_path = path.clone();
+                            convergence_path.set_file_name(format!("{}_convergence.png", convergence_path.file_stem().unwrap().to_str().unwrap()));
+                            encode_and_save_png(&convergence_path, buf, *w, *h)
+                        }
+                        None => Ok(()),
+                    };
+
+                    match (p_res, h_res, c_res) {
+                        (Ok(_), Ok(_), Ok(_)) => {}
+                        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {eprintln!("Error saving charts: {} - main.rs:255", e);}
                     }
                     
                 }
@@ -260,30 +990,67 @@ fn setup_callbacks(main_window: &AppWindow, app_state: Rc<RefCell<AppState>>) {
         }
     });
 
-    //save setup to JSON file
+    //local vol surface CSV for the LocalVol model, only the path is kept, it is read at run time
+    main_window.on_browse_local_vol_pressed({
+        let mw_weak = main_window_weak.clone();
+        let app_state = app_state.clone();
+        move || {
+            let data_dir = app_state.borrow().settings.data_dir.clone();
+            if let Some(mw) = mw_weak.upgrade()
+                && let Some(path) = dialog_in(data_dir.as_deref()).add_filter("CSV file", &["csv"]).pick_file()
+            {
+                mw.set_local_vol_file(path.to_string_lossy().to_string().into());
+                app_state.borrow_mut().settings.data_dir = path.parent().map(Path::to_path_buf);
+            }
+        }
+    });
+
+    //save setup to JSON/YAML file
     main_window.on_save_setup_pressed({
         let mw_weak = main_window_weak.clone();
+        let app_state = app_state.clone();
         move || {
             if let Some(mw) = mw_weak.upgrade() {
             // Gather all current parameters from GUI
-                let config = crate::config::SimConfig {
+                let config = monte_carlo::config::SimConfig {
+                    version: monte_carlo::config::CONFIG_VERSION,
                     initial_price: mw.get_initial_price() as f64,
                     horizon: mw.get_horizon() as usize,
                     num_paths: mw.get_num_paths() as usize,
                     seed: mw.get_seed() as u64,
+                    auto_seed: mw.get_auto_seed(),
                     use_antithetic: mw.get_use_antithetic(),
+                    use_quasi_random: mw.get_use_quasi_random(),
+                    variance_reduction: format!("{:?}", mw.get_variance_reduction()),
+                    shock_distribution: format!("{:?}", mw.get_shock_distribution()),
+                    student_t_df: mw.get_student_t_df() as f64,
                     dt: 1.0,
+                    step_frequency: format!("{:?}", mw.get_step_frequency()),
+                    confidence_level: mw.get_confidence_level() as f64,
+                    risk_free_rate: mw.get_risk_free_rate() as f64,
+                    target_price: (mw.get_target_price() > 0.0).then(|| mw.get_target_price() as f64),
+                    target_mean_price: (mw.get_target_mean_price() > 0.0).then(|| mw.get_target_mean_price() as f64),
+                    stress_factor_sigma: (mw.get_stress_factor_sigma() > 0.0 && mw.get_stress_factor_sigma() != 1.0).then(|| mw.get_stress_factor_sigma() as f64),
+                    stress_shift_mu: (mw.get_stress_shift_mu() != 0.0).then(|| mw.get_stress_shift_mu() as f64),
+                    store_paths: mw.get_store_paths(),
+                    approximate_quantiles: mw.get_approximate_quantiles(),
+                    output_mode: format!("{:?}", mw.get_output_mode()),
+                    fast_mode: mw.get_fast_mode(),
+                    deterministic: mw.get_deterministic(),
+                    diagnostic_path: mw.get_record_diagnostics().then(|| mw.get_diagnostic_path().max(0) as usize),
                     model_type: mw.get_model_type().to_string(),
                     gbm_params: if mw.get_model_type() == "GBM" || mw.get_model_type() == "JumpDiffusion" {
-                    Some(crate::config::GBMParams {
+                    Some(monte_carlo::config::GBMParams {
                         mu: mw.get_mu() as f64,
                         sigma: mw.get_sigma() as f64,
+                        mu_schedule: gui_schedule(&mw, &mw.get_mu_schedule()),
+                        sigma_schedule: gui_schedule(&mw, &mw.get_sigma_schedule()),
                     })
                 } else {
                     None
                 },
                 mean_reversion_params: if mw.get_model_type() == "MeanReversion" {
-                    Some(crate::config::MeanReversionParams {
+                    Some(monte_carlo::config::MeanReversionParams {
                         theta: mw.get_theta() as f64,
                         mu_long_term: mw.get_mu_long_term() as f64,
                         sigma: mw.get_sigma() as f64,
@@ -292,7 +1059,7 @@ fn setup_callbacks(main_window: &AppWindow, app_state: Rc<RefCell<AppState>>) {
                     None
                 },
                 jump_diffusion_params: if mw.get_model_type() == "JumpDiffusion" {
-                    Some(crate::config::JumpDiffusionParams {
+                    Some(monte_carlo::config::JumpDiffusionParams {
                         mu: mw.get_mu() as f64,          
                         sigma: mw.get_sigma() as f64,
                         lambda: mw.get_lambda() as f64,
@@ -302,8 +1069,21 @@ fn setup_callbacks(main_window: &AppWindow, app_state: Rc<RefCell<AppState>>) {
                 } else {
                     None
                 },
+                hawkes_jump_params: if mw.get_model_type() == "HawkesJump" {
+                    Some(monte_carlo::config::HawkesJumpParams {
+                        mu: mw.get_mu() as f64,
+                        sigma: mw.get_sigma() as f64,
+                        lambda: mw.get_lambda() as f64,
+                        excitation: mw.get_hawkes_excitation() as f64,
+                        decay: mw.get_hawkes_decay() as f64,
+                        mu_j: mw.get_mu_j() as f64,
+                        sigma_j: mw.get_sigma_j() as f64,
+                    })
+                } else {
+                    None
+                },
                 garch_params: if mw.get_model_type() == "GARCH" {
-                    Some(crate::config::GARCHParams {
+                    Some(monte_carlo::config::GARCHParams {
                         omega: mw.get_omega() as f64,
                         alpha: mw.get_alpha() as f64,
                         beta: mw.get_beta() as f64,
@@ -311,46 +1091,183 @@ fn setup_callbacks(main_window: &AppWindow, app_state: Rc<RefCell<AppState>>) {
                 } else {
                     None
                 },
+                heston_params: if mw.get_model_type() == "Heston" {
+                    Some(monte_carlo::config::HestonParams {
+                        mu: mw.get_mu() as f64,
+                        v0: mw.get_v0() as f64,
+                        kappa: mw.get_kappa() as f64,
+                        theta: mw.get_theta_v() as f64,
+                        xi: mw.get_xi() as f64,
+                        rho: mw.get_rho() as f64,
+                    })
+                } else {
+                    None
+                },
+                cir_params: if mw.get_model_type() == "CIR" {
+                    Some(monte_carlo::config::CIRParams {
+                        kappa: mw.get_cir_kappa() as f64,
+                        theta: mw.get_cir_theta() as f64,
+                        sigma: mw.get_sigma() as f64,
+                    })
+                } else {
+                    None
+                },
+                vasicek_params: if mw.get_model_type() == "Vasicek" {
+                    Some(monte_carlo::config::VasicekParams {
+                        kappa: mw.get_vasicek_kappa() as f64,
+                        theta: mw.get_vasicek_theta() as f64,
+                        sigma: mw.get_sigma() as f64,
+                    })
+                } else {
+                    None
+                },
+                sabr_params: if mw.get_model_type() == "SABR" {
+                    Some(monte_carlo::config::SABRParams {
+                        alpha: mw.get_sabr_alpha() as f64,
+                        beta: mw.get_sabr_beta() as f64,
+                        rho: mw.get_sabr_rho() as f64,
+                        nu: mw.get_sabr_nu() as f64,
+                    })
+                } else {
+                    None
+                },
+                local_vol_params: if mw.get_model_type() == "LocalVol" {
+                    Some(monte_carlo::config::LocalVolParams {
+                        mu: mw.get_mu() as f64,
+                        surface_file: mw.get_local_vol_file().to_string(),
+                    })
+                } else {
+                    None
+                },
+                regime_switching_params: if mw.get_model_type() == "RegimeSwitching" {
+                    Some(monte_carlo::config::RegimeSwitchingParams {
+                        mu: mw.get_mu() as f64,
+                        sigma_low: mw.get_sigma_low() as f64,
+                        sigma_high: mw.get_sigma_high() as f64,
+                        p_low_to_high: mw.get_p_low_to_high() as f64,
+                        p_high_to_low: mw.get_p_high_to_low() as f64,
+                    })
+                } else {
+                    None
+                },
+                bootstrap_params: if mw.get_model_type() == "Bootstrap" {
+                    Some(monte_carlo::config::BootstrapParams {
+                        block_size: mw.get_block_size().max(1) as usize,
+                        demean: mw.get_bootstrap_demean(),
+                    })
+                } else {
+                    None
+                },
+                option_params: if mw.get_price_option() {
+                    Some(monte_carlo::config::EuropeanOptionParams {
+                        strike: mw.get_strike() as f64,
+                        is_call: mw.get_is_call(),
+                        discount_rate: mw.get_discount_rate() as f64,
+                        compute_greeks: mw.get_compute_greeks(),
+                        greek_bump: mw.get_greek_bump() as f64,
+                        barrier_params: if mw.get_price_barrier() {
+                            Some(monte_carlo::config::BarrierParams {
+                                barrier: mw.get_barrier() as f64,
+                                is_up_and_out: mw.get_is_up_and_out(),
+                            })
+                        } else {
+                            None
+                        },
+                    })
+                } else {
+                    None
+                },
             };
 
-            // Open file dialog to save
-            if let Some(path) = FileDialog::new()
+            // Open file dialog to save, next to the last setup file and under its name
+            let last_config = app_state.borrow().settings.config_path.clone();
+            let file_name = last_config.as_deref()
+                .and_then(Path::file_name)
+                .map_or("simulation_config.json".to_string(), |name| name.to_string_lossy().to_string());
+            if let Some(path) = dialog_in(last_config.as_deref().and_then(Path::parent))
                 .add_filter("JSON", &["json"])
-                .set_file_name("simulation_config.json")
+                .add_filter("YAML", &["yaml", "yml"])
+                .set_file_name(file_name)
                 .save_file()
             {
-                match crate::config::save_config(&config, &path) {
-                    Ok(_) => println!("✅ Configuration saved to {:?} - main.rs:323", path),
+                match monte_carlo::config::save_config(&config, &path) {
+                    Ok(_) => {
+                        println!("✅ Configuration saved to {:?} - main.rs:323", path);
+                        app_state.borrow_mut().settings.config_path = Some(path);
+                    }
                     Err(e) => eprintln!("❌ Error saving config: {} - main.rs:324", e),
                 }
             }
         }
     }
 });
- //load setup from JSON file
+ //load setup from JSON/YAML file
     main_window.on_load_setup_pressed({
         let mw_weak = main_window_weak.clone();
+        let app_state = app_state.clone();
             move || {
                 if let Some(mw) = mw_weak.upgrade() {
-                     // Open file dialog to load
-                    if let Some(path) = FileDialog::new()
-                        .add_filter("JSON", &["json"])
+                     // Open file dialog to load, where the last setup file was
+                    let last_config = app_state.borrow().settings.config_path.clone();
+                    if let Some(path) = dialog_in(last_config.as_deref().and_then(Path::parent))
+                        .add_filter("Config", &["json", "yaml", "yml"])
                         .pick_file()
                 {
-                    match crate::config::load_config(&path) {
+                    match monte_carlo::config::load_config(&path) {
                         Ok(config) => {
+                            app_state.borrow_mut().settings.config_path = Some(path.clone());
+                            if let Err(e) = monte_carlo::config::validate_config(&config) {
+                                eprintln!("⚠️ Loaded config is invalid: {} - main.rs:355", e);
+                            }
                         // Apply loaded config to GUI
                             mw.set_initial_price(config.initial_price as f32);
                             mw.set_horizon(config.horizon as i32);
                             mw.set_num_paths(config.num_paths as i32);
                             mw.set_seed(config.seed as i32);
+                            mw.set_auto_seed(config.auto_seed);
                             mw.set_use_antithetic(config.use_antithetic);
+                            mw.set_use_quasi_random(config.use_quasi_random);
+                            mw.set_store_paths(config.store_paths);
+                            mw.set_approximate_quantiles(config.approximate_quantiles);
+                            mw.set_output_mode(match config.output_mode.as_str() {
+                                "Return" => OutputMode::Return,
+                                _ => OutputMode::Price,
+                            });
+                            mw.set_fast_mode(config.fast_mode);
+                            mw.set_deterministic(config.deterministic);
+                            mw.set_record_diagnostics(config.diagnostic_path.is_some());
+                            mw.set_diagnostic_path(config.diagnostic_path.unwrap_or(0) as i32);
+                            mw.set_variance_reduction(match config.variance_reduction.as_str() {
+                                "ControlVariate" => VarianceReduction::ControlVariate,
+                                "Stratified" => VarianceReduction::Stratified,
+                                "ImportanceSampling" => VarianceReduction::ImportanceSampling,
+                                _ => VarianceReduction::None,
+                            });
+                            mw.set_shock_distribution(match config.shock_distribution.as_str() {
+                                "StudentT" => ShockDistribution::StudentT,
+                                _ => ShockDistribution::Normal,
+                            });
+                            mw.set_student_t_df(config.student_t_df as f32);
+                            mw.set_step_frequency(match config.step_frequency.as_str() {
+                                "Daily" => StepFrequency::Daily,
+                                "Weekly" => StepFrequency::Weekly,
+                                "Monthly" => StepFrequency::Monthly,
+                                _ => StepFrequency::PerStep,
+                            });
+                            mw.set_confidence_level(config.confidence_level as f32);
+                            mw.set_risk_free_rate(config.risk_free_rate as f32);
+                            mw.set_target_price(config.target_price.unwrap_or(0.0) as f32);
+                            mw.set_target_mean_price(config.target_mean_price.unwrap_or(0.0) as f32);
+                            mw.set_stress_factor_sigma(config.stress_factor_sigma.unwrap_or(1.0) as f32);
+                            mw.set_stress_shift_mu(config.stress_shift_mu.unwrap_or(0.0) as f32);
                             mw.set_model_type(config.model_type.clone().into());
 
                         // Load model-specific parameters
                         if let Some(gbm) = config.gbm_params {
                             mw.set_mu(gbm.mu as f32);
                             mw.set_sigma(gbm.sigma as f32);
+                            mw.set_mu_schedule(gbm.mu_schedule.as_deref().map(monte_carlo::config::format_schedule).unwrap_or_default().into());
+                            mw.set_sigma_schedule(gbm.sigma_schedule.as_deref().map(monte_carlo::config::format_schedule).unwrap_or_default().into());
                         }
 
                         if let Some(mr) = config.mean_reversion_params {
@@ -367,12 +1284,83 @@ fn setup_callbacks(main_window: &AppWindow, app_state: Rc<RefCell<AppState>>) {
                             mw.set_sigma_j(jd.sigma_j as f32);
                         }
 
+                        if let Some(hawkes) = config.hawkes_jump_params {
+                            mw.set_mu(hawkes.mu as f32);
+                            mw.set_sigma(hawkes.sigma as f32);
+                            mw.set_lambda(hawkes.lambda as f32);
+                            mw.set_hawkes_excitation(hawkes.excitation as f32);
+                            mw.set_hawkes_decay(hawkes.decay as f32);
+                            mw.set_mu_j(hawkes.mu_j as f32);
+                            mw.set_sigma_j(hawkes.sigma_j as f32);
+                        }
+
                         if let Some(garch) = config.garch_params {
                             mw.set_omega(garch.omega as f32);
                             mw.set_alpha(garch.alpha as f32);
                             mw.set_beta(garch.beta as f32);
                         }
 
+                        if let Some(cir) = config.cir_params {
+                            mw.set_cir_kappa(cir.kappa as f32);
+                            mw.set_cir_theta(cir.theta as f32);
+                            mw.set_sigma(cir.sigma as f32);
+                        }
+
+                        if let Some(vasicek) = config.vasicek_params {
+                            mw.set_vasicek_kappa(vasicek.kappa as f32);
+                            mw.set_vasicek_theta(vasicek.theta as f32);
+                            mw.set_sigma(vasicek.sigma as f32);
+                        }
+
+                        if let Some(sabr) = config.sabr_params {
+                            mw.set_sabr_alpha(sabr.alpha as f32);
+                            mw.set_sabr_beta(sabr.beta as f32);
+                            mw.set_sabr_rho(sabr.rho as f32);
+                            mw.set_sabr_nu(sabr.nu as f32);
+                        }
+
+                        if let Some(local_vol) = config.local_vol_params {
+                            mw.set_mu(local_vol.mu as f32);
+                            mw.set_local_vol_file(local_vol.surface_file.into());
+                        }
+
+                        if let Some(rs) = config.regime_switching_params {
+                            mw.set_mu(rs.mu as f32);
+                            mw.set_sigma_low(rs.sigma_low as f32);
+                            mw.set_sigma_high(rs.sigma_high as f32);
+                            mw.set_p_low_to_high(rs.p_low_to_high as f32);
+                            mw.set_p_high_to_low(rs.p_high_to_low as f32);
+                        }
+
+                        if let Some(bs) = config.bootstrap_params {
+                            mw.set_block_size(bs.block_size as i32);
+                            mw.set_bootstrap_demean(bs.demean);
+                        }
+
+                        mw.set_price_option(config.option_params.is_some());
+                        if let Some(option) = config.option_params {
+                            mw.set_strike(option.strike as f32);
+                            mw.set_is_call(option.is_call);
+                            mw.set_discount_rate(option.discount_rate as f32);
+                            mw.set_compute_greeks(option.compute_greeks);
+                            mw.set_greek_bump(option.greek_bump as f32);
+
+                            mw.set_price_barrier(option.barrier_params.is_some());
+                            if let Some(barrier) = option.barrier_params {
+                                mw.set_barrier(barrier.barrier as f32);
+                                mw.set_is_up_and_out(barrier.is_up_and_out);
+                            }
+                        }
+
+                        if let Some(heston) = config.heston_params {
+                            mw.set_mu(heston.mu as f32);
+                            mw.set_v0(heston.v0 as f32);
+                            mw.set_kappa(heston.kappa as f32);
+                            mw.set_theta_v(heston.theta as f32);
+                            mw.set_xi(heston.xi as f32);
+                            mw.set_rho(heston.rho as f32);
+                        }
+
                         println!("✅ Configuration loaded from {:?} - main.rs:376", path);
                     }
                     Err(e) => {
@@ -387,6 +1375,291 @@ fn setup_callbacks(main_window: &AppWindow, app_state: Rc<RefCell<AppState>>) {
 
 
 
+//summary.csv contents, shared by the GUI export and headless runs
+fn summary_csv(full_stats: &rustSimStats, exec_time: &str) -> String {
+    //e.g. 0.99 -> "99", 0.975 -> "97.5"
+    let level = (full_stats.confidence_level * 1000.0).round() / 10.0;
+    let ci_half_width = 1.96 * full_stats.mean_std_error;
+    let mut summary_csv = format!(
+        "Metric,Value\nExecTime,{}\nModel,{}\nOutputMode,{}\nHorizon,{}\nPaths,{}\nEffectiveSamples,{}\nMean,{:.4}\nMeanStdError,{:.4}\nMeanCI95Low,{:.4}\nMeanCI95High,{:.4}\nStdDev,{:.4}\nMedian,{:.4}\nP5,{:.4}\nP25,{:.4}\nP75,{:.4}\nP95,{:.4}\nVaR{},{:.4}\nCVaR{},{:.4}\nTailEffectiveSamples,{:.1}\nMeanMaxDrawdown,{:.4}\nP95MaxDrawdown,{:.4}\nSkewness,{:.4}\nExcessKurtosis,{:.4}\nSharpeRatio,{:.4}\nSortinoRatio,{:.4}\nLossProbability,{:.4}\nVarianceReductionRatio,{:.4}\nDivergedPaths,{}\nSeed,{}\n",
+        exec_time, full_stats.model, full_stats.output_mode, full_stats.horizon, full_stats.paths, full_stats.effective_samples, full_stats.mean, full_stats.mean_std_error, full_stats.mean - ci_half_width, full_stats.mean + ci_half_width, full_stats.std_dev, full_stats.median, full_stats.p5, full_stats.p25, full_stats.p75, full_stats.p95, level, full_stats.var95, level, full_stats.cvar95, full_stats.tail_effective_samples, full_stats.mean_max_drawdown, full_stats.p95_max_drawdown, full_stats.skewness, full_stats.kurtosis, full_stats.sharpe_ratio, full_stats.sortino_ratio, full_stats.loss_probability, full_stats.variance_reduction_ratio, full_stats.diverged_paths, full_stats.seed
+    );
+    if let (Some(mean), Some(std_error)) = (full_stats.cv_mean, full_stats.cv_std_error) {
+        summary_csv.push_str(&format!("CVMean,{:.4}\nCVMeanStdError,{:.4}\n", mean, std_error));
+    }
+    if let (Some(price), Some(std_error)) = (full_stats.option_price, full_stats.option_std_error) {
+        summary_csv.push_str(&format!("OptionPrice,{:.4}\nOptionStdError,{:.4}\n", price, std_error));
+    }
+    if let (Some(delta), Some(vega)) = (full_stats.delta, full_stats.vega) {
+        summary_csv.push_str(&format!("Delta,{:.4}\nVega,{:.4}\n", delta, vega));
+    }
+    if let Some(probability) = full_stats.prob_above_target {
+        summary_csv.push_str(&format!("ProbAboveTarget,{:.4}\n", probability));
+    }
+    if let Some(price) = full_stats.barrier_option_price {
+        summary_csv.push_str(&format!("BarrierOptionPrice,{:.4}\n", price));
+    }
+    if let (Some(mean), Some(median), Some(std_dev)) = (full_stats.analytic_mean, full_stats.analytic_median, full_stats.analytic_std_dev) {
+        summary_csv.push_str(&format!("AnalyticMean,{:.4}\nAnalyticMedian,{:.4}\nAnalyticStdDev,{:.4}\n", mean, median, std_dev));
+    }
+    if let Some(percentile) = full_stats.realized_percentile {
+        summary_csv.push_str(&format!("RealizedPercentile,{:.4}\n", percentile));
+    }
+    if let Some(mu) = full_stats.implied_mu {
+        summary_csv.push_str(&format!("ImpliedMu,{:.6}\n", mu));
+    }
+    if let Some(var) = full_stats.historical_var {
+        summary_csv.push_str(&format!("HistoricalVaR{},{:.4}\n", level, var));
+    }
+    if let Some((low, high)) = full_stats.var95_ci {
+        summary_csv.push_str(&format!("VaR{}CI95Low,{:.4}\nVaR{}CI95High,{:.4}\n", level, low, level, high));
+    }
+    summary_csv.push_str(&format!("StressFactorSigma,{:.4}\nStressShiftMu,{:.6}\n", full_stats.stress_factor_sigma, full_stats.stress_shift_mu));
+    summary_csv
+}
+
+//chart size from the settings panel for charts drawn on the UI thread, same fallback as simulate
+fn gui_chart_config(mw: &AppWindow) -> ChartConfig {
+    let default_chart = ChartConfig::default();
+    ChartConfig {
+        width: if mw.get_chart_width() > 0 { mw.get_chart_width() as u32 } else { default_chart.width },
+        height: if mw.get_chart_height() > 0 { mw.get_chart_height() as u32 } else { default_chart.height },
+    }
+}
+
+//put a failure in the window's error banner, the console log alone is invisible in the GUI
+fn show_error(mw: &AppWindow, message: String) {
+    mw.set_error_message(message.into());
+}
+
+//hide the error banner once an action succeeds
+fn clear_error(mw: &AppWindow) {
+    mw.set_error_message(SharedString::new());
+}
+
+//a schedule text field for the saved setup, only GBM reads schedules and blank means none
+fn gui_schedule(mw: &AppWindow, text: &str) -> Option<Vec<f64>> {
+    if mw.get_model_type() != "GBM" {
+        return None;
+    }
+    match monte_carlo::core_sim::parse_schedule(text) {
+        Ok(values) if !values.is_empty() => Some(values),
+        Ok(_) => None,
+        Err(e) => {
+            eprintln!("Schedule not saved: {} - main.rs:840", e);
+            None
+        }
+    }
+}
+
+//the GUI's slint types into the engine's, done once when a run starts
+impl From<SimParams> for CoreSimParams {
+    fn from(params: SimParams) -> Self {
+        CoreSimParams {
+            initial_price: params.initial_price,
+            horizon: params.horizon,
+            num_paths: params.num_paths,
+            mu: params.mu,
+            sigma: params.sigma,
+            seed: params.seed,
+            auto_seed: params.auto_seed,
+            use_antithetic: params.use_antithetic,
+            use_quasi_random: params.use_quasi_random,
+            variance_reduction: params.variance_reduction.into(),
+            shock_distribution: params.shock_distribution.into(),
+            student_t_df: params.student_t_df,
+            price_option: params.price_option,
+            strike: params.strike,
+            is_call: params.is_call,
+            discount_rate: params.discount_rate,
+            price_barrier: params.price_barrier,
+            barrier: params.barrier,
+            is_up_and_out: params.is_up_and_out,
+            compute_greeks: params.compute_greeks,
+            greek_bump: params.greek_bump,
+            show_bands: params.show_bands,
+            log_scale: params.log_scale,
+            max_displayed_paths: params.max_displayed_paths,
+            overlay_density: params.overlay_density,
+            show_kde: params.show_kde,
+            histogram_bins: params.histogram_bins,
+            histogram_fixed_range: params.histogram_fixed_range,
+            histogram_min: params.histogram_min,
+            histogram_max: params.histogram_max,
+            histogram_drop_outside: params.histogram_drop_outside,
+            store_paths: params.store_paths,
+            show_convergence: params.show_convergence,
+            approximate_quantiles: params.approximate_quantiles,
+            output_mode: params.output_mode.into(),
+            fast_mode: params.fast_mode,
+            deterministic: params.deterministic,
+            record_diagnostics: params.record_diagnostics,
+            diagnostic_path: params.diagnostic_path,
+            chart_width: params.chart_width,
+            chart_height: params.chart_height,
+            dt: params.dt,
+            step_frequency: params.step_frequency.into(),
+            history_frequency: Frequency::from_name(&params.data_frequency),
+            model_type: params.model_type.into(),
+            confidence_level: params.confidence_level,
+            risk_free_rate: params.risk_free_rate,
+            target_price: params.target_price,
+            target_mean_price: params.target_mean_price,
+            stress_factor_sigma: params.stress_factor_sigma,
+            stress_shift_mu: params.stress_shift_mu,
+            theta: params.theta,
+            mu_long_term: params.mu_long_term,
+            lambda: params.lambda,
+            mu_j: params.mu_j,
+            sigma_j: params.sigma_j,
+            hawkes_excitation: params.hawkes_excitation,
+            hawkes_decay: params.hawkes_decay,
+            omega: params.omega,
+            alpha: params.alpha,
+            beta: params.beta,
+            cir_kappa: params.cir_kappa,
+            cir_theta: params.cir_theta,
+            vasicek_kappa: params.vasicek_kappa,
+            vasicek_theta: params.vasicek_theta,
+            sabr_alpha: params.sabr_alpha,
+            sabr_beta: params.sabr_beta,
+            sabr_rho: params.sabr_rho,
+            sabr_nu: params.sabr_nu,
+            local_vol_file: params.local_vol_file.into(),
+            sigma_low: params.sigma_low,
+            sigma_high: params.sigma_high,
+            p_low_to_high: params.p_low_to_high,
+            p_high_to_low: params.p_high_to_low,
+            mu_schedule: params.mu_schedule.into(),
+            sigma_schedule: params.sigma_schedule.into(),
+            block_size: params.block_size,
+            bootstrap_demean: params.bootstrap_demean,
+            v0: params.v0,
+            kappa: params.kappa,
+            theta_v: params.theta_v,
+            xi: params.xi,
+            rho: params.rho,
+        }
+    }
+}
+
+impl From<VarianceReduction> for core_sim::VarianceReduction {
+    fn from(value: VarianceReduction) -> Self {
+        match value {
+            VarianceReduction::None => Self::None,
+            VarianceReduction::ControlVariate => Self::ControlVariate,
+            VarianceReduction::Stratified => Self::Stratified,
+            VarianceReduction::ImportanceSampling => Self::ImportanceSampling,
+        }
+    }
+}
+
+impl From<ShockDistribution> for core_sim::ShockDistribution {
+    fn from(value: ShockDistribution) -> Self {
+        match value {
+            ShockDistribution::Normal => Self::Normal,
+            ShockDistribution::StudentT => Self::StudentT,
+        }
+    }
+}
+
+impl From<OutputMode> for core_sim::OutputMode {
+    fn from(value: OutputMode) -> Self {
+        match value {
+            OutputMode::Price => Self::Price,
+            OutputMode::Return => Self::Return,
+        }
+    }
+}
+
+impl From<StepFrequency> for core_sim::StepFrequency {
+    fn from(value: StepFrequency) -> Self {
+        match value {
+            StepFrequency::PerStep => Self::PerStep,
+            StepFrequency::Daily => Self::Daily,
+            StepFrequency::Weekly => Self::Weekly,
+            StepFrequency::Monthly => Self::Monthly,
+        }
+    }
+}
+
+//the parameters that define a run: shared settings plus the ones the chosen model reads
+fn describe_params(params: &CoreSimParams) -> String {
+    let model = match params.model_type.as_str() {
+        "GBM" if !params.mu_schedule.trim().is_empty() || !params.sigma_schedule.trim().is_empty() => format!("mu={} sigma={} (term structure)", params.mu, params.sigma),
+        "GBM" => format!("mu={} sigma={}", params.mu, params.sigma),
+        "MeanReversion" => format!("theta={} mu_lt={} sigma={}", params.theta, params.mu_long_term, params.sigma),
+        "JumpDiffusion" => format!("mu={} sigma={} lambda={} mu_j={} sigma_j={}", params.mu, params.sigma, params.lambda, params.mu_j, params.sigma_j),
+        "HawkesJump" => format!("mu={} sigma={} lambda={} excitation={} decay={} mu_j={} sigma_j={}", params.mu, params.sigma, params.lambda, params.hawkes_excitation, params.hawkes_decay, params.mu_j, params.sigma_j),
+        "GARCH" => format!("omega={} alpha={} beta={}", params.omega, params.alpha, params.beta),
+        "Heston" => format!("mu={} v0={} kappa={} theta_v={} xi={} rho={}", params.mu, params.v0, params.kappa, params.theta_v, params.xi, params.rho),
+        "CIR" => format!("kappa={} theta={} sigma={}", params.cir_kappa, params.cir_theta, params.sigma),
+        "Vasicek" => format!("kappa={} theta={} sigma={}", params.vasicek_kappa, params.vasicek_theta, params.sigma),
+        "SABR" => format!("alpha={} beta={} rho={} nu={}", params.sabr_alpha, params.sabr_beta, params.sabr_rho, params.sabr_nu),
+        "LocalVol" => format!("mu={} surface={}", params.mu, params.local_vol_file),
+        "Bootstrap" => format!("block_size={} demean={}", params.block_size, params.bootstrap_demean),
+        "RegimeSwitching" => format!("mu={} sigma_low={} sigma_high={} p_lh={} p_hl={}", params.mu, params.sigma_low, params.sigma_high, params.p_low_to_high, params.p_high_to_low),
+        _ => String::new(),
+    };
+    let stress = if is_stressed(params) {
+        format!(" stress=x{}/{:+}", stress_factor(params), params.stress_shift_mu)
+    } else {
+        String::new()
+    };
+    format!("S0={} T={} N={} seed={} {}{}", params.initial_price, params.horizon, params.num_paths, params.seed, model, stress).trim_end().to_string()
+}
+
+//table rows for the run history tab, columns as declared in gui.slint
+fn history_rows(history: &VecDeque<RunRecord>) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = history.iter().map(|run| {
+        let cells: Vec<StandardListViewItem> = [
+            run.model.clone(),
+            run.params.clone(),
+            format!("{:.4}", run.stats.mean),
+            format!("{:.4}", run.stats.std_dev),
+            format!("{:.4}", run.stats.p5),
+            format!("{:.4}", run.stats.p95),
+            format!("{:.4}", run.stats.var95),
+            format!("{:.4}", run.stats.cvar95),
+            format!("{:.4}", run.stats.mean_max_drawdown),
+        ].into_iter().map(|cell| StandardListViewItem::from(SharedString::from(cell))).collect();
+        ModelRc::from(Rc::new(VecModel::from(cells)))
+    }).collect();
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+//table rows for the model ensemble tab, columns as declared in gui.slint
+fn ensemble_rows(results: &[(String, rustSimStats)]) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let rows: Vec<ModelRc<StandardListViewItem>> = results.iter().map(|(model, stats)| {
+        let cells: Vec<StandardListViewItem> = [
+            model.clone(),
+            format!("{:.4}", stats.mean),
+            format!("{:.4}", stats.std_dev),
+            format!("{:.4}", stats.p5),
+            format!("{:.4}", stats.p95),
+            format!("{:.4}", stats.var95),
+            format!("{:.4}", stats.cvar95),
+            format!("{:.4}", stats.mean_max_drawdown),
+            format!("{:.4}", stats.loss_probability),
+        ].into_iter().map(|cell| StandardListViewItem::from(SharedString::from(cell))).collect();
+        ModelRc::from(Rc::new(VecModel::from(cells)))
+    }).collect();
+    ModelRc::from(Rc::new(VecModel::from(rows)))
+}
+
+fn history_csv(history: &[RunRecord]) -> String {
+    let mut csv = String::from("Run,Model,OutputMode,Parameters,Paths,Horizon,Mean,MeanStdError,StdDev,Median,P5,P95,ConfidenceLevel,VaR,CVaR,MeanMaxDrawdown,SharpeRatio,LossProbability,OptionPrice\n");
+    for (i, run) in history.iter().enumerate() {
+        let s = &run.stats;
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{:.4},{:.4},{:.4},{:.4},{:.4},{:.4},{},{:.4},{:.4},{:.4},{:.4},{:.4},{}\n",
+            i + 1, run.model, s.output_mode, run.params, s.paths, s.horizon, s.mean, s.mean_std_error, s.std_dev, s.median, s.p5, s.p95, s.confidence_level, s.var95, s.cvar95, s.mean_max_drawdown, s.sharpe_ratio, s.loss_probability,
+            s.option_price.map(|p| format!("{:.4}", p)).unwrap_or_default()
+        ));
+    }
+    csv
+}
+
 //encode from rgb<u8> to png
 fn encode_and_save_png(path: &std::path::Path, buf: &[u8], width: u32, height: u32) -> Result<()> {
     let file = File::create(path)?;
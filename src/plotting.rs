@@ -1,22 +1,74 @@
 use anyhow::{Ok, Result};
+use plotters::coord::ranged1d::ValueFormatter;
+use plotters::coord::types::RangedCoordusize;
 use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
 use plotters_bitmap::bitmap_pixel::RGBPixel;
 use plotters_bitmap::BitMapBackend;
+use statrs::distribution::{ContinuousCDF, Normal};
 
 const CHART_WIDTH: u32 = 800;
 const CHART_HEIGHT: u32 = 600;
 
-pub fn plot_price_paths(paths: &[Vec<f64>],  model_type: &str, mu_long_term: Option<f64>) -> Result<(Vec<u8>, u32, u32)> {
-    let mut buf = vec![0; (CHART_WIDTH * CHART_HEIGHT * 3) as usize];
+/// Raw RGB8 buffer with its width and height
+pub type ChartBuffer = (Vec<u8>, u32, u32);
+
+/// Quantiles of the fan chart's bands: 5-95 outer, 25-75 inner and the median
+pub const BAND_QUANTILES: [f64; 5] = [0.05, 0.25, 0.5, 0.75, 0.95];
+
+/// Colours of the model ensemble overlay, cycled when there are more models
+pub const ENSEMBLE_COLORS: [RGBColor; 6] = [
+    RGBColor(139, 233, 253),
+    RGBColor(255, 184, 108),
+    RGBColor(80, 250, 123),
+    RGBColor(255, 121, 198),
+    RGBColor(189, 147, 249),
+    RGBColor(241, 250, 140),
+];
+
+/// Pixel dimensions of a rendered chart
+#[derive(Debug, Clone, Copy)]
+pub struct ChartConfig {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for ChartConfig {
+    fn default() -> Self {
+        Self { width: CHART_WIDTH, height: CHART_HEIGHT }
+    }
+}
+
+/// Bin count and optional fixed x range of the terminal price histogram
+#[derive(Debug, Clone, Copy)]
+pub struct HistogramBins {
+    pub count: usize,
+    //fixed (min, max) axis so runs can be compared bar for bar, None fits the data
+    pub range: Option<(f64, f64)>,
+    //with a fixed range, values outside it are counted in the edge bins, false drops them
+    pub clamp: bool,
+}
+
+impl Default for HistogramBins {
+    fn default() -> Self {
+        Self { count: 100, range: None, clamp: true }
+    }
+}
+
+//deterministic runs hold identical drift-only paths, drawn as one bold line. realized is the
+//price path that actually happened (hindcasts), drawn over the fan in a contrasting colour
+#[allow(clippy::too_many_arguments)]
+pub fn plot_price_paths(paths: &[Vec<f64>],  model_type: &str, mu_long_term: Option<f64>, show_bands: bool, log_scale: bool, max_displayed_paths: usize, deterministic: bool, realized: Option<&[f64]>, chart: ChartConfig) -> Result<ChartBuffer> {
+    let mut buf = vec![0; (chart.width * chart.height * 3) as usize];
     let backend = BitMapBackend::<RGBPixel>::with_buffer_and_format(
-        &mut buf, (CHART_WIDTH, CHART_HEIGHT))?;
+        &mut buf, (chart.width, chart.height))?;
     {
         let root = backend.into_drawing_area();
         root.fill(&RGBColor(30, 30, 46))?;
 
         if paths.is_empty() || paths[0].is_empty() {
             root.draw(&EmptyElement::at((0,0)))?;
-            return Ok((vec![0; (CHART_WIDTH * CHART_HEIGHT * 3) as usize], CHART_WIDTH, CHART_HEIGHT));
+            return Ok((vec![0; (chart.width * chart.height * 3) as usize], chart.width, chart.height));
         }
 
         let mut min_price = paths[0][0];
@@ -32,63 +84,308 @@ pub fn plot_price_paths(paths: &[Vec<f64>],  model_type: &str, mu_long_term: Opt
             }
         }
         
-        //add padding
+        for &price in realized.unwrap_or(&[]).iter().filter(|p| p.is_finite()) {
+            min_price = min_price.min(price);
+            max_price = max_price.max(price);
+        }
+
+        //keep the long-term mean reference line inside the chart
+        if model_type == "MeanReversion" && let Some(mean_price) = mu_long_term {
+            min_price = min_price.min(mean_price);
+            max_price = max_price.max(mean_price);
+        }
+        
+        //add padding, skipped near f64::MAX where it would overflow into an infinite axis
         min_price *= 0.95;
-        max_price *= 1.05;
+        if (max_price * 1.05).is_finite() {
+            max_price *= 1.05;
+        }
 
         let max_steps = paths[0].len() - 1;
 
-        let mut chart = ChartBuilder::on(&root)
+        let mut builder = ChartBuilder::on(&root);
+        builder
             .caption(
-                "Simulated Price Paths",
+                if deterministic { format!("Drift Path, No Shocks ({})", model_type) } else { format!("Simulated Price Paths ({})", model_type) },
                 ("Inter", 30, &RGBColor(208, 208, 208)),
             )
             .margin(10)
             .x_label_area_size(40)
+            .y_label_area_size(60);
+
+        if log_scale {
+            //log range needs a strictly positive lower bound, same floor as the MeanReversion clamp
+            let min_price = min_price.max(0.01);
+            let max_price = max_price.max(min_price * 10.0);
+            let mut chart = builder.build_cartesian_2d(0..max_steps, (min_price..max_price).log_scale())?;
+            draw_price_chart(&mut chart, paths, model_type, mu_long_term, show_bands, max_displayed_paths, deterministic, realized, max_steps)?;
+        } else {
+            let mut chart = builder.build_cartesian_2d(0..max_steps, min_price..max_price)?;
+            draw_price_chart(&mut chart, paths, model_type, mu_long_term, show_bands, max_displayed_paths, deterministic, realized, max_steps)?;
+        }
+    }
+
+    Ok((buf, chart.width, chart.height))
+}
+
+//mesh, bands, sample paths and mean line; generic over the y axis so linear and log charts share it
+#[allow(clippy::too_many_arguments)]
+fn draw_price_chart<'a, Y>(
+    chart: &mut ChartContext<'a, BitMapBackend<'a, RGBPixel>, Cartesian2d<RangedCoordusize, Y>>,
+    paths: &[Vec<f64>],
+    model_type: &str,
+    mu_long_term: Option<f64>,
+    show_bands: bool,
+    max_displayed_paths: usize,
+    deterministic: bool,
+    realized: Option<&[f64]>,
+    max_steps: usize,
+) -> Result<()>
+where
+    Y: Ranged<ValueType = f64> + ValueFormatter<f64>,
+{
+    chart
+        .configure_mesh()
+        .axis_style(RGBColor(208, 208, 208))
+        .label_style(("Inter", 15, &RGBColor(208, 208, 208)))
+        .draw()?;
+
+    //every path is the same drift trajectory, bands and faded samples would only hide it
+    if deterministic {
+        let path = &paths[0];
+        chart.draw_series(LineSeries::new(
+            path.iter().copied().enumerate(),
+            RGBColor(255, 121, 198).stroke_width(3),
+        ))?;
+        if let Some(&last) = path.last() {
+            chart.draw_series(std::iter::once(Text::new(
+                format!("{:.2}", last),
+                (max_steps, last),
+                TextStyle::from(("Inter", 14).into_font())
+                    .color(&RGBColor(208, 208, 208))
+                    .pos(Pos::new(HPos::Right, VPos::Bottom)),
+            )))?;
+        }
+    }
+
+    //percentile fan behind the sample lines: 5-95 outer band, 25-75 inner band, median line
+    if show_bands && !deterministic {
+        let bands = compute_quantile_bands(paths, &BAND_QUANTILES);
+        for (lower, upper, opacity) in [(0, 4, 0.15), (1, 3, 0.3)] {
+            let outline: Vec<(usize, f64)> = bands[upper].iter().copied().enumerate()
+                .chain(bands[lower].iter().copied().enumerate().rev())
+                .collect();
+            chart.draw_series(std::iter::once(Polygon::new(outline, CYAN.mix(opacity).filled())))?;
+        }
+        chart.draw_series(LineSeries::new(
+            bands[2].iter().copied().enumerate(),
+            CYAN.stroke_width(2),
+        ))?;
+    }
+
+    let displayed = if deterministic { Vec::new() } else { displayed_paths(paths, max_displayed_paths) };
+    //fade lines as the count grows so dense ensembles read as a cloud
+    let opacity = (1.0 / displayed.len().clamp(1, 200) as f64).max(0.02);
+
+    for path in displayed {
+        chart.draw_series(LineSeries::new(
+            path.iter().enumerate().map(|(i, &p)| (i, p)),
+            &YELLOW.mix(opacity),
+        ))?;
+    }
+    //final-step p5/p50/p95 marked and labelled at the right edge, so exported PNGs carry the numbers
+    let mut terminal: Vec<f64> = paths.iter().filter_map(|p| p.last().copied()).filter(|p| p.is_finite()).collect();
+    if !terminal.is_empty() && !deterministic {
+        terminal.sort_by(|a, b| a.total_cmp(b));
+        let label_style = TextStyle::from(("Inter", 14).into_font())
+            .color(&RGBColor(208, 208, 208))
+            .pos(Pos::new(HPos::Right, VPos::Bottom));
+        for (name, q) in [("P5", 0.05), ("P50", 0.5), ("P95", 0.95)] {
+            let value = sorted_quantile(&terminal, q);
+            chart.draw_series(std::iter::once(Circle::new((max_steps, value), 4, CYAN.filled())))?;
+            chart.draw_series(std::iter::once(Text::new(
+                format!("{} {:.2}", name, value),
+                (max_steps, value),
+                label_style.clone(),
+            )))?;
+        }
+    }
+
+    // Draw reference line for Mean Reversion model
+    if model_type == "MeanReversion" && let Some(mean_price) = mu_long_term {
+        // Draw dashed horizontal line for long-term mean
+        chart.draw_series(DashedLineSeries::new(
+            vec![(0, mean_price), (max_steps, mean_price)],
+            10,
+            6,
+            RED.mix(0.8).stroke_width(2),
+        ))?
+            .label("Long-term Mean (μ)")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+    }
+    if let Some(realized) = realized {
+        chart.draw_series(LineSeries::new(
+            realized.iter().copied().enumerate(),
+            RGBColor(255, 85, 85).stroke_width(3),
+        ))?
+            .label("Realized")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RGBColor(255, 85, 85).stroke_width(3)));
+    }
+    // Configure legend if a reference line or the realized path exists
+    if (model_type == "MeanReversion" && mu_long_term.is_some()) || realized.is_some() {
+        chart.configure_series_labels()
+            .background_style(RGBColor(30, 30, 46).mix(0.8))
+            .border_style(RGBColor(208, 208, 208))
+            .draw()?;
+    }
+
+    Ok(())
+}
+
+//past the cap, sample (i, i+1) pairs evenly across the index range so both halves
+//of antithetic pairs show up instead of just the first N paths
+fn displayed_paths(paths: &[Vec<f64>], max_displayed_paths: usize) -> Vec<&Vec<f64>> {
+    if paths.len() <= max_displayed_paths {
+        paths.iter().collect()
+    } else {
+        let pairs = paths.len().div_ceil(2);
+        let wanted = max_displayed_paths.div_ceil(2);
+        (0..wanted)
+            .flat_map(|j| paths.iter().skip(2 * (j * pairs / wanted)).take(2))
+            .collect()
+    }
+}
+
+//several labelled ensembles on shared axes, each in its own colour with a bold mean line and a
+//legend entry. The price range spans every set so none is clipped, and each set draws at most
+//max_displayed_paths sample lines
+pub fn plot_price_paths_overlay(sets: &[(&str, &[Vec<f64>], RGBColor)], max_displayed_paths: usize, chart: ChartConfig) -> Result<ChartBuffer> {
+    let mut buf = vec![0; (chart.width * chart.height * 3) as usize];
+    let backend = BitMapBackend::<RGBPixel>::with_buffer_and_format(
+        &mut buf, (chart.width, chart.height))?;
+    {
+        let root = backend.into_drawing_area();
+        root.fill(&RGBColor(30, 30, 46))?;
+
+        let prices = || sets.iter().flat_map(|(_, paths, _)| paths.iter().flatten().copied()).filter(|p| p.is_finite());
+        let max_steps = sets.iter().flat_map(|(_, paths, _)| paths.iter().map(|p| p.len())).max().unwrap_or(0);
+        if max_steps == 0 {
+            root.draw(&EmptyElement::at((0,0)))?;
+            return Ok((vec![0; (chart.width * chart.height * 3) as usize], chart.width, chart.height));
+        }
+        let max_steps = (max_steps - 1).max(1);
+
+        //same padding as the single-ensemble chart
+        let mut min_price = prices().fold(f64::INFINITY, f64::min) * 0.95;
+        let mut max_price = prices().fold(f64::NEG_INFINITY, f64::max);
+        if (max_price * 1.05).is_finite() {
+            max_price *= 1.05;
+        }
+        if max_price <= min_price {
+            min_price -= 0.5;
+            max_price += 0.5;
+        }
+
+        let mut chart_ctx = ChartBuilder::on(&root)
+            .caption("Scenario Overlay", ("Inter", 30, &RGBColor(208, 208, 208)))
+            .margin(10)
+            .x_label_area_size(40)
             .y_label_area_size(60)
             .build_cartesian_2d(0..max_steps, min_price..max_price)?;
 
-        chart
+        chart_ctx
             .configure_mesh()
-            .axis_style(&RGBColor(208, 208, 208))
+            .axis_style(RGBColor(208, 208, 208))
             .label_style(("Inter", 15, &RGBColor(208, 208, 208)))
             .draw()?;
 
-        for path in paths.iter().take(50) {
-            chart.draw_series(LineSeries::new(
-                path.iter().enumerate().map(|(i, &p)| (i, p)),
-                &YELLOW.mix(0.3),
-            ))?;
-        }
-        // Draw reference line for Mean Reversion model
-        if model_type == "MeanReversion" {
-        if let Some(mean_price) = mu_long_term {
-        // Draw horizontal line for long-term mean
-            chart.draw_series(LineSeries::new(
-                vec![(0, mean_price), (max_steps, mean_price)],
-                &RED.mix(0.8),
-            ))?
-                .label("Long-term Mean (μ)")
-                .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RED));
+        for &(label, paths, color) in sets {
+            let displayed = displayed_paths(paths, max_displayed_paths);
+            let opacity = (1.0 / displayed.len().clamp(1, 200) as f64).max(0.05);
+            for path in displayed {
+                chart_ctx.draw_series(LineSeries::new(
+                    path.iter().enumerate().map(|(i, &p)| (i, p)),
+                    &color.mix(opacity),
+                ))?;
             }
+
+            let means: Vec<(usize, f64)> = (0..paths.iter().map(|p| p.len()).max().unwrap_or(0))
+                .map(|t| {
+                    let column: Vec<f64> = paths.iter().filter_map(|p| p.get(t).copied()).collect();
+                    (t, column.iter().sum::<f64>() / column.len() as f64)
+                })
+                .collect();
+            chart_ctx.draw_series(LineSeries::new(means, color.stroke_width(3)))?
+                .label(label)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(3)));
         }
-        // Configure legend if reference line exists
-        if model_type == "MeanReversion" && mu_long_term.is_some() {
-            chart.configure_series_labels()
-                .background_style(&RGBColor(30, 30, 46).mix(0.8))
-                .border_style(&RGBColor(208, 208, 208))
-                .draw()?;
+
+        chart_ctx.configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .label_font(("Inter", 15, &RGBColor(208, 208, 208)))
+            .background_style(RGBColor(30, 30, 46).mix(0.8))
+            .border_style(RGBColor(208, 208, 208))
+            .draw()?;
+    }
+
+    Ok((buf, chart.width, chart.height))
+}
+
+//per-step quantiles across all paths, one Vec per requested quantile. Steps only count
+//the paths long enough to reach them, so unequal lengths don't panic.
+pub fn compute_quantile_bands(paths: &[Vec<f64>], quantiles: &[f64]) -> Vec<Vec<f64>> {
+    //no paths means no bands at all rather than one empty band per quantile
+    if paths.is_empty() {
+        return Vec::new();
+    }
+    let steps = paths.iter().map(|p| p.len()).max().unwrap_or(0);
+    let mut bands = vec![Vec::with_capacity(steps); quantiles.len()];
+
+    for t in 0..steps {
+        let mut column: Vec<f64> = paths.iter().filter_map(|p| p.get(t).copied()).collect();
+        column.sort_by(|a, b| a.total_cmp(b));
+        for (band, &q) in bands.iter_mut().zip(quantiles) {
+            band.push(sorted_quantile(&column, q));
         }
     }
+    bands
+}
+
+//linear interpolation between closest ranks of an ascending, non-empty slice
+fn sorted_quantile(sorted: &[f64], q: f64) -> f64 {
+    let pos = q * (sorted.len() - 1) as f64;
+    let lo = pos.floor() as usize;
+    let hi = pos.ceil() as usize;
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+}
 
-    Ok((buf, CHART_WIDTH, CHART_HEIGHT))
+//counts per bin over [min_val, max_val], the max itself in the last bin. Values past either end
+//go into the edge bin when clamp is set and are left out otherwise. Also returns how many
+//values were outside
+fn bin_counts(data: &[f64], min_val: f64, max_val: f64, num_bins: usize, clamp: bool) -> (Vec<usize>, usize) {
+    let bin_width = (max_val - min_val) / num_bins as f64;
+    let mut bins = vec![0; num_bins];
+    let mut outside = 0;
+    for &val in data {
+        if !(min_val..=max_val).contains(&val) {
+            outside += 1;
+            if !clamp {
+                continue;
+            }
+        }
+        //negative offsets saturate to 0 in the cast, so low values land in the first bin
+        let bin = ((val - min_val) / bin_width).floor() as usize;
+        bins[bin.min(num_bins - 1)] += 1;
+    }
+    (bins, outside)
 }
 
-pub fn plot_histogram(data: &[f64], num_bins: usize) -> Result<(Vec<u8>, u32, u32)> {
-    let mut buf = vec![0; (CHART_WIDTH * CHART_HEIGHT * 3) as usize];
+//var_threshold is the terminal price at the VaR quantile, marked alongside the mean and median
+pub fn plot_histogram(data: &[f64], bins: HistogramBins, overlay_density: bool, var_threshold: Option<f64>, chart: ChartConfig) -> Result<ChartBuffer> {
+    let mut buf = vec![0; (chart.width * chart.height * 3) as usize];
     let backend = BitMapBackend::<RGBPixel>::with_buffer_and_format(
         &mut buf,
-        (CHART_WIDTH, CHART_HEIGHT),
+        (chart.width, chart.height),
     )?;
 
     {
@@ -97,33 +394,67 @@ pub fn plot_histogram(data: &[f64], num_bins: usize) -> Result<(Vec<u8>, u32, u3
 
         if data.is_empty() {
             root.draw(&EmptyElement::at((0, 0)))?;
-            return Ok((vec![0; (CHART_WIDTH * CHART_HEIGHT * 3) as usize], CHART_WIDTH, CHART_HEIGHT));
+            return Ok((vec![0; (chart.width * chart.height * 3) as usize], chart.width, chart.height));
         }
 
-        let min_val = *data
-            .iter()
-            .min_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap();
-        let max_val = *data
-            .iter()
-            .max_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap();
+        //total_cmp never panics, simulate already drops non-finite terminal prices
+        let min_val = *data.iter().min_by(|a, b| a.total_cmp(b)).unwrap();
+        let max_val = *data.iter().max_by(|a, b| a.total_cmp(b)).unwrap();
+
+        //a zero range (sigma 0, one unique price) would give zero-width bins and NaN indices.
+        //Widen it by 1% either side with an odd bin count, so the value fills the middle bin
+        let fixed_range = bins.range.filter(|(lo, hi)| lo.is_finite() && hi.is_finite() && hi > lo);
+        let degenerate = fixed_range.is_none() && max_val <= min_val;
+        let (min_val, max_val, num_bins) = if let Some((lo, hi)) = fixed_range {
+            (lo, hi, bins.count.max(1))
+        } else if degenerate {
+            let half_width = (min_val.abs() * 0.01).max(0.5);
+            (min_val - half_width, max_val + half_width, bins.count.max(1) | 1)
+        } else {
+            (min_val, max_val, bins.count.max(1))
+        };
 
         let bin_width = (max_val - min_val) / num_bins as f64;
-        let mut bins = vec![0; num_bins];
-        for &val in data {
-            let bin = ((val - min_val) / bin_width).floor() as usize;
-            let bin_idx = (bin).min(num_bins - 1); 
-            bins[bin_idx] += 1;
-        }
+        let (bins, outside) = bin_counts(data, min_val, max_val, num_bins, bins.clamp);
+        let caption = if degenerate {
+            format!("Terminal Price Distribution (all paths at {:.4})", data[0])
+        } else if outside > 0 {
+            format!("Terminal Price Distribution ({} outside range)", outside)
+        } else {
+            "Terminal Price Distribution".to_string()
+        };
         
         let max_count = *bins.iter().max().unwrap_or(&1) as u32;
-        
+
+        //fitted normal pdf scaled to counts so it lines up with the bars
+        let density_curve: Vec<(f64, f64)> = if overlay_density {
+            let n = data.len() as f64;
+            let mean = data.iter().sum::<f64>() / n;
+            let std_dev = (data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+            if std_dev > 0.0 {
+                let scale = bin_width * n / (std_dev * (2.0 * std::f64::consts::PI).sqrt());
+                (0..=200)
+                    .map(|i| {
+                        let x = min_val + (max_val - min_val) * i as f64 / 200.0;
+                        (x, scale * (-0.5 * ((x - mean) / std_dev).powi(2)).exp())
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            }
+        } else {
+            Vec::new()
+        };
+        let y_max = density_curve
+            .iter()
+            .map(|&(_, y)| y)
+            .fold(max_count as f64, f64::max);
+
         let x_spec = (min_val..max_val).step(bin_width);
         
         let mut chart = ChartBuilder::on(&root)
             .caption(
-                "Terminal Price Distribution",
+                caption,
                 ("Inter", 30, &RGBColor(208, 208, 208)),
             )
             .margin(10)
@@ -131,7 +462,7 @@ pub fn plot_histogram(data: &[f64], num_bins: usize) -> Result<(Vec<u8>, u32, u3
             .y_label_area_size(60)
             .build_cartesian_2d(
                 x_spec, 
-                0..max_count, 
+                0.0..y_max, 
             )?;
         
         chart.draw_series(
@@ -139,45 +470,387 @@ pub fn plot_histogram(data: &[f64], num_bins: usize) -> Result<(Vec<u8>, u32, u3
                 let x_start = min_val + i as f64 * bin_width;
                 let x_end = x_start + bin_width;
                 let mut rect = Rectangle::new(
-                    [(x_start, 0), (x_end, count)],
+                    [(x_start, 0.0), (x_end, count as f64)],
                     GREEN.mix(0.5).filled(),
                 );
                 rect.set_margin(0, 0, 1, 1);
                 rect
             })
         )?;
+
+        if !density_curve.is_empty() {
+            chart.draw_series(LineSeries::new(density_curve, RED.stroke_width(2)))?;
+        }
+
+        //labelled vertical markers, stacked at different heights so close values stay readable
+        let mut sorted = data.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let mean = data.iter().sum::<f64>() / data.len() as f64;
+        let markers = [
+            ("Mean", Some(mean), WHITE),
+            ("Median", Some(sorted_quantile(&sorted, 0.5)), CYAN),
+            ("VaR", var_threshold, RGBColor(255, 85, 85)),
+        ];
+        for (i, (name, value, color)) in markers.into_iter().enumerate() {
+            let Some(x) = value.filter(|x| x.is_finite() && (min_val..=max_val).contains(x)) else {
+                continue;
+            };
+            chart.draw_series(DashedLineSeries::new(
+                vec![(x, 0.0), (x, y_max)],
+                8,
+                4,
+                color.stroke_width(2),
+            ))?;
+            chart.draw_series(std::iter::once(Text::new(
+                format!(" {} {:.2}", name, x),
+                (x, y_max * (0.97 - 0.06 * i as f64)),
+                ("Inter", 14).into_font().color(&color),
+            )))?;
+        }
         
         chart
             .configure_mesh()
-            .axis_style(&RGBColor(208, 208, 208))
+            .axis_style(RGBColor(208, 208, 208))
             .label_style(("Inter", 15, &RGBColor(208, 208, 208)))
             .draw()?;
     }
 
-    Ok((buf, CHART_WIDTH, CHART_HEIGHT))
+    Ok((buf, chart.width, chart.height))
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use crate::{SimParams, core_sim::run_simulation};
+//Gaussian kernel density as a filled curve, smoother than the histogram for small samples and
+//better at showing the bimodality jumps and regime switches produce. Without a bandwidth it uses
+//Silverman's rule, 0.9 * min(sd, IQR / 1.34) * n^(-1/5)
+pub fn plot_kde(data: &[f64], bandwidth: Option<f64>, chart: ChartConfig) -> Result<ChartBuffer> {
+    let mut buf = vec![0; (chart.width * chart.height * 3) as usize];
+    let backend = BitMapBackend::<RGBPixel>::with_buffer_and_format(
+        &mut buf,
+        (chart.width, chart.height),
+    )?;
 
-//     #[test]
-//     fn test_gbm_reproducibility() {
-//         let params = SimParams {
-//             initial_price: 100.0,
-//             horizon: 30,
-//             num_paths: 10,
-//             mu: 0.0002,
-//             sigma: 0.015,
-//             seed: 12345,
-//             use_antithetic: false,
-//             dt: 1,
-//             model_type: "GBM".to_string().into(),
-//         };
-        
-//         let result1 = run_simulation(params.clone(), vec![]).unwrap();
-//         let result2 = run_simulation(params, vec![]).unwrap();
-        
-//         assert_eq!(result1.0.mean, result2.0.mean);
-//     }
-// }
\ No newline at end of file
+    {
+        let root = backend.into_drawing_area();
+        root.fill(&RGBColor(30, 30, 46))?;
+
+        if data.is_empty() {
+            root.draw(&EmptyElement::at((0, 0)))?;
+            return Ok((vec![0; (chart.width * chart.height * 3) as usize], chart.width, chart.height));
+        }
+
+        let mut sorted = data.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let n = sorted.len() as f64;
+        let quantile = |q: f64| sorted[((n - 1.0) * q).round() as usize];
+        let mean = sorted.iter().sum::<f64>() / n;
+        let std_dev = (sorted.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+        let iqr = quantile(0.75) - quantile(0.25);
+        //a zero IQR (mostly tied values) falls back to the sd, and a zero sd to a sliver of the level
+        let spread = if iqr > 0.0 { std_dev.min(iqr / 1.34) } else { std_dev };
+        let silverman = 0.9 * spread * n.powf(-0.2);
+        let h = match bandwidth {
+            Some(h) if h > 0.0 => h,
+            _ if silverman > 0.0 => silverman,
+            _ => (mean.abs() * 0.01).max(0.5) / 3.0,
+        };
+
+        let min_val = sorted[0] - 3.0 * h;
+        let max_val = sorted[sorted.len() - 1] + 3.0 * h;
+
+        //bin onto a fine grid first so the cost is grid x bins rather than grid x paths
+        const FINE_BINS: usize = 2048;
+        const GRID_POINTS: usize = 400;
+        let fine_width = (max_val - min_val) / FINE_BINS as f64;
+        let mut counts = vec![0.0; FINE_BINS];
+        for &val in &sorted {
+            let bin = ((val - min_val) / fine_width).floor() as usize;
+            counts[bin.min(FINE_BINS - 1)] += 1.0;
+        }
+        let norm = 1.0 / (n * h * (2.0 * std::f64::consts::PI).sqrt());
+        let curve: Vec<(f64, f64)> = (0..=GRID_POINTS)
+            .map(|i| {
+                let x = min_val + (max_val - min_val) * i as f64 / GRID_POINTS as f64;
+                let density = counts
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &c)| c > 0.0)
+                    .map(|(j, &c)| {
+                        let centre = min_val + (j as f64 + 0.5) * fine_width;
+                        c * (-0.5 * ((x - centre) / h).powi(2)).exp()
+                    })
+                    .sum::<f64>();
+                (x, density * norm)
+            })
+            .collect();
+        let y_max = curve.iter().map(|&(_, y)| y).fold(0.0, f64::max) * 1.05;
+
+        let mut chart = ChartBuilder::on(&root)
+            .caption(
+                format!("Terminal Price Density (bandwidth {:.4})", h),
+                ("Inter", 30, &RGBColor(208, 208, 208)),
+            )
+            .margin(10)
+            .x_label_area_size(40)
+            .y_label_area_size(60)
+            .build_cartesian_2d(min_val..max_val, 0.0..y_max)?;
+
+        chart.draw_series(
+            AreaSeries::new(curve, 0.0, GREEN.mix(0.3)).border_style(GREEN.stroke_width(2)),
+        )?;
+
+        chart
+            .configure_mesh()
+            .axis_style(RGBColor(208, 208, 208))
+            .label_style(("Inter", 15, &RGBColor(208, 208, 208)))
+            .draw()?;
+    }
+
+    Ok((buf, chart.width, chart.height))
+}
+
+//standardized sample quantiles against standard normal ones, with the y = x line a normal
+//sample would follow. Fat tails bend the ends away from it in an S, a hint that the Student-t or
+//jump models fit the returns better than GBM
+pub fn plot_qq(sample: &[f64], chart: ChartConfig) -> Result<ChartBuffer> {
+    let n = sample.len() as f64;
+    let mean = sample.iter().sum::<f64>() / n;
+    let std_dev = (sample.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+    if sample.len() < 2 || !std_dev.is_finite() || std_dev <= 0.0 {
+        anyhow::bail!("Q-Q plot needs at least two distinct finite returns");
+    }
+
+    let mut standardized: Vec<f64> = sample.iter().map(|x| (x - mean) / std_dev).collect();
+    standardized.sort_by(|a, b| a.total_cmp(b));
+    let std_normal = Normal::standard();
+    //(i + 0.5) / n plotting positions keep the end points off the infinite 0 and 1 quantiles
+    let points: Vec<(f64, f64)> = standardized
+        .iter()
+        .enumerate()
+        .map(|(i, &z)| (std_normal.inverse_cdf((i as f64 + 0.5) / n), z))
+        .collect();
+
+    //each axis fits its own data, a single crash day would otherwise squash the rest into the middle
+    let pad = |lo: f64, hi: f64| (lo - 0.05 * (hi - lo), hi + 0.05 * (hi - lo));
+    let (x_min, x_max) = pad(points[0].0, points[points.len() - 1].0);
+    let (y_min, y_max) = pad(points[0].1, points[points.len() - 1].1);
+
+    let mut buf = vec![0; (chart.width * chart.height * 3) as usize];
+    let backend = BitMapBackend::<RGBPixel>::with_buffer_and_format(
+        &mut buf,
+        (chart.width, chart.height),
+    )?;
+
+    {
+        let root = backend.into_drawing_area();
+        root.fill(&RGBColor(30, 30, 46))?;
+
+        let mut chart = ChartBuilder::on(&root)
+            .caption(
+                "Normal Q-Q Plot of Log Returns",
+                ("Inter", 30, &RGBColor(208, 208, 208)),
+            )
+            .margin(10)
+            .x_label_area_size(40)
+            .y_label_area_size(60)
+            .build_cartesian_2d(x_min..x_max, y_min..y_max)?;
+
+        chart
+            .configure_mesh()
+            .x_desc("Theoretical Normal Quantile")
+            .y_desc("Standardized Sample Quantile")
+            .axis_style(RGBColor(208, 208, 208))
+            .label_style(("Inter", 15, &RGBColor(208, 208, 208)))
+            .axis_desc_style(("Inter", 15, &RGBColor(208, 208, 208)))
+            .draw()?;
+
+        //y = x, kept inside the plotted box
+        let (line_lo, line_hi) = (x_min.max(y_min), x_max.min(y_max));
+        chart.draw_series(LineSeries::new(
+            vec![(line_lo, line_lo), (line_hi, line_hi)],
+            RED.stroke_width(2),
+        ))?;
+
+        chart.draw_series(
+            points.into_iter().map(|point| Circle::new(point, 2, GREEN.mix(0.7).filled())),
+        )?;
+    }
+
+    Ok((buf, chart.width, chart.height))
+}
+
+//running mean against path count (log x axis) with a 95% band of +-1.96 standard errors
+pub fn plot_convergence(trace: &[(usize, f64, f64)], chart: ChartConfig) -> Result<ChartBuffer> {
+    let mut buf = vec![0; (chart.width * chart.height * 3) as usize];
+    let backend = BitMapBackend::<RGBPixel>::with_buffer_and_format(
+        &mut buf,
+        (chart.width, chart.height),
+    )?;
+
+    {
+        let root = backend.into_drawing_area();
+        root.fill(&RGBColor(30, 30, 46))?;
+
+        if trace.is_empty() {
+            root.draw(&EmptyElement::at((0, 0)))?;
+            return Ok((vec![0; (chart.width * chart.height * 3) as usize], chart.width, chart.height));
+        }
+
+        let upper: Vec<(f64, f64)> = trace.iter().map(|&(n, mean, se)| (n as f64, mean + 1.96 * se)).collect();
+        let lower: Vec<(f64, f64)> = trace.iter().map(|&(n, mean, se)| (n as f64, mean - 1.96 * se)).collect();
+        let min_val = lower.iter().map(|&(_, y)| y).fold(f64::INFINITY, f64::min);
+        let max_val = upper.iter().map(|&(_, y)| y).fold(f64::NEG_INFINITY, f64::max);
+        //pad by a share of the spread, or of the level when the trace is flat
+        let padding = ((max_val - min_val) * 0.05).max(min_val.abs() * 1e-3).max(1e-9);
+        //log range needs an upper bound above 1 even for a single path
+        let max_paths = (trace[trace.len() - 1].0 as f64).max(10.0);
+
+        let mut chart = ChartBuilder::on(&root)
+            .caption(
+                "Convergence of the Mean",
+                ("Inter", 30, &RGBColor(208, 208, 208)),
+            )
+            .margin(10)
+            .x_label_area_size(40)
+            .y_label_area_size(60)
+            .build_cartesian_2d(
+                (1.0..max_paths).log_scale(),
+                (min_val - padding)..(max_val + padding),
+            )?;
+
+        chart
+            .configure_mesh()
+            .axis_style(RGBColor(208, 208, 208))
+            .label_style(("Inter", 15, &RGBColor(208, 208, 208)))
+            .x_desc("Paths")
+            .draw()?;
+
+        let outline: Vec<(f64, f64)> = upper.iter().copied().chain(lower.iter().copied().rev()).collect();
+        chart.draw_series(std::iter::once(Polygon::new(outline, CYAN.mix(0.3).filled())))?;
+        chart.draw_series(LineSeries::new(
+            trace.iter().map(|&(n, mean, _)| (n as f64, mean)),
+            YELLOW.stroke_width(2),
+        ))?;
+    }
+
+    Ok((buf, chart.width, chart.height))
+}
+
+//annualized rolling volatility per window end, with the full-sample value as a dashed reference
+pub fn plot_rolling_volatility(rolling: &[f64], full_sample: f64, window: usize, chart: ChartConfig) -> Result<ChartBuffer> {
+    let mut buf = vec![0; (chart.width * chart.height * 3) as usize];
+    let backend = BitMapBackend::<RGBPixel>::with_buffer_and_format(
+        &mut buf,
+        (chart.width, chart.height),
+    )?;
+
+    {
+        let root = backend.into_drawing_area();
+        root.fill(&RGBColor(30, 30, 46))?;
+
+        if rolling.is_empty() {
+            root.draw(&EmptyElement::at((0, 0)))?;
+            return Ok((vec![0; (chart.width * chart.height * 3) as usize], chart.width, chart.height));
+        }
+
+        let max_val = rolling.iter().copied().fold(full_sample, f64::max);
+        let max_val = if max_val > 0.0 { max_val * 1.1 } else { 1.0 };
+        //x is the index of the window's last return, so windows line up with the return series
+        let first = window.saturating_sub(1);
+        let last = (first + rolling.len()).max(first + 2);
+
+        let mut chart = ChartBuilder::on(&root)
+            .caption(
+                format!("Annualized Rolling Volatility ({}-bar window)", window),
+                ("Inter", 30, &RGBColor(208, 208, 208)),
+            )
+            .margin(10)
+            .x_label_area_size(40)
+            .y_label_area_size(60)
+            .build_cartesian_2d(first..last, 0.0..max_val)?;
+
+        chart
+            .configure_mesh()
+            .axis_style(RGBColor(208, 208, 208))
+            .label_style(("Inter", 15, &RGBColor(208, 208, 208)))
+            .x_desc("Return index")
+            .draw()?;
+
+        chart.draw_series(LineSeries::new(
+            rolling.iter().enumerate().map(|(i, &v)| (first + i, v)),
+            YELLOW.stroke_width(2),
+        ))?
+            .label("Rolling")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], YELLOW.stroke_width(2)));
+        chart.draw_series(DashedLineSeries::new(
+            vec![(first, full_sample), (last, full_sample)],
+            10,
+            6,
+            CYAN.stroke_width(2),
+        ))?
+            .label(format!("Full sample {:.2}%", full_sample * 100.0))
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], CYAN.stroke_width(2)));
+
+        chart.configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .label_font(("Inter", 15, &RGBColor(208, 208, 208)))
+            .background_style(RGBColor(30, 30, 46).mix(0.8))
+            .border_style(RGBColor(208, 208, 208))
+            .draw()?;
+    }
+
+    Ok((buf, chart.width, chart.height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_sim::MODEL_TYPES;
+
+    //a rendered chart has pixels other than the background fill
+    fn has_drawing(buffer: &ChartBuffer) -> bool {
+        let (buf, width, height) = buffer;
+        buf.len() == (width * height * 3) as usize && buf.chunks(3).any(|pixel| pixel != [30, 30, 46])
+    }
+
+    #[test]
+    fn price_paths_chart_draws_every_model() {
+        let paths: Vec<Vec<f64>> = (0..5).map(|i| (0..=20).map(|step| 100.0 + (i * step) as f64 * 0.5).collect()).collect();
+        let chart = ChartConfig { width: 320, height: 240 };
+        for model in MODEL_TYPES {
+            let mu_long_term = (model == "MeanReversion").then_some(105.0);
+            let buffer = plot_price_paths(&paths, model, mu_long_term, false, false, 50, false, None, chart).unwrap();
+            assert!(has_drawing(&buffer), "{} chart is blank", model);
+        }
+    }
+
+    #[test]
+    fn histogram_of_constant_data_renders_one_centered_bar() {
+        let data = [42.0; 100];
+        for overlay_density in [false, true] {
+            let buffer = plot_histogram(&data, HistogramBins::default(), overlay_density, Some(42.0), ChartConfig { width: 320, height: 240 }).unwrap();
+            assert!(has_drawing(&buffer));
+        }
+        //the widened range with an odd bin count, as plot_histogram picks it
+        let (counts, outside) = bin_counts(&data, 41.5, 42.5, 101, true);
+        assert_eq!(counts[50], data.len());
+        assert_eq!(outside, 0);
+    }
+
+    #[test]
+    fn fixed_range_bounds_land_in_the_edge_bins() {
+        //[0, 10] in 10 bins: 0 opens the first bin, 10 closes the last rather than starting an 11th
+        let (counts, outside) = bin_counts(&[0.0, 0.999, 1.0, 9.999, 10.0], 0.0, 10.0, 10, false);
+        assert_eq!(counts, [2, 1, 0, 0, 0, 0, 0, 0, 0, 2]);
+        assert_eq!(outside, 0);
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped_or_dropped() {
+        let data = [-5.0, -1e-9, 5.0, 10.0 + 1e-9, 1e6];
+        let (clamped, outside) = bin_counts(&data, 0.0, 10.0, 10, true);
+        assert_eq!(clamped, [2, 0, 0, 0, 0, 1, 0, 0, 0, 2]);
+        assert_eq!(outside, 4);
+        let (dropped, outside) = bin_counts(&data, 0.0, 10.0, 10, false);
+        assert_eq!(dropped, [0, 0, 0, 0, 0, 1, 0, 0, 0, 0]);
+        assert_eq!(outside, 4);
+    }
+}